@@ -0,0 +1,87 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::{page, titles::unescape};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// abstract dump, such as enwiki-latest-abstract.xml.gz
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// what the dump puts in front of every title
+    #[clap(long, default_value = "Wikipedia: ")]
+    title_prefix: String,
+}
+
+/// Writes a JSON record with the title, URL and abstract of every article in an abstract
+/// dump, leaving out those with an empty abstract. These dumps hold only each article's
+/// opening text, already cleaned, so reading one is much quicker than a full dump.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let (reader, progress) = page::open_dump(&args.dump)?;
+
+    let mut doc = Doc::default();
+    let (mut written, mut empty) = (0, 0);
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line == "<doc>" {
+            doc = Doc::default();
+        } else if line == "</doc>" {
+            progress.add_page(&doc.title);
+            if doc.text.is_empty() {
+                empty += 1;
+                continue;
+            }
+            let title = doc.title.strip_prefix(args.title_prefix.as_str());
+            let record = json!({
+                "title": title.unwrap_or(&doc.title),
+                "url": doc.url,
+                "abstract": doc.text,
+            });
+            writeln!(out, "{record}")?;
+            written += 1;
+        } else if let Some(title) = element(line, "title") {
+            doc.title = unescape(title);
+        } else if let Some(url) = element(line, "url") {
+            doc.url = unescape(url);
+        } else if let Some(text) = element(line, "abstract") {
+            doc.text = unescape(text);
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {written} abstracts, leaving out {empty} empty ones");
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Doc {
+    title: String,
+    url: String,
+    text: String,
+}
+
+/// The text of an element that opens and closes on one line. An empty element, written
+/// `<name />`, has none.
+fn element<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix('<')?.strip_prefix(name)?;
+    if rest.trim() == "/>" {
+        return Some("");
+    }
+    rest.strip_prefix('>')?
+        .strip_suffix('>')?
+        .strip_suffix(name)?
+        .strip_suffix("</")
+}
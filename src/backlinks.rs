@@ -0,0 +1,159 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::graph::{self, Graph};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// title whose backlinks to list
+    title: String,
+    /// rebuild the index even if it looks current
+    #[clap(long)]
+    rebuild: bool,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let index = index_path(&args.graph);
+    if args.rebuild || is_stale(&args.graph, &index)? {
+        build(&Graph::load(&args.graph)?, &index)?;
+    }
+
+    if let Some(sources) = lookup(&index, &graph::normalize(&args.title))? {
+        for source in sources {
+            println!("{source}");
+        }
+    }
+
+    Ok(())
+}
+
+/// The index lives next to the graph it was built from.
+fn index_path(graph: &str) -> PathBuf {
+    let mut path = PathBuf::from(graph).into_os_string();
+    path.push(".backlinks");
+    path.into()
+}
+
+fn is_stale(graph: &str, index: &Path) -> io::Result<bool> {
+    let index = match fs::metadata(index) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    Ok(index.modified()? < fs::metadata(graph)?.modified()?)
+}
+
+/// Writes one `target\tsource\tsource...` line per linked-to page, sorted by target so that
+/// lookups can binary search the file instead of loading it.
+pub fn build(graph: &Graph, path: &Path) -> io::Result<()> {
+    let mut entries: Vec<_> = graph
+        .backlinks()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, sources)| !sources.is_empty())
+        .map(|(id, sources)| {
+            let sources: Vec<_> = sources.into_iter().map(|id| graph.title(id)).collect();
+            (graph.title(id as u32), sources)
+        })
+        .collect();
+    entries.sort_unstable();
+
+    let mut out = File::create(path).map(BufWriter::new)?;
+    for (target, mut sources) in entries {
+        // A page linking to another more than once is listed once.
+        sources.sort_unstable();
+        sources.dedup();
+        write!(out, "{target}")?;
+        for source in sources {
+            write!(out, "\t{source}")?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+pub fn lookup(path: &Path, title: &str) -> io::Result<Option<Vec<String>>> {
+    let mut reader = File::open(path).map(BufReader::new)?;
+    let len = reader.get_ref().metadata()?.len();
+    let mut line = String::new();
+
+    let mut lo = 0;
+    let mut hi = len;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let start = line_after(&mut reader, mid, &mut line)?;
+        if start >= len {
+            hi = mid;
+            continue;
+        }
+
+        if key(&line) < title {
+            lo = start + line.len() as u64;
+        } else {
+            hi = mid;
+        }
+    }
+
+    reader.seek(SeekFrom::Start(lo))?;
+    line.clear();
+    reader.read_line(&mut line)?;
+    if key(&line) != title {
+        return Ok(None);
+    }
+
+    let sources = line.trim_end_matches('\n').split('\t').skip(1);
+    Ok(Some(sources.map(String::from).collect()))
+}
+
+/// Reads the first full line starting at or after `offset`, returning its start.
+fn line_after(reader: &mut BufReader<File>, offset: u64, line: &mut String) -> io::Result<u64> {
+    reader.seek(SeekFrom::Start(offset.saturating_sub(1)))?;
+    let mut start = offset;
+    if offset > 0 {
+        let mut skipped = Vec::new();
+        start = offset - 1 + reader.read_until(b'\n', &mut skipped)? as u64;
+    }
+
+    line.clear();
+    reader.read_line(line)?;
+    Ok(start)
+}
+
+fn key(line: &str) -> &str {
+    line.trim_end_matches('\n')
+        .split('\t')
+        .next()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_sources_by_target() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let links = dir.path().join("links.txt");
+        fs::write(
+            &links,
+            "B -> A\nC -> A\nA -> Zulu\nC -> Mid\nB -> Mid\nB -> Mid\nA -> Mid\n",
+        )?;
+        let index = dir.path().join("links.txt.backlinks");
+        build(&Graph::load(&links)?, &index)?;
+
+        let lookup = |title| lookup(&index, title).unwrap();
+        let titles = |titles: &[&str]| Some(titles.iter().map(|&title| title.to_owned()).collect());
+        assert_eq!(lookup("A"), titles(&["B", "C"]));
+        assert_eq!(lookup("Mid"), titles(&["A", "B", "C"]));
+        assert_eq!(lookup("Zulu"), titles(&["A"]));
+        for missing in ["", "0", "Lima", "Zz", "B"] {
+            assert_eq!(lookup(missing), None, "{missing:?}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use rand::{rngs::StdRng, seq::index, SeedableRng};
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// number of source pages to sample
+    #[clap(short, long, default_value_t = 100)]
+    samples: usize,
+    /// random seed for choosing sample pages
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// number of top-ranked titles to print
+    #[clap(short = 'n', long, default_value_t = 20)]
+    top: usize,
+    /// write every title's estimate to this file
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let samples = args.samples.min(graph.len());
+    let pivots: Vec<u32> = index::sample(&mut rng, graph.len(), samples)
+        .into_iter()
+        .map(|id| id as u32)
+        .collect();
+
+    let scale = graph.len() as f64 / samples.max(1) as f64;
+    let mut scores: Vec<_> = graph
+        .betweenness(&pivots)
+        .into_iter()
+        .enumerate()
+        .map(|(id, score)| (graph.title(id as u32), score * scale))
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+
+    for &(title, score) in scores.iter().take(args.top) {
+        println!("{score:.2}\t{title}");
+    }
+
+    if let Some(path) = &args.output {
+        let mut out = File::create(path).map(BufWriter::new)?;
+        for (title, score) in scores {
+            writeln!(out, "{title}\t{score}")?;
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// An on-disk store of fetched wikitext.
+///
+/// Revisions never change, so text is stored once per revision id. A separate entry per
+/// requested title records which revision that title last resolved to.
+#[derive(Debug)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct CachedPage {
+    pub title: String,
+    pub revid: u64,
+    pub text: String,
+}
+
+impl Cache {
+    /// Opens the cache for a given API endpoint under `dir`, or under the user's cache
+    /// directory if `dir` is `None`.
+    pub fn open(dir: Option<&Path>, api: &str) -> io::Result<Option<Self>> {
+        let dir = match dir {
+            Some(dir) => dir.to_owned(),
+            None => match default_dir() {
+                Some(dir) => dir,
+                None => return Ok(None),
+            },
+        };
+
+        let slug: String = api
+            .chars()
+            .map(|u| if u.is_ascii_alphanumeric() { u } else { '_' })
+            .collect();
+        let root = dir.join(slug);
+        fs::create_dir_all(root.join("titles"))?;
+        fs::create_dir_all(root.join("revisions"))?;
+        Ok(Some(Self { root }))
+    }
+
+    pub fn get(&self, title: &str) -> io::Result<Option<CachedPage>> {
+        let entry = match read_optional(&self.title_path(title))? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let mut lines = entry.lines();
+        let (requested, canonical, revid) = match (lines.next(), lines.next(), lines.next()) {
+            (Some(requested), Some(canonical), Some(revid)) => (requested, canonical, revid),
+            _ => return Ok(None),
+        };
+
+        // Entries are named by hash, so make sure this one is really ours.
+        let revid = match revid.parse() {
+            Ok(revid) if requested == title => revid,
+            _ => return Ok(None),
+        };
+
+        Ok(
+            read_optional(&self.revision_path(revid))?.map(|text| CachedPage {
+                title: canonical.into(),
+                revid,
+                text,
+            }),
+        )
+    }
+
+    pub fn put(&self, requested: &str, page: &CachedPage) -> io::Result<()> {
+        fs::write(self.revision_path(page.revid), &page.text)?;
+        fs::write(
+            self.title_path(requested),
+            format!("{requested}\n{}\n{}\n", page.title, page.revid),
+        )
+    }
+
+    fn title_path(&self, title: &str) -> PathBuf {
+        self.root
+            .join("titles")
+            .join(format!("{:016x}", fnv1a(title)))
+    }
+
+    fn revision_path(&self, revid: u64) -> PathBuf {
+        self.root.join("revisions").join(revid.to_string())
+    }
+}
+
+fn default_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(base.join("wiki-crawler"))
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A stable hash for naming files; std's hasher makes no promises across releases.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
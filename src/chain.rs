@@ -0,0 +1,101 @@
+use anyhow::{anyhow, bail};
+
+use crate::{
+    filter::{LinkExtractor, TextFilter},
+    graph::{normalize, Graph, Outcome},
+    live::{Client, ClientArgs},
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(group(clap::ArgGroup::new("source").required(true).args(&["map", "live"])))]
+pub struct Args {
+    /// title to start from
+    title: String,
+    /// first-link map produced by the extract command
+    #[clap(short, long)]
+    map: Option<String>,
+    /// fetch pages from the MediaWiki API instead of reading a map
+    #[clap(long)]
+    live: bool,
+    /// title at which to stop
+    #[clap(short, long, default_value = "Philosophy")]
+    target: String,
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let (path, outcome) = match &args.map {
+        Some(map) => map_chain(map, args)?,
+        None => live_chain(&Client::new(&args.client)?, args)?,
+    };
+
+    for title in &path {
+        println!("{title}");
+    }
+
+    let steps = path.len() - 1;
+    match outcome {
+        Outcome::Reached => println!("reached {} in {steps} steps", args.target),
+        Outcome::DeadEnd => println!("dead end after {steps} steps"),
+        Outcome::Loop(idx) => println!("loop after {steps} steps: returned to {}", path[idx]),
+    }
+
+    Ok(())
+}
+
+fn map_chain(map: &str, args: &Args) -> anyhow::Result<(Vec<String>, Outcome)> {
+    let graph = Graph::load(map)?;
+    let start = graph
+        .id(&args.title)
+        .ok_or_else(|| anyhow!("unknown title: {}", args.title))?;
+
+    // The target need not appear in the map; if it doesn't, the chain can only end in a dead
+    // end or a loop, which is still worth reporting.
+    let target = graph.id(&args.target).unwrap_or(u32::MAX);
+    let chain = graph.chain(start, target);
+    let path = chain
+        .path
+        .iter()
+        .map(|&id| graph.title(id).into())
+        .collect();
+    Ok((path, chain.outcome))
+}
+
+fn live_chain(client: &Client, args: &Args) -> anyhow::Result<(Vec<String>, Outcome)> {
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new();
+    let target = normalize(&args.target);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut title = args.title.clone();
+    loop {
+        let page = match client.fetch(&title)? {
+            Some(page) => page,
+            None if path.is_empty() => bail!("unknown title: {title}"),
+            None => {
+                path.push(title);
+                return Ok((path, Outcome::DeadEnd));
+            }
+        };
+
+        if let Some(idx) = path.iter().position(|seen| *seen == page.title) {
+            path.push(page.title);
+            return Ok((path, Outcome::Loop(idx)));
+        }
+
+        let reached = page.title == target;
+        let next = if page.title.ends_with("(disambiguation)") {
+            None
+        } else {
+            ex.extract(&tf.filter(&page.text)).map(String::from)
+        };
+        path.push(page.title);
+
+        match next {
+            _ if reached => return Ok((path, Outcome::Reached)),
+            Some(next) => title = next,
+            None => return Ok((path, Outcome::DeadEnd)),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// How far an extraction run had gotten: an offset into the decompressed dump that falls on a
+/// page boundary, and where each output stood by the time it was read.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Checkpoint {
+    pub offset: u64,
+    pub output: u64,
+    pub red_links: u64,
+}
+
+impl Checkpoint {
+    /// Checkpoints live alongside the output they describe.
+    pub fn path_for(output: &Path) -> PathBuf {
+        let mut path = output.as_os_str().to_owned();
+        path.push(".checkpoint");
+        path.into()
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read checkpoint {}", path.display()))?;
+
+        let mut checkpoint = Checkpoint::default();
+        for line in text.lines() {
+            let (key, value) = line
+                .split_once(' ')
+                .with_context(|| format!("malformed checkpoint line: {line}"))?;
+            let value = value
+                .parse()
+                .with_context(|| format!("malformed checkpoint line: {line}"))?;
+            match key {
+                "offset" => checkpoint.offset = value,
+                "output" => checkpoint.output = value,
+                "red_links" => checkpoint.red_links = value,
+                _ => anyhow::bail!("unknown checkpoint field: {key}"),
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a temporary file and renames it into place, so a crash midway
+    /// leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temp = path.with_extension("checkpoint.tmp");
+        let mut file = fs::File::create(&temp)?;
+        writeln!(file, "offset {}", self.offset)?;
+        writeln!(file, "output {}", self.output)?;
+        writeln!(file, "red_links {}", self.red_links)?;
+        file.sync_all()?;
+        fs::rename(temp, path)
+    }
+}
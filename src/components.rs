@@ -0,0 +1,59 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// consider only each page's first link
+    #[clap(long)]
+    first_link: bool,
+    /// find weakly rather than strongly connected components
+    #[clap(long)]
+    weak: bool,
+    /// list the members of the largest component
+    #[clap(long)]
+    members: bool,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let mut components = if args.weak {
+        graph.weakly_connected(args.first_link)
+    } else {
+        graph.strongly_connected(args.first_link)
+    };
+    components.sort_by_key(|component| Reverse(component.len()));
+
+    let mut sizes = BTreeMap::new();
+    for component in &components {
+        *sizes.entry(component.len()).or_insert(0usize) += 1;
+    }
+
+    println!("components: {}", components.len());
+    if let Some(giant) = components.first() {
+        println!(
+            "largest: {} pages ({:.2}%)",
+            giant.len(),
+            giant.len() as f64 / graph.len() as f64 * 100.0
+        );
+    }
+
+    println!("size\tcount");
+    for (size, count) in sizes.into_iter().rev() {
+        println!("{size}\t{count}");
+    }
+
+    if args.members {
+        if let Some(giant) = components.first() {
+            let mut members: Vec<_> = giant.iter().map(|&id| graph.title(id)).collect();
+            members.sort_unstable();
+            for title in members {
+                println!("{title}");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::graph::{titles_path, Graph};
+
+/// What a compressed graph file starts with, ahead of its node and edge counts.
+const MAGIC: &[u8; 4] = b"WCG1";
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph to compress, in any form the analysis commands read
+    graph: String,
+    /// file to write the compressed graph to, ending in `.cgraph`; its titles go alongside
+    #[clap(short, long)]
+    output: PathBuf,
+}
+
+/// Writes a link graph in a compact form that the analysis commands read in place of an
+/// edge list, with its titles alongside as for id pairs.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    if args.output.extension().is_none_or(|ext| ext != "cgraph") {
+        anyhow::bail!("compressed graphs are read by their .cgraph extension");
+    }
+    let graph = Graph::load(&args.graph)?;
+    let compressed = Compressed::from_graph(&graph);
+    compressed.save(&args.output)?;
+
+    let mut titles = File::create(titles_path(&args.output)).map(BufWriter::new)?;
+    for id in 0..graph.len() as u32 {
+        writeln!(titles, "{id}\t{}", graph.title(id))?;
+    }
+    titles.flush()?;
+
+    info!(
+        "compressed {} links between {} pages into {} bytes",
+        graph.link_count(),
+        graph.len(),
+        compressed.data.len()
+    );
+    Ok(())
+}
+
+/// A graph's links in the manner of WebGraph: each page's list of targets is sorted and
+/// written as gaps between neighbours, which are small for pages linking to ids near each
+/// other, in as few bytes as they take. The first link is kept in its place at the head of
+/// the list, since it means something of its own, but the order of the rest is not.
+///
+/// Lists are decoded as they are read, and each page's is found through an offset kept for it
+/// in memory.
+pub struct Compressed {
+    data: Vec<u8>,
+    /// Where each page's list starts in `data`, with the end of the last one after them.
+    offsets: Vec<usize>,
+    links: u64,
+}
+
+impl Compressed {
+    pub fn from_graph(graph: &Graph) -> Self {
+        let mut data = Vec::new();
+        let mut offsets = Vec::with_capacity(graph.len() + 1);
+        let mut sorted = Vec::new();
+        for id in 0..graph.len() as u32 {
+            offsets.push(data.len());
+            let links = graph.links(id);
+            write_varint(&mut data, links.len() as u64);
+            let Some((&first, rest)) = links.split_first() else {
+                continue;
+            };
+            write_varint(&mut data, zigzag(i64::from(first) - i64::from(id)));
+
+            sorted.clear();
+            sorted.extend_from_slice(rest);
+            sorted.sort_unstable();
+            let mut previous = None;
+            for &target in &sorted {
+                let gap = match previous {
+                    Some(previous) => u64::from(target - previous),
+                    None => zigzag(i64::from(target) - i64::from(first)),
+                };
+                write_varint(&mut data, gap);
+                previous = Some(target);
+            }
+        }
+        offsets.push(data.len());
+
+        Self {
+            data,
+            offsets,
+            links: graph.link_count() as u64,
+        }
+    }
+
+    /// Writes the graph as its magic number, its page and link counts, the length of each
+    /// page's list, and then the lists.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = File::create(path).map(BufWriter::new)?;
+        out.write_all(MAGIC)?;
+        out.write_all(&(self.len() as u32).to_le_bytes())?;
+        out.write_all(&self.links.to_le_bytes())?;
+
+        let mut lengths = Vec::new();
+        for pair in self.offsets.windows(2) {
+            write_varint(&mut lengths, (pair[1] - pair[0]) as u64);
+        }
+        out.write_all(&lengths)?;
+        out.write_all(&self.data)?;
+        out.flush()
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed compressed graph");
+
+        if bytes.get(..4) != Some(MAGIC) || bytes.len() < 16 {
+            return Err(invalid());
+        }
+        let pages = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        let links = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+
+        let mut at = 16;
+        let mut offsets = Vec::with_capacity(pages + 1);
+        let mut offset = 0;
+        for _ in 0..pages {
+            offsets.push(offset);
+            offset += read_varint(&bytes, &mut at).ok_or_else(invalid)? as usize;
+        }
+        offsets.push(offset);
+        if bytes.len() - at != offset {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            data: bytes.split_off(at),
+            offsets,
+            links,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// The pages a page links to, its first link first. `None` marks a list that runs past the
+    /// end of the page's bytes or out of the graph.
+    pub fn links(&self, id: u32) -> impl Iterator<Item = Option<u32>> + '_ {
+        let data = &self.data[self.offsets[id as usize]..self.offsets[id as usize + 1]];
+        let pages = self.len() as i64;
+        let mut at = 0;
+        let count = read_varint(data, &mut at).unwrap_or(0);
+        let (mut first, mut previous) = (None, None);
+        (0..count).map(move |_| {
+            let value = read_varint(data, &mut at)?;
+            let target = match (first, previous) {
+                (None, _) => i64::from(id) + unzigzag(value),
+                (Some(first), None) => i64::from(first) + unzigzag(value),
+                (Some(_), Some(previous)) => i64::from(previous) + value as i64,
+            };
+            let target = u32::try_from(target).ok().filter(|_| target < pages)?;
+            match first {
+                None => first = Some(target),
+                Some(_) => previous = Some(target),
+            }
+            Some(target)
+        })
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*at)?;
+        *at += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Folds a signed gap into an unsigned one, so that small gaps either way stay small.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
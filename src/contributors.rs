@@ -0,0 +1,65 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{csv, history::RevisionArgs, page};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to read revisions from; history dumps give every revision of each page
+    dump: String,
+    /// write the table to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    #[clap(flatten)]
+    revisions: RevisionArgs,
+}
+
+/// Writes a CSV row for every revision in a dump naming who made it. Registered users have a
+/// username and user id, anonymous editors an IP address, and suppressed contributors
+/// neither.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let filter = args.revisions.filter()?;
+
+    writeln!(out, "title,revision,timestamp,username,user_id,ip")?;
+    let (mut count, mut skipped) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        let title = csv::field(&page.title);
+        for revision in page.revisions() {
+            if !filter.keeps(revision) {
+                skipped += 1;
+                continue;
+            }
+            let contributor = revision.contributor.as_ref();
+            let username = contributor.and_then(|c| c.username.as_deref());
+            let user_id = contributor.and_then(|c| c.id);
+            let ip = contributor.and_then(|c| c.ip.as_deref());
+            writeln!(
+                out,
+                "{title},{},{},{},{},{}",
+                optional(revision.id),
+                revision.timestamp.as_deref().unwrap_or_default(),
+                csv::field(username.unwrap_or_default()),
+                optional(user_id),
+                ip.unwrap_or_default(),
+            )?;
+            count += 1;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {count} revisions, leaving out {skipped}");
+    Ok(())
+}
+
+/// An empty field for a missing number.
+fn optional(n: Option<u64>) -> String {
+    n.map(|n| n.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// largest chunk, in units
+    #[clap(long, default_value_t = 1000)]
+    max_size: usize,
+    /// units each chunk repeats from the end of the one before it
+    #[clap(long, default_value_t = 100)]
+    overlap: usize,
+    /// what chunk sizes are measured in
+    #[clap(long, arg_enum, default_value = "chars")]
+    unit: Unit,
+    /// add the language detected in each page, or null where it's unclear
+    #[clap(long)]
+    language: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum Unit {
+    /// characters
+    Chars,
+    /// whitespace-separated words, a rough stand-in for tokens
+    Words,
+}
+
+/// Writes one JSON record per chunk of cleaned article text, each with the page title and
+/// the headings of the section it comes from.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    if args.overlap >= args.max_size {
+        anyhow::bail!("overlap must be smaller than the chunk size");
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+
+    let mut records = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let text = plain.convert(text);
+        let language = args.language.then(|| plain::language(&text));
+        for section in plain::sections(&text) {
+            for (idx, chunk) in chunks(&section.text, args.max_size, args.overlap, args.unit)
+                .into_iter()
+                .enumerate()
+            {
+                let mut record = json!({
+                    "title": page.title,
+                    "section": section.path,
+                    "chunk": idx,
+                    "text": chunk,
+                });
+                if let Some(language) = language {
+                    record["language"] = json!(language);
+                }
+                writeln!(out, "{record}")?;
+                records += 1;
+            }
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {records} chunks");
+    Ok(())
+}
+
+/// Splits text into chunks of at most `max_size` units, breaking only between words, with
+/// each chunk after the first starting up to `overlap` units before the last one ended. A
+/// word too long to fit in any chunk gets one to itself.
+pub fn chunks(text: &str, max_size: usize, overlap: usize, unit: Unit) -> Vec<&str> {
+    // Where each word starts and ends, in bytes and then in characters, counted as they go
+    // so that sizing a run of words doesn't mean counting it again.
+    let (mut chars, mut counted) = (0, 0);
+    let words: Vec<(usize, usize, usize, usize)> = text
+        .split_whitespace()
+        .map(|word| {
+            let start = word.as_ptr() as usize - text.as_ptr() as usize;
+            chars += text[counted..start].chars().count();
+            let start_chars = chars;
+            chars += word.chars().count();
+            counted = start + word.len();
+            (start, counted, start_chars, chars)
+        })
+        .collect();
+
+    // The size of the text spanning words `first..=last`.
+    let size = |first: usize, last: usize| match unit {
+        Unit::Chars => words[last].3 - words[first].2,
+        Unit::Words => last - first + 1,
+    };
+
+    let mut chunks = Vec::new();
+    let mut first = 0;
+    while first < words.len() {
+        let mut last = first;
+        while last + 1 < words.len() && size(first, last + 1) <= max_size {
+            last += 1;
+        }
+        chunks.push(&text[words[first].0..words[last].1]);
+        if last + 1 == words.len() {
+            break;
+        }
+
+        // Back up from the next word as far as the overlap allows, but always move forward.
+        let mut next = last + 1;
+        while next > first + 1 && size(next - 1, last) <= overlap {
+            next -= 1;
+        }
+        first = next;
+    }
+    chunks
+}
@@ -0,0 +1,10 @@
+use std::borrow::Cow;
+
+/// Quotes a CSV field if it holds anything that would otherwise break the row.
+pub fn field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{
+    filter::{LinkExtractor, TextFilter},
+    page,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    path: String,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let tf = TextFilter::new();
+    let site = page::site_info(&args.path)?;
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+
+    let dead_ends = page::read_pages(&args.path)?
+        .filter(|page| !page.title.ends_with("(disambiguation)"))
+        .filter_map(|page| {
+            let reason = ex.dead_end(&tf.filter(page.text()?))?;
+            Some((page.title, reason))
+        });
+
+    for (title, reason) in dead_ends {
+        println!("{title}\t{}", reason.as_str());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::{page, plain::PlainText, terms::Lead};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to take definitions from
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Writes a JSON record pairing the first term set in bold in each article's first sentence
+/// with that sentence, which by convention defines it, along with any other bold terms as
+/// aliases. Articles with no bold term in their first sentence, and disambiguation pages,
+/// are left out.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+
+    let (mut written, mut skipped) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        if page.title.ends_with("(disambiguation)") {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+        let Some(Lead { sentence, terms }) = Lead::find(&plain, text) else {
+            skipped += 1;
+            continue;
+        };
+        let Some((term, aliases)) = terms.split_first() else {
+            skipped += 1;
+            continue;
+        };
+
+        let record = json!({
+            "title": page.title,
+            "term": term,
+            "aliases": aliases,
+            "definition": sentence,
+        });
+        writeln!(out, "{record}")?;
+        written += 1;
+    }
+    out.flush()?;
+
+    info!("wrote {written} definitions, leaving out {skipped} pages without one");
+    Ok(())
+}
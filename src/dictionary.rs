@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::graph::normalize;
+
+/// A title-to-id mapping kept in an embedded database on disk rather than in memory, so that
+/// numbering the titles of even the largest wikis takes a bounded amount of memory.
+///
+/// Ids are dense and handed out in the order titles are first seen, starting from zero.
+pub struct Dictionary {
+    ids: sled::Tree,
+    titles: sled::Tree,
+    next: u32,
+}
+
+impl Dictionary {
+    /// Opens the dictionary in `dir`, creating it if need be. `cache` is how many bytes of it
+    /// to keep in memory.
+    pub fn open(dir: impl AsRef<Path>, cache: u64) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let db = sled::Config::new()
+            .path(dir)
+            .cache_capacity(cache)
+            .open()
+            .with_context(|| format!("failed to open dictionary {}", dir.display()))?;
+        let ids = db.open_tree("ids")?;
+        let titles = db.open_tree("titles")?;
+
+        // Ids are stored big-endian, so the last key is the highest id handed out.
+        let next = match titles.last()? {
+            Some((key, _)) => decode(&key) + 1,
+            None => 0,
+        };
+        Ok(Self { ids, titles, next })
+    }
+
+    pub fn len(&self) -> u32 {
+        self.next
+    }
+
+    /// Looks up the id of a title, giving it the next one if it doesn't have one yet.
+    pub fn insert(&mut self, title: &str) -> anyhow::Result<u32> {
+        let title = normalize(title);
+        if let Some(id) = self.ids.get(&title)? {
+            return Ok(decode(&id));
+        }
+
+        let id = self.next;
+        self.ids.insert(title.as_str(), &id.to_be_bytes())?;
+        self.titles.insert(id.to_be_bytes(), title.as_str())?;
+        self.next += 1;
+        Ok(id)
+    }
+
+    /// Every title in id order.
+    pub fn titles(&self) -> impl Iterator<Item = anyhow::Result<(u32, String)>> + '_ {
+        self.titles.iter().map(|entry| {
+            let (id, title) = entry?;
+            Ok((decode(&id), String::from_utf8(title.to_vec())?))
+        })
+    }
+
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.ids.flush()?;
+        self.titles.flush()?;
+        Ok(())
+    }
+}
+
+fn decode(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().expect("ids are four bytes"))
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use tracing::info;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph from the older dump
+    old: String,
+    /// link graph from the newer dump
+    new: String,
+    /// report only first links changed, gained or lost
+    #[clap(long)]
+    first_links: bool,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let old = Graph::load(&args.old)?;
+    let new = Graph::load(&args.new)?;
+
+    let (mut changed, mut gained, mut lost) = (0, 0, 0);
+    for id in 0..old.len() as u32 {
+        let title = old.title(id);
+        // Pages gone from the newer graph are reported as removed, not as losing a link.
+        let Some(new_id) = new.id(title) else {
+            continue;
+        };
+        let before = old.first_link(id).map(|id| old.title(id));
+        let after = new.first_link(new_id).map(|id| new.title(id));
+
+        match (before, after) {
+            (Some(before), Some(after)) if before != after => {
+                println!("~ {title}: {before} => {after}");
+                changed += 1;
+            }
+            (None, Some(after)) => {
+                println!("+ {title}: => {after}");
+                gained += 1;
+            }
+            (Some(before), None) => {
+                println!("- {title}: {before} =>");
+                lost += 1;
+            }
+            _ => {}
+        }
+    }
+    let first_links = format!("{changed} first links changed, {gained} gained, {lost} lost");
+
+    if args.first_links {
+        info!("{first_links}");
+        return Ok(());
+    }
+
+    let old_nodes = nodes(&old);
+    let new_nodes = nodes(&new);
+    let old_edges = edges(&old);
+    let new_edges = edges(&new);
+
+    let mut added_nodes: Vec<_> = new_nodes.difference(&old_nodes).collect();
+    let mut removed_nodes: Vec<_> = old_nodes.difference(&new_nodes).collect();
+    let mut added_edges: Vec<_> = new_edges.difference(&old_edges).collect();
+    let mut removed_edges: Vec<_> = old_edges.difference(&new_edges).collect();
+    added_nodes.sort_unstable();
+    removed_nodes.sort_unstable();
+    added_edges.sort_unstable();
+    removed_edges.sort_unstable();
+
+    for title in &added_nodes {
+        println!("+ {title}");
+    }
+    for title in &removed_nodes {
+        println!("- {title}");
+    }
+    for (source, target) in &added_edges {
+        println!("+ {source} -> {target}");
+    }
+    for (source, target) in &removed_edges {
+        println!("- {source} -> {target}");
+    }
+
+    info!(
+        "{} pages added, {} removed; {} links added, {} removed; {first_links}",
+        added_nodes.len(),
+        removed_nodes.len(),
+        added_edges.len(),
+        removed_edges.len(),
+    );
+
+    Ok(())
+}
+
+fn nodes(graph: &Graph) -> HashSet<&str> {
+    (0..graph.len() as u32).map(|id| graph.title(id)).collect()
+}
+
+fn edges(graph: &Graph) -> HashSet<(&str, &str)> {
+    (0..graph.len() as u32)
+        .flat_map(|id| {
+            graph
+                .links(id)
+                .iter()
+                .map(move |&target| (graph.title(id), graph.title(target)))
+        })
+        .collect()
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use anyhow::anyhow;
+
+use tracing::info;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// title to measure distances from
+    seed: String,
+    /// write distances to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let seed = graph
+        .id(&args.seed)
+        .ok_or_else(|| anyhow!("unknown title: {}", args.seed))?;
+
+    let mut reachable: Vec<_> = graph
+        .distances(seed)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, dist)| Some((dist?, graph.title(id as u32))))
+        .collect();
+    reachable.sort_unstable();
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    for &(dist, title) in &reachable {
+        writeln!(out, "{title}\t{dist}")?;
+    }
+    out.flush()?;
+
+    let eccentricity = reachable.last().map_or(0, |&(dist, _)| dist);
+    info!(
+        "{} of {} pages reachable from {}; farthest at {eccentricity}",
+        reachable.len(),
+        graph.len(),
+        args.seed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+    corpus::Unit,
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// give each article's whole text rather than its lead section
+    #[clap(long)]
+    full: bool,
+    /// cut text down to at most this many units, between words
+    #[clap(long)]
+    max_size: Option<usize>,
+    /// what --max-size is measured in
+    #[clap(long, arg_enum, default_value = "chars")]
+    unit: Unit,
+}
+
+/// Writes an `id`, `title`, `text` JSON record for each article in a dump, one per line, as
+/// sentence-embedding pipelines take them in. The text is the article's cleaned lead section,
+/// or with `--full` all of its sections one after another, leaving out the headings.
+/// Pages outside the main namespace, redirects, disambiguation pages and articles with no text
+/// left once cleaned are left out.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+
+    let (mut written, mut truncated) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 || page.title.ends_with("(disambiguation)") {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let sections = plain::sections(&plain.convert(text));
+        let text = match args.full {
+            true => sections
+                .iter()
+                .map(|section| section.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            false => match sections.into_iter().next() {
+                Some(lead) if lead.path.is_empty() => lead.text,
+                _ => continue,
+            },
+        };
+        if text.is_empty() {
+            continue;
+        }
+        let kept = match args.max_size {
+            Some(max_size) => truncate(&text, max_size, args.unit),
+            None => &text,
+        };
+        if kept.len() < text.len() {
+            truncated += 1;
+        }
+
+        let record = json!({
+            "id": page.id,
+            "title": page.title,
+            "text": kept,
+        });
+        writeln!(out, "{record}")?;
+        written += 1;
+    }
+    out.flush()?;
+
+    info!("wrote {written} records, {truncated} of them cut down to size");
+    Ok(())
+}
+
+/// The longest run of whole words from the start of some text that fits in `max_size` units,
+/// or the first word alone if even that doesn't fit.
+fn truncate(text: &str, max_size: usize, unit: Unit) -> &str {
+    let (mut size, mut end) = (0, 0);
+    for word in text.split_whitespace() {
+        let word_end = word.as_ptr() as usize - text.as_ptr() as usize + word.len();
+        size += match unit {
+            Unit::Chars => text[end..word_end].chars().count(),
+            Unit::Words => 1,
+        };
+        if size > max_size && end > 0 {
+            break;
+        }
+        end = word_end;
+    }
+    &text[..end]
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{self, BufWriter, Write},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tracing::info;
+
+use crate::{
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to look for duplicates in
+    dump: String,
+    /// words per shingle
+    #[clap(long, default_value_t = 5)]
+    shingle: usize,
+    /// estimated share of shingles two pages must have in common to count as duplicates
+    #[clap(long, default_value_t = 0.8)]
+    threshold: f64,
+    /// hash functions in each page's signature; more give closer estimates
+    #[clap(long, default_value_t = 128)]
+    hashes: usize,
+    /// bands the signature is cut into when looking for candidates; more find pairs of lower
+    /// similarity, at the cost of checking more of them
+    #[clap(long, default_value_t = 16)]
+    bands: usize,
+    /// random seed for the hash functions
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// write clusters to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Finds clusters of near-duplicate articles by MinHash over word shingles of their cleaned
+/// text, writing `cluster<TAB>title` lines for every page that has a duplicate.
+///
+/// Pages that share a band of their signatures become candidates, and candidates whose
+/// signatures agree on at least `threshold` of their hashes are joined into one cluster, so a
+/// cluster can hold pages that are only similar by way of others.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    anyhow::ensure!(args.shingle > 0, "shingles need at least one word");
+    anyhow::ensure!(
+        args.bands > 0 && args.hashes.is_multiple_of(args.bands),
+        "the number of hashes must be a multiple of the number of bands"
+    );
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let minhash = MinHash::new(args.hashes, args.seed);
+    let plain = PlainText::new();
+    let mut titles = Vec::new();
+    let mut signatures = Vec::new();
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let text = plain.convert(text);
+        let words: Vec<String> = plain::words(&text).map(str::to_lowercase).collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        // Pages shorter than a shingle are one shingle of their own.
+        let size = args.shingle.min(words.len());
+        let shingles = words.windows(size).map(|shingle| {
+            let mut hasher = DefaultHasher::new();
+            shingle.hash(&mut hasher);
+            hasher.finish()
+        });
+        signatures.push(minhash.signature(shingles));
+        titles.push(page.title);
+    }
+
+    fn find(parents: &mut [u32], mut x: u32) -> u32 {
+        while parents[x as usize] != x {
+            let parent = parents[x as usize];
+            parents[x as usize] = parents[parent as usize];
+            x = parent;
+        }
+        x
+    }
+
+    let n = signatures.len();
+    let mut parents: Vec<u32> = (0..n as u32).collect();
+    let mut checked = HashSet::new();
+    let rows = args.hashes / args.bands;
+    for band in 0..args.bands {
+        let mut buckets: HashMap<&[u32], Vec<u32>> = HashMap::new();
+        for (id, signature) in signatures.iter().enumerate() {
+            let key = &signature[band * rows..(band + 1) * rows];
+            buckets.entry(key).or_default().push(id as u32);
+        }
+
+        for bucket in buckets.values().filter(|bucket| bucket.len() > 1) {
+            let first = bucket[0];
+            for &other in &bucket[1..] {
+                if !checked.insert((first, other)) {
+                    continue;
+                }
+                let similarity =
+                    similarity(&signatures[first as usize], &signatures[other as usize]);
+                if similarity < args.threshold {
+                    continue;
+                }
+                let (a, b) = (find(&mut parents, first), find(&mut parents, other));
+                if a != b {
+                    parents[a.max(b) as usize] = a.min(b);
+                }
+            }
+        }
+    }
+
+    // Clusters are numbered in the order their first page appears in the dump.
+    let mut clusters: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for id in 0..n as u32 {
+        clusters.entry(find(&mut parents, id)).or_default().push(id);
+    }
+    let clusters: Vec<_> = clusters
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .collect();
+
+    let mut duplicates = 0;
+    for (cluster, members) in clusters.iter().enumerate() {
+        for &id in members {
+            writeln!(out, "{cluster}\t{}", titles[id as usize])?;
+        }
+        duplicates += members.len();
+    }
+    out.flush()?;
+
+    info!(
+        "found {} clusters holding {duplicates} of {n} pages",
+        clusters.len()
+    );
+    Ok(())
+}
+
+/// A family of hash functions over shingle hashes, each an affine map with random odd
+/// multipliers.
+struct MinHash {
+    coefficients: Vec<(u64, u64)>,
+}
+
+impl MinHash {
+    fn new(hashes: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let coefficients = (0..hashes)
+            .map(|_| (rng.gen::<u64>() | 1, rng.gen()))
+            .collect();
+        Self { coefficients }
+    }
+
+    /// The least value of each hash function over a set of shingles.
+    fn signature(&self, shingles: impl Iterator<Item = u64>) -> Vec<u32> {
+        let mut signature = vec![u32::MAX; self.coefficients.len()];
+        for shingle in shingles {
+            for (min, &(a, b)) in signature.iter_mut().zip(&self.coefficients) {
+                let hash = (a.wrapping_mul(shingle).wrapping_add(b) >> 32) as u32;
+                *min = (*min).min(hash);
+            }
+        }
+        signature
+    }
+}
+
+/// The share of hash functions on which two signatures agree, which estimates the Jaccard
+/// similarity of their pages' shingles.
+fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let same = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    same as f64 / a.len() as f64
+}
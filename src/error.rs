@@ -0,0 +1,100 @@
+//! What can go wrong reading a dump and writing out what's found in it.
+
+use std::{io, path::PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A page that isn't well-formed, or isn't shaped like a page, left out of a run.
+    #[error("failed to parse {} at byte {offset}: {message}", describe(title))]
+    XmlParse {
+        /// Where the page begins in the decompressed dump.
+        offset: u64,
+        /// The page's title, if it could be made out.
+        title: Option<String>,
+        message: String,
+    },
+    /// A page with a revision whose text doesn't match the SHA-1 the dump gives for it, as
+    /// when a dump was damaged on disk or in decompression.
+    #[error(
+        "{} of page {title:?} at byte {offset} doesn't match its SHA-1",
+        describe_revision(revision)
+    )]
+    Checksum {
+        /// Where the page begins in the decompressed dump.
+        offset: u64,
+        title: String,
+        revision: Option<u64>,
+    },
+    /// A page with revision text shorter than the `bytes` the dump gives for it, as when a
+    /// dump was cut off or damaged.
+    #[error(
+        "page {title:?} at byte {offset} has {found} bytes of text where the dump says {expected}"
+    )]
+    TruncatedText {
+        /// Where the page begins in the decompressed dump.
+        offset: u64,
+        title: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An input that couldn't be opened or read at all.
+    #[error("failed to read {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// A filter that can't be applied as given.
+    #[error("bad filter: {0}")]
+    BadFilter(String),
+    /// A run ended at the first page it had to skip, as `--fail-fast` asks.
+    #[error("stopped at a skipped page, as --fail-fast asks")]
+    Stopped,
+    /// An output that couldn't be opened or written to.
+    #[error("output {output} failed")]
+    Sink {
+        output: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+fn describe(title: &Option<String>) -> String {
+    match title {
+        Some(title) => format!("page {title:?}"),
+        None => "page".to_owned(),
+    }
+}
+
+fn describe_revision(revision: &Option<u64>) -> String {
+    match revision {
+        Some(revision) => format!("revision {revision}"),
+        None => "a revision".to_owned(),
+    }
+}
+
+impl Error {
+    /// Wraps an error from reading `path` in an [`io::Error`] of the same kind, for code that
+    /// deals in those, so that it can later be told apart from other failures.
+    pub fn unreadable(path: impl Into<PathBuf>, source: io::Error) -> io::Error {
+        let kind = source.kind();
+        let path = path.into();
+        io::Error::new(kind, Error::Io { path, source })
+    }
+
+    /// Finds an [`Error`] among the causes of another error, including one carried inside
+    /// an [`io::Error`].
+    pub fn find<'a>(mut error: Option<&'a (dyn std::error::Error + 'static)>) -> Option<&'a Error> {
+        while let Some(e) = error {
+            if let Some(found) = e.downcast_ref::<Error>() {
+                return Some(found);
+            }
+            let inner = e.downcast_ref::<io::Error>().and_then(|e| e.get_ref());
+            if let Some(found) = inner.and_then(|inner| inner.downcast_ref::<Error>()) {
+                return Some(found);
+            }
+            error = e.source();
+        }
+        None
+    }
+}
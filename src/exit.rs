@@ -0,0 +1,104 @@
+//! Exit statuses, so that whatever runs a command can tell a clean run from one that finished
+//! with pages left out, and both from one that didn't finish.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use wiki_crawler::Error;
+
+/// Everything was read.
+pub const SUCCESS: i32 = 0;
+/// The command failed.
+pub const FAILURE: i32 = 1;
+/// The command finished, but skipped pages it couldn't read or parse.
+pub const PARTIAL: i32 = 2;
+/// An input couldn't be opened or read at all.
+pub const UNREADABLE: i32 = 3;
+/// The command stopped at the first page it had to skip, as asked by `--fail-fast`.
+pub const STOPPED: i32 = 4;
+/// The command finished, but a dump ended before its closing tag, so pages may be missing.
+pub const INCOMPLETE: i32 = 5;
+
+pub const HELP: &str = "\
+EXIT STATUS:
+    0    success
+    1    failure
+    2    finished, but some pages couldn't be read or parsed and were skipped
+    3    an input couldn't be opened or read
+    4    stopped at the first skipped page under --fail-fast
+    5    finished, but a dump ended early and may be missing pages";
+
+static SKIPPED: AtomicU64 = AtomicU64::new(0);
+static FAIL_FAST: AtomicBool = AtomicBool::new(false);
+static STOP: AtomicBool = AtomicBool::new(false);
+static INCOMPLETE_DUMP: AtomicBool = AtomicBool::new(false);
+static READ_ERROR: Mutex<Option<io::Error>> = Mutex::new(None);
+
+/// Makes the first skipped page end the run rather than being counted.
+pub fn set_fail_fast(fail_fast: bool) {
+    FAIL_FAST.store(fail_fast, Ordering::Relaxed);
+}
+
+/// Records a page left out of the run because it couldn't be read or parsed. Under
+/// `--fail-fast`, the run is to stop here, and [`stopped`] fails from then on.
+pub fn page_skipped() -> Result<(), Error> {
+    SKIPPED.fetch_add(1, Ordering::Relaxed);
+    if FAIL_FAST.load(Ordering::Relaxed) {
+        STOP.store(true, Ordering::Relaxed);
+        return Err(Error::Stopped);
+    }
+    Ok(())
+}
+
+/// Fails once a page has been skipped under `--fail-fast`, so that the run winds down, writing
+/// out what it has, and ends with an error.
+pub fn stopped() -> Result<(), Error> {
+    match STOP.load(Ordering::Relaxed) {
+        true => Err(Error::Stopped),
+        false => Ok(()),
+    }
+}
+
+pub fn skipped() -> u64 {
+    SKIPPED.load(Ordering::Relaxed)
+}
+
+/// Records an input that failed partway through being read, such as a damaged compressed
+/// block or a dropped connection, so that the run ends with the error once it has written out
+/// what it has. The first such failure is the one reported.
+pub fn read_failed(e: io::Error) {
+    READ_ERROR.lock().unwrap().get_or_insert(e);
+}
+
+/// Fails with the input that couldn't be read to the end, if one couldn't.
+pub fn read_error() -> io::Result<()> {
+    match READ_ERROR.lock().unwrap().take() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Records that a dump ended without its closing tag.
+pub fn dump_incomplete() {
+    INCOMPLETE_DUMP.store(true, Ordering::Relaxed);
+}
+
+pub fn incomplete() -> bool {
+    INCOMPLETE_DUMP.load(Ordering::Relaxed)
+}
+
+/// The status for a command that failed with `e`.
+pub fn code(e: &anyhow::Error) -> i32 {
+    match Error::find(Some(e.as_ref())) {
+        Some(Error::Stopped) => STOPPED,
+        // Whatever else went wrong on the way out, the run was stopped first.
+        _ if stopped().is_err() => STOPPED,
+        Some(Error::Io { .. }) => UNREADABLE,
+        _ => FAILURE,
+    }
+}
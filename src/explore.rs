@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    filter::{LinkExtractor, TextFilter, Verdict},
+    graph::normalize,
+    live::{Client, ClientArgs},
+    page,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(group(clap::ArgGroup::new("source").required(true).args(&["dump", "live"])))]
+pub struct Args {
+    /// dump to load pages from; every page is held in memory, so keep it small
+    #[clap(short, long)]
+    dump: Option<String>,
+    /// fetch pages from the MediaWiki API
+    #[clap(long)]
+    live: bool,
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+enum Source {
+    /// Page text and redirect targets by normalized title.
+    Dump(HashMap<String, (String, Result<String, String>)>),
+    Live(Client),
+}
+
+impl Source {
+    /// Finds a page's canonical title and raw text, following a redirect if need be.
+    fn fetch(&self, title: &str) -> anyhow::Result<Option<(String, String)>> {
+        match self {
+            Source::Dump(pages) => {
+                let mut key = normalize(title);
+                for _ in 0..2 {
+                    match pages.get(&key) {
+                        Some((title, Ok(text))) => return Ok(Some((title.clone(), text.clone()))),
+                        Some((_, Err(target))) => key = normalize(target),
+                        None => break,
+                    }
+                }
+                Ok(None)
+            }
+            Source::Live(client) => Ok(client.fetch(title)?.map(|page| (page.title, page.text))),
+        }
+    }
+}
+
+struct Current {
+    title: String,
+    raw: String,
+    filtered: String,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let source = match &args.dump {
+        Some(path) => Source::Dump(
+            page::read_pages(path)?
+                .filter_map(|page| {
+                    let text = match page.redirect() {
+                        Some(target) => Err(target.to_owned()),
+                        None => Ok(page.text()?.to_owned()),
+                    };
+                    Some((normalize(&page.title), (page.title, text)))
+                })
+                .collect(),
+        ),
+        None => Source::Live(Client::new(&args.client)?),
+    };
+
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new();
+    let mut history: Vec<Current> = Vec::new();
+
+    println!("type a title to begin, or 'help' for commands");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let (command, rest) = match line.trim().split_once(' ') {
+            Some((command, rest)) => (command, rest.trim()),
+            None => (line.trim(), ""),
+        };
+
+        match command {
+            "" => continue,
+            "quit" | "q" => break,
+            "help" | "?" => print_help(),
+            "back" | "b" => {
+                history.pop();
+                match history.last() {
+                    Some(current) => show(&ex, current),
+                    None => println!("nothing to go back to"),
+                }
+            }
+            "path" | "p" => {
+                let titles: Vec<_> = history.iter().map(|page| page.title.as_str()).collect();
+                println!("{}", titles.join(" -> "));
+            }
+            "why" | "w" => match history.last() {
+                Some(current) => explain(&ex, current, rest),
+                None => println!("no page loaded"),
+            },
+            "next" | "n" => {
+                let next = history
+                    .last()
+                    .and_then(|current| ex.extract(&current.filtered))
+                    .map(String::from);
+                match next {
+                    Some(next) => visit(&source, &tf, &ex, &mut history, &next)?,
+                    None => println!("no link to follow"),
+                }
+            }
+            "go" | "g" => visit(&source, &tf, &ex, &mut history, rest)?,
+            _ => visit(&source, &tf, &ex, &mut history, line.trim())?,
+        }
+    }
+
+    Ok(())
+}
+
+fn visit(
+    source: &Source,
+    tf: &TextFilter,
+    ex: &LinkExtractor,
+    history: &mut Vec<Current>,
+    title: &str,
+) -> anyhow::Result<()> {
+    if title.is_empty() {
+        println!("no title given");
+        return Ok(());
+    }
+
+    let (title, raw) = match source.fetch(title)? {
+        Some(page) => page,
+        None => {
+            println!("no such page: {title}");
+            return Ok(());
+        }
+    };
+
+    if let Some(idx) = history.iter().position(|page| page.title == title) {
+        println!("note: {title} was already visited at step {idx}");
+    }
+
+    let filtered = tf.filter(&raw);
+    history.push(Current {
+        title,
+        raw,
+        filtered,
+    });
+    show(ex, history.last().unwrap());
+    Ok(())
+}
+
+fn show(ex: &LinkExtractor, current: &Current) {
+    println!("{}", current.title);
+    match ex.extract(&current.filtered) {
+        Some(link) => {
+            println!("  first link: {link}");
+            if let Some(sentence) = sentence_containing(&current.filtered, link) {
+                println!("  in: {sentence}");
+            }
+        }
+        None => println!("  no usable link"),
+    }
+}
+
+fn explain(ex: &LinkExtractor, current: &Current, link: &str) {
+    let verdicts = ex.explain(&current.raw, &current.filtered);
+    if link.is_empty() {
+        for (link, verdict) in verdicts {
+            println!("  {link}: {}", verdict.describe());
+        }
+        return;
+    }
+
+    let wanted = normalize(link);
+    match verdicts
+        .iter()
+        .find(|(candidate, _)| normalize(candidate) == wanted)
+    {
+        Some((link, verdict)) => println!("  {link}: {}", verdict.describe()),
+        None => println!("  {link}: not linked from this page"),
+    }
+
+    if let Some((chosen, Verdict::Chosen)) = verdicts.iter().find(|(_, v)| *v == Verdict::Chosen) {
+        println!("  (first link is {chosen})");
+    }
+}
+
+/// Finds the sentence around a link's first appearance in prose.
+fn sentence_containing<'a>(text: &'a str, link: &str) -> Option<&'a str> {
+    let needle = format!("[[{link}");
+    let line = text.lines().find(|line| line.contains(&needle))?;
+    let pos = line.find(&needle)?;
+    let start = line[..pos].rfind(". ").map_or(0, |idx| idx + 2);
+    let end = line[pos..]
+        .find(". ")
+        .map_or(line.len(), |idx| pos + idx + 1);
+    Some(line[start..end].trim())
+}
+
+fn print_help() {
+    println!("  TITLE, go TITLE   load a page");
+    println!("  next, n           follow the first link");
+    println!("  back, b           return to the previous page");
+    println!("  why, w [LINK]     explain how each link, or one link, was treated");
+    println!("  path, p           show the pages visited so far");
+    println!("  quit, q           leave");
+}
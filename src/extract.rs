@@ -0,0 +1,740 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, IsTerminal, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tracing::{info, warn};
+use wiki_crawler::{title::Rules, Error};
+
+use crate::{
+    checkpoint::Checkpoint,
+    csv, exit,
+    filter::{self, DeadEnd, LinkExtractor, TextFilter, Truncation},
+    memory::{ByteSize, MemoryArgs, LOW_MEMORY_MAX_TEXT},
+    metrics,
+    page::{self, Page, Pages},
+    progress::{Bar, Progress},
+    sink::{self, Edge, Output, Sink},
+    titles::{self, TitleSet},
+    tui::Dashboard,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    path: String,
+    /// emit every link on each page rather than only the first
+    #[clap(long)]
+    all_links: bool,
+    /// leave out links to pages that don't exist in the dump
+    #[clap(long)]
+    drop_red_links: bool,
+    /// send links to pages that don't exist in the dump here, as for --output
+    #[clap(long)]
+    red_links: Option<Output>,
+    /// emit only links to pages in the dump, resolving redirects to their targets
+    #[clap(long)]
+    validate: bool,
+    /// show a live dashboard on stderr
+    #[clap(long)]
+    tui: bool,
+    /// don't show a progress bar
+    #[clap(long)]
+    no_progress: bool,
+    /// serve Prometheus metrics on this address, e.g. 127.0.0.1:9100
+    #[clap(long)]
+    metrics_addr: Option<String>,
+    /// where to send links: -, a file (.gz and .bz2 are compressed), sqlite:PATH,
+    /// duckdb:PATH, postgres://..., tcp://HOST:PORT, or nats://HOST:PORT/SUBJECT; progress is
+    /// checkpointed alongside files
+    #[clap(short, long, default_value = "-")]
+    output: Output,
+    /// pick up an interrupted run from its last checkpoint
+    #[clap(long)]
+    resume: bool,
+    /// add to an sqlite: or postgres:// output rather than emptying it, passing over pages
+    /// it already holds from the same revision and replacing those it holds from another
+    #[clap(long, conflicts_with_all = &["resume", "dry-run"])]
+    upsert: bool,
+    /// pages between checkpoints
+    #[clap(long, default_value_t = 10_000)]
+    checkpoint_every: u64,
+    /// handle only pages starting at or after this byte of the decompressed dump
+    #[clap(long, default_value_t = 0)]
+    start_offset: u64,
+    /// handle only pages starting before this byte of the decompressed dump
+    #[clap(long)]
+    end_offset: Option<u64>,
+    /// emit article URLs in place of titles, or alongside them
+    #[clap(long, arg_enum)]
+    urls: Option<UrlMode>,
+    /// prefix for article URLs; defaults to the one in the dump's site information
+    #[clap(long)]
+    base_url: Option<String>,
+    /// write redirects that take more than one hop to land, or never do, to this file, as
+    /// tab-separated lines of the redirect, how it ended, and the titles along the way
+    #[clap(long)]
+    double_redirects: Option<String>,
+    /// write a line for each page no link was written for to this file: its title, where it
+    /// begins in the dump, and why, as one of redirect, disambiguation, empty, no-links,
+    /// skipped-only, red-links, timeout, parse-error, truncated and sha1-mismatch
+    #[clap(long)]
+    skipped: Option<String>,
+    /// write a CSV row to this file for each link in each page's wikitext, giving the page's
+    /// size before and after filtering, how many links it has, the time it took, and what
+    /// became of the link: chosen, later, namespace, filtered or not-prose
+    #[clap(long)]
+    diagnostics: Option<String>,
+    /// learn which pages exist from this page.sql dump rather than a pass over the dump
+    #[clap(long)]
+    page_table: Option<String>,
+    /// learn where redirects point from this redirect.sql dump, alongside --page-table
+    #[clap(long, requires = "page-table")]
+    redirect_table: Option<String>,
+    /// columns to write for each link, in place of `source -> target`: any of title, pageid,
+    /// ns, link, raw_link, link_display and offset, separated by commas; written tab-separated
+    #[clap(long, arg_enum, value_delimiter = ',', conflicts_with = "urls")]
+    fields: Vec<Field>,
+    /// keep what's inside this template rather than stripping it, for wikis that wrap
+    /// prose in templates; may be given more than once
+    #[clap(long)]
+    keep_template: Vec<String>,
+    /// keep at most this much of a revision's text, e.g. 4M; the rest is dropped unread
+    #[clap(long, default_value = "16M")]
+    max_revision_size: ByteSize,
+    /// skip any page that takes longer than this many seconds to handle, logging its title
+    /// and offset, rather than keeping what was filtered in time
+    #[clap(long)]
+    page_timeout: Option<f64>,
+    /// go through the dump without writing any output, then report how many pages and links
+    /// each step kept; a few sample links are written to stdout
+    #[clap(long, conflicts_with = "resume")]
+    dry_run: bool,
+    #[clap(flatten)]
+    memory: MemoryArgs,
+}
+
+/// Sample links written by a dry run.
+const SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+enum UrlMode {
+    /// replace titles with URLs
+    Only,
+    /// add URL columns after each edge
+    Also,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+enum Field {
+    /// the title of the page the link is on
+    Title,
+    /// the id of the page the link is on
+    Pageid,
+    /// the namespace of the page the link is on
+    Ns,
+    /// the page the link goes to, resolved to its canonical title under --validate
+    Link,
+    /// the page the link goes to as written in the wikitext
+    #[clap(name = "raw_link")]
+    RawLink,
+    /// the text the link displays
+    #[clap(name = "link_display")]
+    LinkDisplay,
+    /// where the page the link is on begins in the decompressed dump
+    Offset,
+}
+
+impl Args {
+    /// Reads the options for extracting links from one part of a dump split over several,
+    /// as given after the part and its output on the command line. Each part is run without
+    /// a progress display of its own, which the parts share instead.
+    pub(crate) fn for_part(part: &str, output: &str, options: &[String]) -> anyhow::Result<Self> {
+        use clap::{Args as _, FromArgMatches as _};
+
+        let command = Self::augment_args(clap::Command::new("extract").no_binary_name(true));
+        let given = [part, "--output", output]
+            .into_iter()
+            .chain(options.iter().map(String::as_str));
+        let mut args = Self::from_arg_matches(&command.try_get_matches_from(given)?)?;
+        if args.tui || args.metrics_addr.is_some() {
+            anyhow::bail!(
+                "parts share one progress bar, so --tui and --metrics-addr can't be given"
+            );
+        }
+        // Pages linked to might lie in any of the parts.
+        let needs_titles = args.validate || args.drop_red_links || args.red_links.is_some();
+        if (needs_titles || args.double_redirects.is_some()) && args.page_table.is_none() {
+            anyhow::bail!(
+                "no one part knows every page, so checking links against them needs --page-table"
+            );
+        }
+        args.no_progress = true;
+        Ok(args)
+    }
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    run_watched(args, |_| {})?;
+    Ok(())
+}
+
+/// Runs as [`run`] does, handing the dump's progress to `watch` once the pass that writes
+/// links begins, for callers showing progress of their own, and returning what each step
+/// kept.
+pub(crate) fn run_watched(
+    args: &Args,
+    watch: impl FnOnce(Arc<Progress>),
+) -> anyhow::Result<Stages> {
+    let sinks = || std::iter::once(&args.output).chain(&args.red_links);
+    if !args.fields.is_empty() && !sinks().all(Output::is_lines) {
+        anyhow::bail!("--fields needs outputs written as lines");
+    }
+    if args.upsert && !sinks().all(Output::keeps_pages) {
+        anyhow::bail!("--upsert needs outputs to sqlite: or postgres:// databases");
+    }
+    let max_text = match args.memory.low_memory() {
+        true => {
+            if let Some(output) = sinks().find(|output| !output.is_streaming()) {
+                anyhow::bail!("--low-memory can't write {output}, which builds up in memory");
+            }
+            if args.upsert {
+                anyhow::bail!("--low-memory can't --upsert, which holds every page's id");
+            }
+            args.max_revision_size.0.min(LOW_MEMORY_MAX_TEXT)
+        }
+        false => args.max_revision_size.0,
+    };
+
+    let timeout = args.page_timeout.map(Duration::from_secs_f64);
+    let mut tf = TextFilter::new().keeping_templates(&args.keep_template)?;
+    if let Some(timeout) = timeout {
+        tf = tf.time_budget(timeout);
+    }
+    let site = page::site_info(&args.path)?;
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+    let rules = Rules::new().with_namespaces(&site.namespaces);
+
+    // Spotting red links takes a first pass over the dump to learn which pages exist, unless
+    // the wiki's own tables say.
+    let needs_titles = args.validate || args.drop_red_links || args.red_links.is_some();
+    let titles = if needs_titles || args.double_redirects.is_some() {
+        let (budget, temp_dir) = (args.memory.budget(), args.memory.temp_dir());
+        Some(match &args.page_table {
+            Some(pages) => {
+                TitleSet::from_tables(pages, args.redirect_table.as_ref(), budget, temp_dir)?
+            }
+            None => TitleSet::collect(&args.path, budget, temp_dir)?,
+        })
+    } else {
+        None
+    };
+
+    if let (Some(path), Some(titles)) = (&args.double_redirects, &titles) {
+        write_double_redirects(path, titles)?;
+    }
+
+    // A dry run writes nothing worth resuming.
+    let checkpoint = args
+        .output
+        .local_path()
+        .filter(|_| !args.dry_run)
+        .map(|path| Checkpoint::path_for(path));
+    let resume = match &checkpoint {
+        Some(path) if args.resume => {
+            let resume = Checkpoint::load(path)?;
+            info!("resuming from byte {} of the dump", resume.offset);
+            resume
+        }
+        None if args.resume => anyhow::bail!("only output to a file or database can be resumed"),
+        _ => Checkpoint::default(),
+    };
+
+    let base = match (&args.urls, &args.base_url) {
+        (None, _) => None,
+        (Some(_), Some(base)) => Some(base.clone()),
+        (Some(_), None) => Some(site.base.clone().ok_or_else(|| {
+            anyhow::anyhow!("the dump has no base URL in its site information; pass --base-url")
+        })?),
+    };
+
+    let mut red_links = match &args.red_links {
+        Some(_) if args.dry_run => None,
+        Some(output) if args.upsert => Some(output.reopen()?),
+        Some(output) => Some(output.open(resume.red_links)?),
+        None => None,
+    };
+    let mut out = if args.dry_run {
+        sink::sample(SAMPLES)
+    } else if args.upsert {
+        args.output.reopen()?
+    } else {
+        args.output.open(resume.output)?
+    };
+
+    // Validation needs every candidate on hand in case the first few are red links.
+    let all_links = args.all_links || args.validate;
+    // A checkpoint always lies within the range the run was started with.
+    let start = resume.offset.max(args.start_offset);
+    let mut pages =
+        page::read_pages_range(&args.path, start, args.end_offset)?.max_text(Some(max_text));
+    // A resumed run adds to the reports the runs before it wrote.
+    let report = |path: &str| {
+        let resuming = resume.offset > 0;
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(resuming)
+            .truncate(!resuming)
+            .open(path)
+            .map(BufWriter::new)
+    };
+    let mut skipped = match &args.skipped {
+        Some(path) => {
+            pages = pages.keeping_failed();
+            Some(report(path)?)
+        }
+        None => None,
+    };
+    let mut diagnostics = match &args.diagnostics {
+        Some(path) => {
+            let mut out = report(path)?;
+            if out.get_ref().metadata()?.len() == 0 {
+                writeln!(
+                    out,
+                    "title,offset,raw_bytes,filtered_bytes,candidates,millis,link,verdict"
+                )?;
+            }
+            Some(out)
+        }
+        None => None,
+    };
+    if let Some(addr) = &args.metrics_addr {
+        metrics::spawn_exporter(addr, pages.progress())?;
+    }
+    watch(pages.progress());
+
+    // There is nothing to draw on when stderr isn't a terminal, so quietly go without.
+    let interactive = io::stderr().is_terminal();
+    let dashboard = if args.tui && interactive {
+        Dashboard::spawn(pages.progress())
+            .map_err(|e| warn!("dashboard unavailable: {e}"))
+            .ok()
+    } else {
+        None
+    };
+    let bar = (interactive && dashboard.is_none() && !args.no_progress)
+        .then(|| Bar::spawn(pages.progress()));
+
+    let emit = Emit {
+        urls: args.urls,
+        base: base.as_deref(),
+        fields: &args.fields,
+    };
+    let mut stages = Stages::default();
+    let mut since_checkpoint = 0;
+    // Filtered text goes into the same buffer for every page.
+    let mut filtered = String::new();
+    while let Some(page) = pages.next() {
+        let started = Instant::now();
+        let (links, truncated) = links_within(&tf, &ex, &page, all_links, &mut filtered);
+        let elapsed = started.elapsed();
+        let offset = pages.page_offset();
+        let mut timed_out = false;
+        let links = match timeout {
+            Some(timeout) if truncated == Some(Truncation::Time) || elapsed > timeout => {
+                warn!(title = %page.title, offset, "page skipped for taking longer than --page-timeout");
+                // Under --fail-fast, reading ends once this page is done with.
+                let _ = exit::page_skipped();
+                timed_out = true;
+                Vec::new()
+            }
+            _ => {
+                log_truncation(&page, truncated);
+                links
+            }
+        };
+        let found = links.len();
+        let emitted = stages.emitted;
+        stages.page(&page, links.len());
+
+        out.page(&page)?;
+        if let Some(red_links) = &mut red_links {
+            red_links.page(&page)?;
+        }
+        let mut seen = HashSet::new();
+        for Link { target, display } in links {
+            let raw = target.clone();
+            let resolved = titles.as_ref().map(|titles| titles.resolve(&target));
+            let target = match resolved.transpose()? {
+                Some(Some(canonical)) if args.validate => canonical,
+                Some(None) => {
+                    stages.red_links += 1;
+                    if let Some(red_links) = &mut red_links {
+                        emit.send(red_links, &page, offset, &target, &raw, &display)?;
+                    }
+                    if args.drop_red_links || args.validate {
+                        continue;
+                    }
+                    target
+                }
+                _ => target,
+            };
+
+            if !seen.insert(target.clone()) {
+                stages.duplicates += 1;
+                continue;
+            }
+
+            emit.send(&mut out, &page, offset, &target, &raw, &display)?;
+            stages.written(&rules, &raw, &target);
+            if args.validate && !args.all_links {
+                break;
+            }
+        }
+
+        if let Some(diagnostics) = &mut diagnostics {
+            write_diagnostics(diagnostics, &ex, &page, offset, &filtered, elapsed)?;
+        }
+        if let Some(skipped) = &mut skipped {
+            write_failed(skipped, &mut pages)?;
+            if stages.emitted == emitted {
+                let reason = match () {
+                    _ if page.redirect().is_some() => "redirect",
+                    _ if page.title.ends_with("(disambiguation)") => "disambiguation",
+                    _ if timed_out => "timeout",
+                    _ if found > 0 => "red-links",
+                    _ => ex.dead_end(&filtered).map_or("empty", DeadEnd::as_str),
+                };
+                writeln!(skipped, "{}\t{offset}\t{reason}", page.title)?;
+            }
+        }
+
+        since_checkpoint += 1;
+        if let Some(path) = &checkpoint {
+            if since_checkpoint >= args.checkpoint_every {
+                since_checkpoint = 0;
+                save_checkpoint(path, pages.offset(), &mut out, &mut red_links)?;
+            }
+        }
+    }
+
+    // A run stopped under --fail-fast can be resumed from where it stopped.
+    let stopped = exit::stopped();
+    if let (Err(_), Some(path)) = (&stopped, &checkpoint) {
+        save_checkpoint(path, pages.offset(), &mut out, &mut red_links)?;
+    }
+    out.finish()?;
+    if let Some(skipped) = &mut skipped {
+        write_failed(skipped, &mut pages)?;
+        skipped.flush()?;
+    }
+    if let Some(diagnostics) = &mut diagnostics {
+        diagnostics.flush()?;
+    }
+    if let Some(red_links) = &mut red_links {
+        red_links.finish()?;
+    }
+
+    if let Some(dashboard) = dashboard {
+        dashboard.finish()?;
+    }
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+
+    if args.dry_run {
+        stages.report();
+    }
+    stopped?;
+
+    // A finished run has nothing to resume.
+    if let Some(path) = checkpoint.filter(|path| path.exists()) {
+        fs::remove_file(path)?;
+    }
+
+    Ok(stages)
+}
+
+/// How links are written out.
+struct Emit<'a> {
+    urls: Option<UrlMode>,
+    base: Option<&'a str>,
+    fields: &'a [Field],
+}
+
+impl Emit<'_> {
+    fn send(
+        &self,
+        sink: &mut Box<dyn Sink>,
+        page: &Page,
+        offset: u64,
+        target: &str,
+        raw: &str,
+        display: &str,
+    ) -> anyhow::Result<()> {
+        let source = page.title.as_str();
+        let edge = Edge {
+            page: Some(page),
+            ..Edge::new(source, target)
+        };
+        if !self.fields.is_empty() {
+            let columns: Vec<String> = self
+                .fields
+                .iter()
+                .map(|field| match field {
+                    Field::Title => source.to_owned(),
+                    Field::Pageid => page.id.map(|id| id.to_string()).unwrap_or_default(),
+                    Field::Ns => page.ns.map(|ns| ns.to_string()).unwrap_or_default(),
+                    Field::Link => target.to_owned(),
+                    Field::RawLink => raw.to_owned(),
+                    Field::LinkDisplay => display.to_owned(),
+                    Field::Offset => offset.to_string(),
+                })
+                .collect();
+            let columns = columns.join("\t");
+            return sink.edge(Edge {
+                columns: Some(&columns),
+                ..edge
+            });
+        }
+
+        let (Some(mode), Some(base)) = (self.urls, self.base) else {
+            return sink.edge(edge);
+        };
+
+        let source_url = titles::article_url(base, source);
+        let target_url = titles::article_url(base, target);
+        let edge = match mode {
+            UrlMode::Only => Edge {
+                source: &source_url,
+                target: &target_url,
+                ..edge
+            },
+            UrlMode::Also => Edge {
+                urls: Some((&source_url, &target_url)),
+                ..edge
+            },
+        };
+        sink.edge(edge)
+    }
+}
+
+/// Records the pages that failed to parse or were found damaged since the last time, for
+/// --skipped.
+fn write_failed(out: &mut impl Write, pages: &mut Pages) -> anyhow::Result<()> {
+    for e in pages.take_failed() {
+        match e {
+            Error::XmlParse { offset, title, .. } => {
+                let title = title.unwrap_or_default();
+                writeln!(out, "{title}\t{offset}\tparse-error")?;
+            }
+            Error::Checksum { offset, title, .. } => {
+                writeln!(out, "{title}\t{offset}\tsha1-mismatch")?;
+            }
+            Error::TruncatedText { offset, title, .. } => {
+                writeln!(out, "{title}\t{offset}\ttruncated")?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Writes a page's rows for --diagnostics, one for each distinct link in its wikitext, or one
+/// with no link if it has none.
+fn write_diagnostics(
+    out: &mut impl Write,
+    ex: &LinkExtractor,
+    page: &Page,
+    offset: u64,
+    filtered: &str,
+    elapsed: Duration,
+) -> anyhow::Result<()> {
+    let raw = page.text().unwrap_or_default();
+    let verdicts = ex.explain(raw, filtered);
+    let row = format!(
+        "{},{offset},{},{},{},{:.3}",
+        csv::field(&page.title),
+        raw.len(),
+        filtered.len(),
+        verdicts.len(),
+        elapsed.as_secs_f64() * 1000.0,
+    );
+    if verdicts.is_empty() {
+        writeln!(out, "{row},,")?;
+    }
+    for (link, verdict) in verdicts {
+        writeln!(out, "{row},{},{}", csv::field(link), verdict.as_str())?;
+    }
+    Ok(())
+}
+
+/// Reports every redirect [`TitleSet::follow`] finds to be double.
+fn write_double_redirects(path: &str, titles: &TitleSet) -> anyhow::Result<()> {
+    let mut out = File::create(path).map(BufWriter::new)?;
+    let mut count = 0;
+    for title in titles.redirects() {
+        let chain = titles.follow(&title?)?;
+        if chain.is_double() {
+            let (first, rest) = chain.titles.split_first().unwrap();
+            writeln!(
+                out,
+                "{first}\t{}\t{}",
+                chain.ending.as_str(),
+                rest.join("\t")
+            )?;
+            count += 1;
+        }
+    }
+    out.flush()?;
+    info!("found {count} double redirects");
+    Ok(())
+}
+
+/// How many pages and links each step of a run kept, for a dry run to report.
+#[derive(Debug, Default)]
+pub(crate) struct Stages {
+    pub(crate) pages: u64,
+    redirects: u64,
+    disambiguation: u64,
+    without_links: u64,
+    links: u64,
+    red_links: u64,
+    duplicates: u64,
+    pub(crate) emitted: u64,
+    /// What the written links lead to.
+    articles: u64,
+    categories: u64,
+    files: u64,
+    other_namespaces: u64,
+    other_wikis: u64,
+}
+
+impl Stages {
+    fn page(&mut self, page: &Page, links: usize) {
+        self.pages += 1;
+        self.links += links as u64;
+        if page.redirect().is_some() {
+            self.redirects += 1;
+        } else if page.title.ends_with("(disambiguation)") {
+            self.disambiguation += 1;
+        } else if links == 0 {
+            self.without_links += 1;
+        }
+    }
+
+    /// Counts a written link by what it leads to, going by the target as extracted to tell
+    /// links to other wikis.
+    fn written(&mut self, rules: &Rules, raw: &str, target: &str) {
+        self.emitted += 1;
+        let count = if filter::is_interwiki(raw) {
+            &mut self.other_wikis
+        } else {
+            match rules.parse(target).namespace.as_deref() {
+                None => &mut self.articles,
+                Some("Category") => &mut self.categories,
+                Some("File" | "Media") => &mut self.files,
+                Some(_) => &mut self.other_namespaces,
+            }
+        };
+        *count += 1;
+    }
+
+    fn report(&self) {
+        info!("pages read: {}", self.pages);
+        info!("  redirects: {}", self.redirects);
+        info!("  disambiguation pages: {}", self.disambiguation);
+        info!("  pages without links: {}", self.without_links);
+        info!("links found: {}", self.links);
+        info!("  to missing pages: {}", self.red_links);
+        info!("  repeated on a page: {}", self.duplicates);
+        info!("links that would be written: {}", self.emitted);
+        info!("  to articles: {}", self.articles);
+        info!("  to categories: {}", self.categories);
+        info!("  to files: {}", self.files);
+        info!("  to other namespaces: {}", self.other_namespaces);
+        info!("  to other wikis: {}", self.other_wikis);
+    }
+}
+
+/// Records a checkpoint once both outputs have everything sent to them so far on disk.
+fn save_checkpoint(
+    path: &Path,
+    offset: u64,
+    out: &mut Box<dyn Sink>,
+    red_links: &mut Option<Box<dyn Sink>>,
+) -> anyhow::Result<()> {
+    let Some(output) = out.checkpoint()? else {
+        return Ok(());
+    };
+    let red_links = match red_links {
+        Some(red_links) => red_links.checkpoint()?.unwrap_or(0),
+        None => 0,
+    };
+
+    let checkpoint = Checkpoint {
+        offset,
+        output,
+        red_links,
+    };
+    Ok(checkpoint.save(path)?)
+}
+
+/// A link as extracted from a page, with the text it displays there.
+pub struct Link {
+    pub target: String,
+    pub display: String,
+}
+
+/// Extracts the first link or all links of a page. Redirects and disambiguation pages have
+/// none.
+pub fn page_links(tf: &TextFilter, ex: &LinkExtractor, page: &Page, all: bool) -> Vec<Link> {
+    let (links, truncated) = links_within(tf, ex, page, all, &mut String::new());
+    log_truncation(page, truncated);
+    links
+}
+
+fn log_truncation(page: &Page, truncated: Option<Truncation>) {
+    if let Some(truncated) = truncated {
+        warn!(title = %page.title, reason = truncated.as_str(), "page truncated while filtering");
+    }
+}
+
+/// Extracts links as for [`page_links`], saying rather than logging whether the page was cut
+/// short. The page's filtered text is left in `text`, which is emptied for pages that aren't
+/// looked at.
+fn links_within(
+    tf: &TextFilter,
+    ex: &LinkExtractor,
+    page: &Page,
+    all: bool,
+    text: &mut String,
+) -> (Vec<Link>, Option<Truncation>) {
+    text.clear();
+    if page.title.ends_with("(disambiguation)") {
+        return (Vec::new(), None);
+    }
+
+    let Some(raw) = page.text() else {
+        return (Vec::new(), None);
+    };
+    let truncated = tf.filter_into(text, raw);
+
+    let links = if all {
+        ex.extract_all_labeled(text)
+    } else {
+        ex.extract_labeled(text).into_iter().collect()
+    };
+    let links = links
+        .into_iter()
+        .map(|(target, display)| Link {
+            target: target.to_owned(),
+            display: display.to_owned(),
+        })
+        .collect();
+    (links, truncated)
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use regex::Regex;
+use tracing::info;
+
+use crate::{
+    csv,
+    filter::is_skipped,
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to measure
+    dump: String,
+    /// write the table to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Writes a CSV row of structural measurements for every article: words and sections of its
+/// cleaned text, and references, images, article links and templates in its wikitext.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+    let measure = Measure::new();
+
+    writeln!(
+        out,
+        "title,words,sections,references,images,links,templates"
+    )?;
+    let mut count = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let features = measure.features(&plain, text);
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            csv::field(&page.title),
+            features.words,
+            features.sections,
+            features.references,
+            features.images,
+            features.links,
+            features.templates,
+        )?;
+        count += 1;
+    }
+    out.flush()?;
+
+    info!("measured {count} pages");
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Features {
+    words: usize,
+    sections: usize,
+    references: usize,
+    images: usize,
+    links: usize,
+    templates: usize,
+}
+
+struct Measure {
+    reference: Regex,
+    link: Regex,
+}
+
+impl Measure {
+    fn new() -> Self {
+        Self {
+            // Self-closing tags reuse a named reference, which still counts as citing it.
+            reference: Regex::new(r"(?i)<ref\b[^>]*>").unwrap(),
+            link: Regex::new(r"\[\[([^|\]]*)").unwrap(),
+        }
+    }
+
+    fn features(&self, plain: &PlainText, text: &str) -> Features {
+        let converted = plain.convert(text);
+        let mut features = Features {
+            words: plain::words(&converted).count(),
+            sections: converted
+                .lines()
+                .filter(|line| plain::heading(line).is_some())
+                .count(),
+            references: self.reference.find_iter(text).count(),
+            // Parameters are written `{{{name}}}` and aren't templates, but they only turn
+            // up in the template namespace.
+            templates: text.matches("{{").count(),
+            ..Features::default()
+        };
+
+        for cx in self.link.captures_iter(text) {
+            let target = cx[1].trim_start();
+            let namespace = target
+                .split_once(':')
+                .map(|(prefix, _)| prefix.trim().to_lowercase());
+            if matches!(namespace.as_deref(), Some("file" | "image")) {
+                features.images += 1;
+            } else if !is_skipped(target) {
+                features.links += 1;
+            }
+        }
+        features
+    }
+}
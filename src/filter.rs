@@ -0,0 +1,389 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+
+use wiki_crawler::Error;
+
+use crate::{templates::Templates, wikitext};
+
+/// Text beyond this many bytes of a page is dropped before filtering.
+pub const MAX_TEXT: usize = 1 << 20;
+
+/// Filtering stops early once a page has taken this long.
+pub const TIME_BUDGET: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct TextFilter {
+    max_text: usize,
+    budget: Duration,
+    templates: Templates,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    /// The page was longer than the filter will look at.
+    Size,
+    /// Filtering ran past its time budget.
+    Time,
+}
+
+impl Truncation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Truncation::Size => "size",
+            Truncation::Time => "time",
+        }
+    }
+}
+
+impl TextFilter {
+    pub fn new() -> Self {
+        Self {
+            max_text: MAX_TEXT,
+            budget: TIME_BUDGET,
+            templates: Templates::default(),
+        }
+    }
+
+    /// Keeps the contents of these templates rather than stripping them.
+    pub fn keeping_templates(self, names: &[String]) -> Result<Self, Error> {
+        // What no template name can hold, being markup itself.
+        if let Some(name) = names.iter().find(|name| {
+            name.trim().is_empty() || name.contains(['{', '}', '|', '[', ']', '<', '>', '#'])
+        }) {
+            return Err(Error::BadFilter(format!(
+                "{name:?} can't be a template name"
+            )));
+        }
+        Ok(Self {
+            templates: Templates::keeping(names),
+            ..self
+        })
+    }
+
+    /// Gives each page this long to filter rather than the usual budget.
+    pub fn time_budget(self, budget: Duration) -> Self {
+        Self { budget, ..self }
+    }
+
+    pub fn filter(&self, text: &str) -> String {
+        let mut out = String::new();
+        self.filter_into(&mut out, text);
+        out
+    }
+
+    /// Filters text within the size and time limits into `out`, replacing what was there so
+    /// that one buffer can serve page after page, and says whether it had to stop short.
+    ///
+    /// A page is cut to size before anything else; when time runs out, whatever the pass
+    /// underway has finished is kept and the rest dropped. Either way the first links of a
+    /// page usually survive, since they come early.
+    pub fn filter_into(&self, out: &mut String, text: &str) -> Option<Truncation> {
+        let deadline = Instant::now() + self.budget;
+        let mut truncated = None;
+
+        let text = if text.len() > self.max_text {
+            truncated = Some(Truncation::Size);
+            &text[..floor_char_boundary(text, self.max_text)]
+        } else {
+            text
+        };
+
+        let finished = wikitext::clean_into(out, text, &self.templates, true, deadline);
+        if !finished || Instant::now() > deadline {
+            truncated = Some(Truncation::Time);
+        }
+        truncated
+    }
+}
+
+/// The largest index no greater than `idx` that falls on a character boundary.
+fn floor_char_boundary(text: &str, idx: usize) -> usize {
+    (0..=idx)
+        .rev()
+        .find(|&idx| text.is_char_boundary(idx))
+        .unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub struct LinkExtractor {
+    expr: Regex,
+    namespaces: HashSet<String>,
+}
+
+impl LinkExtractor {
+    pub fn new() -> Self {
+        Self {
+            expr: Regex::new(r#"\[\[([^|]+?)(\|.+?)?\]\]"#).unwrap(),
+            namespaces: HashSet::new(),
+        }
+    }
+
+    /// Also skips links into these namespaces, as listed in a dump's site information, for
+    /// wikis with namespaces of their own.
+    pub fn skipping(self, namespaces: &[String]) -> Self {
+        let namespaces = namespaces.iter().map(|name| name.to_lowercase()).collect();
+        Self { namespaces, ..self }
+    }
+
+    pub fn extract<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.candidates(text)
+            .find(|&candidate| !self.is_skipped(candidate))
+    }
+
+    /// Extracts every link in the page's prose, in order, without repeats.
+    pub fn extract_all<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut seen = HashSet::new();
+        self.candidates(text)
+            .filter(|&candidate| !self.is_skipped(candidate) && seen.insert(candidate))
+            .collect()
+    }
+
+    /// Like [`extract`](Self::extract), along with the text the link displays.
+    pub fn extract_labeled<'a>(&self, text: &'a str) -> Option<(&'a str, &'a str)> {
+        self.labeled_candidates(text)
+            .find(|&(candidate, _)| !self.is_skipped(candidate))
+    }
+
+    /// Like [`extract_all`](Self::extract_all), along with the text each link displays
+    /// where it first appears.
+    pub fn extract_all_labeled<'a>(&self, text: &'a str) -> Vec<(&'a str, &'a str)> {
+        let mut seen = HashSet::new();
+        self.labeled_candidates(text)
+            .filter(|&(candidate, _)| !self.is_skipped(candidate) && seen.insert(candidate))
+            .collect()
+    }
+
+    /// Explains why no link can be extracted from the text, if that is the case.
+    pub fn dead_end(&self, text: &str) -> Option<DeadEnd> {
+        if text.lines().all(|line| !is_prose(line)) {
+            return Some(DeadEnd::Empty);
+        }
+
+        let mut candidates = self.candidates(text).peekable();
+        if candidates.peek().is_none() {
+            return Some(DeadEnd::NoLinks);
+        }
+
+        candidates
+            .all(|candidate| self.is_skipped(candidate))
+            .then_some(DeadEnd::SkippedOnly)
+    }
+
+    /// Lists the links in a page's raw text, along with what extraction made of each once
+    /// the page had been filtered.
+    pub fn explain<'a>(&self, raw: &'a str, filtered: &str) -> Vec<(&'a str, Verdict)> {
+        let chosen = self.extract(filtered);
+        let in_prose: HashSet<_> = self.candidates(filtered).collect();
+        let in_filtered: HashSet<_> = self
+            .expr
+            .captures_iter(filtered)
+            .filter_map(|cx| cx.get(1).map(|cx| cx.as_str()))
+            .collect();
+
+        let mut seen = HashSet::new();
+        self.expr
+            .captures_iter(raw)
+            .filter_map(|cx| cx.get(1).map(|cx| cx.as_str()))
+            .filter(|&link| seen.insert(link))
+            .map(|link| {
+                let verdict = if self.is_skipped(link) {
+                    Verdict::Namespace
+                } else if !in_filtered.contains(link) {
+                    Verdict::Filtered
+                } else if !in_prose.contains(link) {
+                    Verdict::NotProse
+                } else if Some(link) == chosen {
+                    Verdict::Chosen
+                } else {
+                    Verdict::Later
+                };
+                (link, verdict)
+            })
+            .collect()
+    }
+
+    fn is_skipped(&self, link: &str) -> bool {
+        if is_skipped(link) {
+            return true;
+        }
+        link.split_once(':').is_some_and(|(prefix, _)| {
+            let prefix = prefix.trim().replace('_', " ").to_lowercase();
+            self.namespaces.contains(&prefix)
+        })
+    }
+
+    fn candidates<'s, 'a: 's>(&'s self, text: &'a str) -> impl Iterator<Item = &'a str> + 's {
+        self.labeled_candidates(text).map(|(target, _)| target)
+    }
+
+    /// Links in prose along with the text they display, which is the target itself unless
+    /// the link gives a label.
+    fn labeled_candidates<'s, 'a: 's>(
+        &'s self,
+        text: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a str)> + 's {
+        let paragraphs = text.lines().filter(|&text| is_prose(text));
+
+        paragraphs.flat_map(|paragraph| {
+            self.expr.captures_iter(paragraph).filter_map(|cx| {
+                let target = cx.get(1)?.as_str();
+                let label = cx
+                    .get(2)
+                    .map(|label| &label.as_str()[1..])
+                    .filter(|label| !label.is_empty());
+                Some((target, label.unwrap_or(target)))
+            })
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadEnd {
+    /// Nothing that looks like prose survives filtering.
+    Empty,
+    /// The prose contains no links.
+    NoLinks,
+    /// Every link in the prose points outside the article namespace.
+    SkippedOnly,
+}
+
+impl DeadEnd {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeadEnd::Empty => "empty",
+            DeadEnd::NoLinks => "no-links",
+            DeadEnd::SkippedOnly => "skipped-only",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Chosen,
+    /// A usable link that comes after the chosen one.
+    Later,
+    /// Points outside the article namespace.
+    Namespace,
+    /// Removed along with a template, parenthetical or reference.
+    Filtered,
+    /// Sits on a line that doesn't look like prose, such as a table row or list item.
+    NotProse,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Chosen => "chosen",
+            Verdict::Later => "later",
+            Verdict::Namespace => "namespace",
+            Verdict::Filtered => "filtered",
+            Verdict::NotProse => "not-prose",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Verdict::Chosen => "chosen as the first link",
+            Verdict::Later => "usable, but comes after the first link",
+            Verdict::Namespace => "skipped: outside the article namespace",
+            Verdict::Filtered => "skipped: inside a template, parenthetical or reference",
+            Verdict::NotProse => "skipped: not in a prose paragraph",
+        }
+    }
+}
+
+/// Namespaces and interwiki prefixes whose links never make sense as a first link.
+static SKIPPED_PREFIXES: &[&str] = &[
+    // Help pages of other language editions, which pronunciation guides link to.
+    "aide",
+    "aiuto",
+    "ajuda",
+    "ayuda",
+    "category",
+    "draft",
+    "file",
+    "help",
+    "hilfe",
+    "image",
+    "media",
+    "mos",
+    "pomoc",
+    "portal",
+    "special",
+    "talk",
+    "template",
+    "user",
+    "w",
+    "wikipedia",
+    "wikt",
+    "wiktionary",
+    "wp",
+];
+
+fn is_prose(line: &str) -> bool {
+    line.starts_with(|u: char| u.is_alphanumeric() || u == '\'')
+}
+
+pub(crate) fn is_skipped(link: &str) -> bool {
+    let link = link.trim_start();
+    if link.starts_with(':') {
+        return true;
+    }
+
+    match link.split_once(':') {
+        Some((prefix, _)) => {
+            let prefix = prefix.trim();
+            let lower = prefix.to_lowercase();
+            SKIPPED_PREFIXES.contains(&lower.as_str())
+                || lower.ends_with(" talk")
+                || is_language_code(prefix)
+        }
+        None => false,
+    }
+}
+
+/// Prefixes that lead to the other Wikimedia projects rather than a page on this wiki.
+static INTERWIKI_PREFIXES: &[&str] = &[
+    "b",
+    "commons",
+    "d",
+    "m",
+    "meta",
+    "n",
+    "q",
+    "s",
+    "species",
+    "v",
+    "voy",
+    "w",
+    "wikibooks",
+    "wikidata",
+    "wikinews",
+    "wikiquote",
+    "wikisource",
+    "wikiversity",
+    "wikivoyage",
+    "wikt",
+    "wiktionary",
+];
+
+/// Whether a link leads to another wiki: another language edition, as in `[[de:Banane]]`,
+/// or another project, as in `[[wikt:banana]]`.
+pub(crate) fn is_interwiki(link: &str) -> bool {
+    match link.trim_start().trim_start_matches(':').split_once(':') {
+        Some((prefix, _)) => {
+            let prefix = prefix.trim();
+            INTERWIKI_PREFIXES.contains(&prefix.to_lowercase().as_str()) || is_language_code(prefix)
+        }
+        None => false,
+    }
+}
+
+/// Interlanguage links look like `[[de:Banane]]`.
+fn is_language_code(prefix: &str) -> bool {
+    (2..=3).contains(&prefix.len()) && prefix.bytes().all(|u| u.is_ascii_lowercase())
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tracing::{info, warn};
+
+use crate::{
+    graph::normalize,
+    page::{self, PageBuffer},
+    titles::unescape,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to take pages from
+    dump: String,
+    /// title of a page to include; may be given more than once
+    #[clap(short, long)]
+    title: Vec<String>,
+    /// file of titles to include, one per line
+    #[clap(long)]
+    titles: Option<String>,
+    /// number of further pages to pick at random
+    #[clap(short = 'n', long, default_value_t = 0)]
+    sample: usize,
+    /// random seed for picking pages
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// write the fixture to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Copies chosen pages out of a dump, verbatim and in their original order, into a dump of
+/// their own that keeps the original's header.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut wanted: HashSet<String> = args.title.iter().map(|title| normalize(title)).collect();
+    if let Some(path) = &args.titles {
+        for line in File::open(path).map(io::BufReader::new)?.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                wanted.insert(normalize(line.trim()));
+            }
+        }
+    }
+
+    if wanted.is_empty() && args.sample == 0 {
+        anyhow::bail!("nothing to include; name some titles or ask for a sample");
+    }
+
+    // Named pages are kept outright; the sample is a reservoir over everything else.
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut chosen = Vec::new();
+    let mut reservoir = Vec::with_capacity(args.sample);
+    let mut seen = 0;
+
+    let (reader, _) = page::open_dump(&args.dump)?;
+    for (idx, text) in PageBuffer::starting_at(reader, 0).enumerate() {
+        let text = text?;
+        let title = raw_title(&text).map(|title| normalize(&unescape(title)));
+        if title.is_some_and(|title| wanted.remove(&title)) {
+            chosen.push((idx, text));
+            continue;
+        }
+
+        if args.sample == 0 {
+            if wanted.is_empty() {
+                break;
+            }
+            continue;
+        }
+
+        seen += 1;
+        if reservoir.len() < args.sample {
+            reservoir.push((idx, text));
+        } else {
+            let slot = rng.gen_range(0..seen);
+            if slot < args.sample {
+                reservoir[slot] = (idx, text);
+            }
+        }
+    }
+
+    for title in &wanted {
+        warn!("no page titled {title}");
+    }
+
+    chosen.extend(reservoir);
+    chosen.sort_by_key(|&(idx, _)| idx);
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    write_header(&args.dump, &mut out)?;
+    for (_, text) in &chosen {
+        out.write_all(text.as_bytes())?;
+    }
+    writeln!(out, "</mediawiki>")?;
+    out.flush()?;
+
+    info!("wrote {} pages", chosen.len());
+    Ok(())
+}
+
+fn raw_title(text: &str) -> Option<&str> {
+    text.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("<title>")
+            .and_then(|line| line.strip_suffix("</title>"))
+    })
+}
+
+/// Copies everything ahead of the first page: the opening `<mediawiki>` tag and the site
+/// information that follows it.
+fn write_header(dump: &str, out: &mut impl Write) -> io::Result<()> {
+    let (reader, _) = page::open_dump(dump)?;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim() == "<page>" {
+            break;
+        }
+        writeln!(out, "{}", line.trim_end_matches('\r'))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{
+    csv, page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to count words in
+    dump: String,
+    /// write the table to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// leave out words seen fewer times than this
+    #[clap(long, default_value_t = 1)]
+    min_count: u64,
+    /// count words as written rather than lowercased
+    #[clap(long)]
+    keep_case: bool,
+    /// also write title,word,count rows for every page to this file
+    #[clap(long)]
+    per_page: Option<String>,
+}
+
+/// Counts words across the cleaned text of every article, writing a word,count table with
+/// the most frequent first.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut per_page = match &args.per_page {
+        Some(path) => {
+            let mut out = File::create(path).map(BufWriter::new)?;
+            writeln!(out, "title,word,count")?;
+            Some(out)
+        }
+        None => None,
+    };
+
+    let plain = PlainText::new();
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    let mut pages = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+        pages += 1;
+
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for word in plain::words(&plain.convert(text)) {
+            let word = if args.keep_case {
+                word.to_owned()
+            } else {
+                word.to_lowercase()
+            };
+            *counts.entry(word).or_default() += 1;
+        }
+
+        if let Some(out) = &mut per_page {
+            let title = csv::field(&page.title);
+            for (word, count) in sorted(&counts) {
+                writeln!(out, "{title},{},{count}", csv::field(word))?;
+            }
+        }
+        for (word, count) in counts {
+            *totals.entry(word).or_default() += count;
+        }
+    }
+
+    if let Some(mut out) = per_page {
+        out.flush()?;
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    writeln!(out, "word,count")?;
+    for (word, count) in sorted(&totals) {
+        if count < args.min_count {
+            break;
+        }
+        writeln!(out, "{},{count}", csv::field(word))?;
+    }
+    out.flush()?;
+
+    info!("counted {} distinct words over {pages} pages", totals.len());
+    Ok(())
+}
+
+/// Most frequent first, then alphabetically.
+fn sorted(counts: &HashMap<String, u64>) -> Vec<(&str, u64)> {
+    let mut sorted: Vec<_> = counts
+        .iter()
+        .map(|(word, &count)| (word.as_str(), count))
+        .collect();
+    sorted.sort_unstable_by_key(|&(word, count)| (Reverse(count), word));
+    sorted
+}
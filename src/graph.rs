@@ -0,0 +1,860 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+pub use wiki_crawler::title::normalize;
+
+use crate::compress::Compressed;
+
+/// A directed link graph loaded from extractor output.
+///
+/// Edges are kept in the order they were read, so the first edge recorded for a page is its
+/// first link.
+#[derive(Debug, Default)]
+pub struct Graph {
+    titles: Vec<String>,
+    ids: HashMap<String, u32>,
+    links: Vec<Vec<u32>>,
+}
+
+impl Graph {
+    /// Loads a graph from a file of `source -> target` lines, from id pairs if the file name
+    /// ends in `.pairs`, or from a compressed graph if it ends in `.cgraph`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pairs") => return Self::load_pairs(path),
+            Some("cgraph") => return Self::load_compressed(path),
+            _ => {}
+        }
+
+        let reader = File::open(path).map(BufReader::new)?;
+        let mut graph = Graph::default();
+
+        for line in reader.lines() {
+            let line = line?;
+            if let Some((source, target)) = line.split_once(" -> ") {
+                let source = graph.insert(source);
+                let target = graph.insert(target);
+                graph.links[source as usize].push(target);
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Loads a graph of little-endian `u32` id pairs, whose titles are in a dictionary of
+    /// `id\ttitle` lines alongside.
+    fn load_pairs(path: &Path) -> io::Result<Self> {
+        let mut graph = Graph::load_titles(path)?;
+
+        let mut reader = File::open(path).map(BufReader::new)?;
+        let mut pair = [0; 8];
+        loop {
+            match reader.read_exact(&mut pair) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let source = u32::from_le_bytes(pair[..4].try_into().unwrap());
+            let target = u32::from_le_bytes(pair[4..].try_into().unwrap());
+            let in_range = |id| (id as usize) < graph.links.len();
+            if !in_range(source) || !in_range(target) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "pair refers to an id missing from the dictionary",
+                ));
+            }
+            graph.links[source as usize].push(target);
+        }
+
+        Ok(graph)
+    }
+
+    /// Loads a graph written by the compress command, whose titles are alongside as for id
+    /// pairs.
+    fn load_compressed(path: &Path) -> io::Result<Self> {
+        let compressed = Compressed::open(path)?;
+        let mut graph = Graph::load_titles(path)?;
+        if compressed.len() != graph.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "compressed graph and dictionary differ in size",
+            ));
+        }
+        for (id, links) in graph.links.iter_mut().enumerate() {
+            *links = compressed
+                .links(id as u32)
+                .collect::<Option<_>>()
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "malformed compressed graph")
+                })?;
+        }
+        Ok(graph)
+    }
+
+    /// An unlinked graph of the titles in the `id\ttitle` dictionary alongside `path`.
+    fn load_titles(path: &Path) -> io::Result<Self> {
+        let mut graph = Graph::default();
+
+        let dictionary = File::open(titles_path(path)).map(BufReader::new)?;
+        for line in dictionary.lines() {
+            let line = line?;
+            let (id, title) = line
+                .split_once('\t')
+                .and_then(|(id, title)| Some((id.parse::<usize>().ok()?, title)))
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "malformed dictionary")
+                })?;
+
+            if id >= graph.titles.len() {
+                graph.titles.resize(id + 1, String::new());
+            }
+            graph.titles[id] = title.to_owned();
+            graph.ids.insert(title.to_owned(), id as u32);
+        }
+        graph.links = vec![Vec::new(); graph.titles.len()];
+        Ok(graph)
+    }
+
+    fn insert(&mut self, title: &str) -> u32 {
+        let title = normalize(title);
+        if let Some(&id) = self.ids.get(&title) {
+            return id;
+        }
+
+        let id = self.titles.len() as u32;
+        self.titles.push(title.clone());
+        self.ids.insert(title, id);
+        self.links.push(Vec::new());
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.links.iter().map(Vec::len).sum()
+    }
+
+    pub fn id(&self, title: &str) -> Option<u32> {
+        self.ids.get(&normalize(title)).copied()
+    }
+
+    pub fn title(&self, id: u32) -> &str {
+        &self.titles[id as usize]
+    }
+
+    pub fn links(&self, id: u32) -> &[u32] {
+        &self.links[id as usize]
+    }
+
+    pub fn first_link(&self, id: u32) -> Option<u32> {
+        self.links(id).first().copied()
+    }
+
+    /// Follows first links from `start` until `target`, a dead end, or a loop.
+    pub fn chain(&self, start: u32, target: u32) -> Chain {
+        let mut path = vec![start];
+        let mut seen = HashMap::new();
+        seen.insert(start, 0);
+
+        let mut current = start;
+        loop {
+            if current == target {
+                return Chain {
+                    path,
+                    outcome: Outcome::Reached,
+                };
+            }
+
+            let next = match self.first_link(current) {
+                Some(next) => next,
+                None => {
+                    return Chain {
+                        path,
+                        outcome: Outcome::DeadEnd,
+                    }
+                }
+            };
+
+            if let Some(&idx) = seen.get(&next) {
+                path.push(next);
+                return Chain {
+                    path,
+                    outcome: Outcome::Loop(idx),
+                };
+            }
+
+            seen.insert(next, path.len());
+            path.push(next);
+            current = next;
+        }
+    }
+
+    /// Computes the first-link chain length from every page to `target`.
+    ///
+    /// Pages whose chain ends in a dead end or a loop get `None`. Each page is walked at most
+    /// once, so this is linear in the number of pages.
+    pub fn chain_lengths(&self, target: u32) -> Vec<Option<u32>> {
+        #[derive(Clone, Copy)]
+        enum State {
+            Unknown,
+            Walking,
+            Done(Option<u32>),
+        }
+
+        let mut states = vec![State::Unknown; self.titles.len()];
+        let mut stack = Vec::new();
+
+        for start in 0..self.titles.len() as u32 {
+            let mut current = start;
+            let mut result = loop {
+                match states[current as usize] {
+                    State::Done(result) => break result,
+                    State::Walking => break None,
+                    State::Unknown => {}
+                }
+
+                if current == target {
+                    states[current as usize] = State::Done(Some(0));
+                    break Some(0);
+                }
+
+                states[current as usize] = State::Walking;
+                stack.push(current);
+                match self.first_link(current) {
+                    Some(next) => current = next,
+                    None => break None,
+                }
+            };
+
+            while let Some(id) = stack.pop() {
+                result = result.map(|len| len + 1);
+                states[id as usize] = State::Done(result);
+            }
+        }
+
+        states
+            .into_iter()
+            .map(|state| match state {
+                State::Done(result) => result,
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Finds every distinct loop in the first-link graph, along with the number of pages whose
+    /// chains run into it without being part of it.
+    pub fn loops(&self) -> Vec<Loop> {
+        #[derive(Clone, Copy)]
+        enum State {
+            Unknown,
+            Walking(usize),
+            Done(Option<usize>),
+        }
+
+        let mut states = vec![State::Unknown; self.titles.len()];
+        let mut stack = Vec::new();
+        let mut loops = Vec::new();
+
+        for start in 0..self.titles.len() as u32 {
+            let mut current = start;
+            let result = loop {
+                match states[current as usize] {
+                    State::Done(result) => break result,
+                    State::Walking(pos) => {
+                        loops.push(Loop {
+                            members: stack[pos..].to_vec(),
+                            feeders: 0,
+                        });
+                        break Some(loops.len() - 1);
+                    }
+                    State::Unknown => {}
+                }
+
+                states[current as usize] = State::Walking(stack.len());
+                stack.push(current);
+                match self.first_link(current) {
+                    Some(next) => current = next,
+                    None => break None,
+                }
+            };
+
+            for id in stack.drain(..) {
+                states[id as usize] = State::Done(result);
+            }
+        }
+
+        for state in states {
+            if let State::Done(Some(idx)) = state {
+                loops[idx].feeders += 1;
+            }
+        }
+
+        for cycle in &mut loops {
+            cycle.feeders -= cycle.members.len();
+        }
+
+        loops
+    }
+
+    /// Computes the breadth-first distance from `source` to every page. Unreachable pages get
+    /// `None`.
+    pub fn distances(&self, source: u32) -> Vec<Option<u32>> {
+        let mut dist = vec![None; self.titles.len()];
+        let mut queue = VecDeque::new();
+        dist[source as usize] = Some(0);
+        queue.push_back(source);
+
+        while let Some(u) = queue.pop_front() {
+            let depth = dist[u as usize].unwrap_or_default();
+            for &v in self.links(u) {
+                if dist[v as usize].is_none() {
+                    dist[v as usize] = Some(depth + 1);
+                    queue.push_back(v);
+                }
+            }
+        }
+
+        dist
+    }
+
+    /// Finds strongly connected components with an iterative version of Tarjan's algorithm.
+    /// With `first_only`, only each page's first link is considered.
+    pub fn strongly_connected(&self, first_only: bool) -> Vec<Vec<u32>> {
+        let n = self.titles.len();
+        let edges = |v: u32| {
+            let links = self.links(v);
+            if first_only {
+                &links[..links.len().min(1)]
+            } else {
+                links
+            }
+        };
+
+        let mut index = vec![u32::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut calls: Vec<(u32, usize)> = Vec::new();
+        let mut counter = 0;
+        let mut components = Vec::new();
+
+        for root in 0..n as u32 {
+            if index[root as usize] != u32::MAX {
+                continue;
+            }
+
+            index[root as usize] = counter;
+            low[root as usize] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root as usize] = true;
+            calls.push((root, 0));
+
+            while let Some((v, i)) = calls.last_mut() {
+                let v = *v;
+                let links = edges(v);
+
+                if *i < links.len() {
+                    let w = links[*i];
+                    *i += 1;
+
+                    if index[w as usize] == u32::MAX {
+                        index[w as usize] = counter;
+                        low[w as usize] = counter;
+                        counter += 1;
+                        stack.push(w);
+                        on_stack[w as usize] = true;
+                        calls.push((w, 0));
+                    } else if on_stack[w as usize] {
+                        low[v as usize] = low[v as usize].min(index[w as usize]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(u, _)) = calls.last() {
+                    low[u as usize] = low[u as usize].min(low[v as usize]);
+                }
+
+                if low[v as usize] == index[v as usize] {
+                    let mut component = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack[w as usize] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Finds weakly connected components, treating every link as undirected.
+    pub fn weakly_connected(&self, first_only: bool) -> Vec<Vec<u32>> {
+        fn find(parents: &mut [u32], mut x: u32) -> u32 {
+            while parents[x as usize] != x {
+                let parent = parents[x as usize];
+                parents[x as usize] = parents[parent as usize];
+                x = parent;
+            }
+            x
+        }
+
+        let n = self.titles.len();
+        let mut parents: Vec<u32> = (0..n as u32).collect();
+
+        for u in 0..n as u32 {
+            let links = self.links(u);
+            let links = if first_only {
+                &links[..links.len().min(1)]
+            } else {
+                links
+            };
+
+            for &v in links {
+                let (a, b) = (find(&mut parents, u), find(&mut parents, v));
+                if a != b {
+                    parents[a.max(b) as usize] = a.min(b);
+                }
+            }
+        }
+
+        let mut components: HashMap<u32, Vec<u32>> = HashMap::new();
+        for id in 0..n as u32 {
+            let root = find(&mut parents, id);
+            components.entry(root).or_default().push(id);
+        }
+        components.into_values().collect()
+    }
+
+    /// Lists the pages linking to each page.
+    pub fn backlinks(&self) -> Vec<Vec<u32>> {
+        let mut backlinks = vec![Vec::new(); self.titles.len()];
+        for (u, links) in self.links.iter().enumerate() {
+            for &v in links {
+                backlinks[v as usize].push(u as u32);
+            }
+        }
+        backlinks
+    }
+
+    /// Counts the links pointing at each page.
+    pub fn in_degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.titles.len()];
+        for links in &self.links {
+            for &v in links {
+                degrees[v as usize] += 1;
+            }
+        }
+        degrees
+    }
+
+    /// Computes PageRank by power iteration. Rank held by pages without links is spread
+    /// evenly over every page.
+    pub fn pagerank(&self, damping: f64, iterations: usize) -> Vec<f64> {
+        let n = self.titles.len();
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let base = 1.0 / n as f64;
+        let mut ranks = vec![base; n];
+        let mut next = vec![0.0; n];
+
+        for _ in 0..iterations {
+            let mut dangling = 0.0;
+            next.iter_mut().for_each(|rank| *rank = 0.0);
+
+            for (u, &rank) in ranks.iter().enumerate() {
+                let links = &self.links[u];
+                if links.is_empty() {
+                    dangling += rank;
+                    continue;
+                }
+
+                let share = rank / links.len() as f64;
+                for &v in links {
+                    next[v as usize] += share;
+                }
+            }
+
+            let teleport = (1.0 - damping) * base + damping * dangling * base;
+            for rank in &mut next {
+                *rank = teleport + damping * *rank;
+            }
+
+            std::mem::swap(&mut ranks, &mut next);
+        }
+
+        ranks
+    }
+
+    /// Computes HITS hub and authority scores, normalized so each vector has unit length.
+    pub fn hits(&self, iterations: usize) -> (Vec<f64>, Vec<f64>) {
+        let n = self.titles.len();
+        let mut hubs = vec![1.0; n];
+        let mut authorities = vec![1.0; n];
+
+        for _ in 0..iterations {
+            authorities.iter_mut().for_each(|score| *score = 0.0);
+            for (u, links) in self.links.iter().enumerate() {
+                for &v in links {
+                    authorities[v as usize] += hubs[u];
+                }
+            }
+            normalize_scores(&mut authorities);
+
+            for (u, links) in self.links.iter().enumerate() {
+                hubs[u] = links.iter().map(|&v| authorities[v as usize]).sum();
+            }
+            normalize_scores(&mut hubs);
+        }
+
+        (hubs, authorities)
+    }
+
+    /// Accumulates Brandes' betweenness dependencies from each of `pivots`. Scaling the
+    /// result by `len / pivots.len()` estimates betweenness centrality over the whole graph.
+    pub fn betweenness(&self, pivots: &[u32]) -> Vec<f64> {
+        let n = self.titles.len();
+        let mut centrality = vec![0.0; n];
+        let mut sigma = vec![0.0f64; n];
+        let mut dist = vec![u32::MAX; n];
+        let mut delta = vec![0.0f64; n];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for &s in pivots {
+            for &v in &order {
+                sigma[v as usize] = 0.0;
+                dist[v as usize] = u32::MAX;
+                delta[v as usize] = 0.0;
+            }
+            order.clear();
+
+            sigma[s as usize] = 1.0;
+            dist[s as usize] = 0;
+            queue.push_back(s);
+
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+                for &w in self.links(v) {
+                    if dist[w as usize] == u32::MAX {
+                        dist[w as usize] = dist[v as usize] + 1;
+                        queue.push_back(w);
+                    }
+                    if dist[w as usize] == dist[v as usize] + 1 {
+                        sigma[w as usize] += sigma[v as usize];
+                    }
+                }
+            }
+
+            // Predecessors of w are exactly the pages one step closer to s that link to it,
+            // so walking pages in reverse BFS order lets each push its dependency back.
+            for &v in order.iter().rev() {
+                for &w in self.links(v) {
+                    if dist[w as usize] == dist[v as usize] + 1 {
+                        delta[v as usize] +=
+                            sigma[v as usize] / sigma[w as usize] * (1.0 + delta[w as usize]);
+                    }
+                }
+                if v != s {
+                    centrality[v as usize] += delta[v as usize];
+                }
+            }
+        }
+
+        centrality
+    }
+
+    /// Finds shortest paths from `source` to `target` by breadth-first search, at most `limit`
+    /// of them. Well-linked pages can be joined by more paths than fit in memory.
+    pub fn shortest_paths(&self, source: u32, target: u32, limit: usize) -> Vec<Vec<u32>> {
+        let all = limit > 1;
+        let mut dist = vec![u32::MAX; self.titles.len()];
+        let mut preds: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut frontier = vec![source];
+        let mut depth = 0;
+        dist[source as usize] = 0;
+
+        while !frontier.is_empty() && dist[target as usize] == u32::MAX {
+            let mut next = Vec::new();
+            for &u in &frontier {
+                for &v in self.links(u) {
+                    if dist[v as usize] == u32::MAX {
+                        dist[v as usize] = depth + 1;
+                        preds.insert(v, vec![u]);
+                        next.push(v);
+                    } else if all && dist[v as usize] == depth + 1 {
+                        preds.entry(v).or_default().push(u);
+                    }
+                }
+            }
+            frontier = next;
+            depth += 1;
+        }
+
+        if dist[target as usize] == u32::MAX {
+            return Vec::new();
+        }
+
+        let mut paths = Vec::new();
+        let mut stack = vec![vec![target]];
+        while let Some(path) = stack.pop() {
+            let head = *path.last().unwrap();
+            if head == source {
+                paths.push(path.into_iter().rev().collect());
+                if paths.len() >= limit {
+                    break;
+                }
+                continue;
+            }
+
+            for &pred in &preds[&head] {
+                let mut path = path.clone();
+                path.push(pred);
+                stack.push(path);
+            }
+        }
+
+        paths
+    }
+}
+
+#[derive(Debug)]
+pub struct Loop {
+    /// Pages on the loop, in first-link order.
+    pub members: Vec<u32>,
+    /// Pages outside the loop whose chains end in it.
+    pub feeders: usize,
+}
+
+#[derive(Debug)]
+pub struct Chain {
+    /// Every page visited, starting with the start page. When the chain ends in a loop, the
+    /// page closing the loop appears twice.
+    pub path: Vec<u32>,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Reached,
+    DeadEnd,
+    /// The chain revisits the page at this index of the path.
+    Loop(usize),
+}
+
+fn normalize_scores(scores: &mut [f64]) {
+    let norm = scores.iter().map(|score| score * score).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        scores.iter_mut().for_each(|score| *score /= norm);
+    }
+}
+
+/// The dictionary of titles for a file of id pairs lives alongside it.
+pub fn titles_path(pairs: &Path) -> PathBuf {
+    pairs.with_extension("titles")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph with the given links, in order, so the first given for a page is its first
+    /// link.
+    fn graph(links: &[(&str, &str)]) -> Graph {
+        let mut graph = Graph::default();
+        for (source, target) in links {
+            let source = graph.insert(source);
+            let target = graph.insert(target);
+            graph.links[source as usize].push(target);
+        }
+        graph
+    }
+
+    fn ids(graph: &Graph, titles: &[&str]) -> Vec<u32> {
+        titles
+            .iter()
+            .map(|title| graph.id(title).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn chains_end_at_a_first_link_loop() {
+        let graph = graph(&[
+            ("A", "B"),
+            ("B", "C"),
+            ("C", "A"),
+            ("D", "A"),
+            ("E", "T"),
+            ("F", "E"),
+            ("F", "A"),
+        ]);
+        let lengths = graph.chain_lengths(graph.id("T").unwrap());
+        let length = |title| lengths[graph.id(title).unwrap() as usize];
+        assert_eq!(length("T"), Some(0));
+        assert_eq!(length("E"), Some(1));
+        assert_eq!(length("F"), Some(2));
+        for title in ["A", "B", "C", "D"] {
+            assert_eq!(length(title), None);
+        }
+
+        let chain = graph.chain(graph.id("D").unwrap(), graph.id("T").unwrap());
+        assert_eq!(chain.path, ids(&graph, &["D", "A", "B", "C", "A"]));
+        assert_eq!(chain.outcome, Outcome::Loop(1));
+    }
+
+    #[test]
+    fn reports_each_loop_once() {
+        let graph = graph(&[
+            ("A", "B"),
+            ("B", "C"),
+            ("C", "A"),
+            ("D", "B"),
+            ("E", "D"),
+            ("F", "F"),
+            ("G", "H"),
+        ]);
+        let loops = graph.loops();
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].members, ids(&graph, &["A", "B", "C"]));
+        assert_eq!(loops[0].feeders, 2);
+        assert_eq!(loops[1].members, ids(&graph, &["F"]));
+        assert_eq!(loops[1].feeders, 0);
+    }
+
+    #[test]
+    fn finds_shortest_paths_up_to_a_limit() {
+        let graph = graph(&[
+            ("S", "A"),
+            ("S", "B"),
+            ("S", "C"),
+            ("A", "T"),
+            ("B", "T"),
+            ("C", "T"),
+            ("S", "D"),
+            ("D", "E"),
+            ("E", "T"),
+        ]);
+        let [s, t] = ids(&graph, &["S", "T"])[..] else {
+            unreachable!()
+        };
+        let mut paths = graph.shortest_paths(s, t, 10);
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                ids(&graph, &["S", "A", "T"]),
+                ids(&graph, &["S", "B", "T"]),
+                ids(&graph, &["S", "C", "T"]),
+            ]
+        );
+        assert_eq!(graph.shortest_paths(s, t, 2).len(), 2);
+        assert_eq!(graph.shortest_paths(s, t, 1).len(), 1);
+        assert_eq!(graph.shortest_paths(s, s, 10), [vec![s]]);
+    }
+
+    #[test]
+    fn finds_no_path_against_the_links() {
+        let graph = graph(&[("S", "A"), ("A", "T"), ("U", "S")]);
+        let [s, t, u] = ids(&graph, &["S", "T", "U"])[..] else {
+            unreachable!()
+        };
+        assert!(graph.shortest_paths(t, s, 10).is_empty());
+        assert!(graph.shortest_paths(s, u, 1).is_empty());
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn ranks_a_cycle_evenly() {
+        let graph = graph(&[("A", "B"), ("B", "C"), ("C", "A")]);
+        let ranks = graph.pagerank(0.85, 50);
+        assert!(ranks.iter().all(|&rank| close(rank, 1.0 / 3.0)));
+    }
+
+    #[test]
+    fn keeps_the_rank_of_dangling_pages() {
+        let ranks = graph(&[("A", "B")]).pagerank(0.85, 100);
+        // r(A) = 0.15 / 2 + 0.85 r(B) / 2, with r(A) + r(B) = 1.
+        let a = 0.5 / 1.425;
+        assert!(close(ranks[0], a), "{ranks:?}");
+        assert!(close(ranks[1], 1.0 - a), "{ranks:?}");
+
+        let graph = graph(&[("A", "B"), ("A", "C"), ("C", "A"), ("D", "C"), ("E", "A")]);
+        for iterations in [1, 5, 50] {
+            let ranks = graph.pagerank(0.85, iterations);
+            assert!(close(ranks.iter().sum(), 1.0), "{ranks:?}");
+        }
+    }
+
+    #[test]
+    fn finds_strongly_connected_components() {
+        let graph = graph(&[
+            ("A", "B"),
+            ("B", "C"),
+            ("C", "A"),
+            ("C", "D"),
+            ("D", "E"),
+            ("E", "D"),
+            ("F", "F"),
+            ("G", "F"),
+        ]);
+        let mut components = graph.strongly_connected(false);
+        components.iter_mut().for_each(|component| component.sort());
+        components.sort();
+        assert_eq!(
+            components,
+            [
+                ids(&graph, &["A", "B", "C"]),
+                ids(&graph, &["D", "E"]),
+                ids(&graph, &["F"]),
+                ids(&graph, &["G"]),
+            ]
+        );
+
+        // C's first link stays in its loop, so first links alone find the same components.
+        let mut first = graph.strongly_connected(true);
+        first.iter_mut().for_each(|component| component.sort());
+        first.sort();
+        assert_eq!(first, components);
+    }
+
+    #[test]
+    fn scores_the_middle_of_a_path_highest() {
+        let mut links = Vec::new();
+        for pair in ["A", "B", "C", "D", "E"].windows(2) {
+            links.extend([(pair[0], pair[1]), (pair[1], pair[0])]);
+        }
+        let graph = graph(&links);
+        let all: Vec<u32> = (0..graph.len() as u32).collect();
+        // Each pair of pages either side of a page, both ways round.
+        assert_eq!(graph.betweenness(&all), [0.0, 6.0, 8.0, 6.0, 0.0]);
+        // From one end alone, each page is on the way to those beyond it.
+        assert_eq!(graph.betweenness(&all[..1]), [0.0, 3.0, 2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn estimates_betweenness_from_a_sample() {
+        let graph = graph(&[("A", "B"), ("B", "C"), ("C", "D"), ("D", "A")]);
+        let all: Vec<u32> = (0..4).collect();
+        assert_eq!(graph.betweenness(&all), [3.0; 4]);
+        // Scaled up, one pivot accounts for all the pages' betweenness between them.
+        let sampled = graph.betweenness(&all[2..3]);
+        assert_eq!(sampled.iter().sum::<f64>() * 4.0, 12.0);
+    }
+}
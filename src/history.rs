@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use regex::Regex;
+use tracing::info;
+use wiki_crawler::model::Revision;
+
+use crate::{
+    csv,
+    filter::{LinkExtractor, TextFilter},
+    graph::normalize,
+    page,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// history dump to read revisions from
+    dump: String,
+    /// write the timeline to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// leave out revisions made before this date, as in 2015-01-01 or 2015-01-01T12:00:00Z
+    #[clap(long)]
+    from: Option<Date>,
+    /// leave out revisions made after this date; a day includes all of itself
+    #[clap(long)]
+    to: Option<Date>,
+    #[clap(flatten)]
+    revisions: RevisionArgs,
+}
+
+/// Options for leaving revisions out of per-revision datasets.
+#[derive(Debug, clap::Args)]
+pub struct RevisionArgs {
+    /// leave out revisions their editors marked as minor
+    #[clap(long)]
+    skip_minor: bool,
+    /// leave out revisions made by bots, going by --bot-pattern and --bots
+    #[clap(long)]
+    skip_bots: bool,
+    /// usernames matching this are taken to be bots
+    #[clap(long, default_value = r"(?i)bot\b")]
+    bot_pattern: Regex,
+    /// file of bot usernames, one per line, as listed at Special:ListUsers/bot
+    #[clap(long)]
+    bots: Option<PathBuf>,
+}
+
+impl RevisionArgs {
+    pub fn filter(&self) -> anyhow::Result<RevisionFilter> {
+        let bots = match &self.bots {
+            Some(path) => fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(normalize)
+                .collect(),
+            None => HashSet::new(),
+        };
+        Ok(RevisionFilter {
+            skip_minor: self.skip_minor,
+            skip_bots: self.skip_bots,
+            pattern: self.bot_pattern.clone(),
+            bots,
+        })
+    }
+}
+
+/// Decides which revisions go into a dataset, as [`RevisionArgs`] say.
+pub struct RevisionFilter {
+    skip_minor: bool,
+    skip_bots: bool,
+    pattern: Regex,
+    bots: HashSet<String>,
+}
+
+impl RevisionFilter {
+    pub fn keeps(&self, revision: &Revision) -> bool {
+        if self.skip_minor && revision.minor {
+            return false;
+        }
+        !self.skip_bots || !self.is_bot(revision)
+    }
+
+    fn is_bot(&self, revision: &Revision) -> bool {
+        let username = revision
+            .contributor
+            .as_ref()
+            .and_then(|contributor| contributor.username.as_deref());
+        username.is_some_and(|username| {
+            self.pattern.is_match(username) || self.bots.contains(&normalize(username))
+        })
+    }
+}
+
+/// A day, as in `2015-01-01`, or a moment, as in `2015-01-01T12:00:00Z`.
+#[derive(Debug, Clone)]
+struct Date(String);
+
+impl Date {
+    /// The earliest timestamp on the date.
+    fn start(&self) -> String {
+        match self.0.len() {
+            10 => format!("{}T00:00:00Z", self.0),
+            _ => self.0.clone(),
+        }
+    }
+
+    /// The latest timestamp on the date.
+    fn end(&self) -> String {
+        match self.0.len() {
+            10 => format!("{}T23:59:59Z", self.0),
+            _ => self.0.clone(),
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let shape = match s.len() {
+            10 => "dddd-dd-dd",
+            20 => "dddd-dd-ddTdd:dd:ddZ",
+            _ => return Err(format!("not a date: {s}")),
+        };
+        let matches = s.bytes().zip(shape.bytes()).all(|(u, v)| match v {
+            b'd' => u.is_ascii_digit(),
+            _ => u == v,
+        });
+        match matches {
+            true => Ok(Self(s.to_owned())),
+            false => Err(format!("not a date: {s}")),
+        }
+    }
+}
+
+/// Writes a CSV timeline of every revision in a history dump with how much text and which
+/// links it added and removed compared with the revision before it, oldest first. The first
+/// revision of a page is compared with an empty one.
+///
+/// Text that changed is compared line by line without regard to order, so a paragraph that
+/// moves counts as neither added nor removed. Links are the ones extract would consider, compared as
+/// sets of normalized titles.
+///
+/// With `--from` and `--to`, only revisions made between the two are written, and those made
+/// after are never looked at. The first revision in the window is compared with the last one
+/// before it, so that what it changed is counted the same either way. Revisions left out for
+/// being minor or made by bots are still compared with, so the edit after one is credited
+/// only with what it changed itself.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new();
+    let links_in = |text: &str| -> HashSet<String> {
+        ex.extract_all(&tf.filter(text))
+            .into_iter()
+            .map(normalize)
+            .collect()
+    };
+    let filter = args.revisions.filter()?;
+    let from = args.from.as_ref().map(Date::start);
+    let to = args.to.as_ref().map(Date::end);
+
+    writeln!(
+        out,
+        "title,revision,timestamp,bytes,bytes_added,bytes_removed,links,links_added,links_removed"
+    )?;
+    let (mut count, mut skipped) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        let title = csv::field(&page.title);
+        let mut previous = "";
+        let mut previous_links = HashSet::new();
+        // Timestamps are ISO 8601, which sort as strings.
+        let mut revisions: Vec<_> = page.revisions().iter().collect();
+        revisions.sort_by_key(|revision| revision.timestamp.as_deref());
+        if from.is_some() || to.is_some() {
+            revisions.retain(|revision| revision.timestamp.is_some());
+        }
+        if let Some(from) = &from {
+            let start = revisions
+                .partition_point(|revision| revision.timestamp.as_deref() < Some(from.as_str()));
+            if let Some(before) = start.checked_sub(1).map(|idx| revisions[idx]) {
+                previous = before.text();
+                previous_links = links_in(previous);
+            }
+            revisions.drain(..start);
+        }
+        if let Some(to) = &to {
+            let end = revisions
+                .partition_point(|revision| revision.timestamp.as_deref() <= Some(to.as_str()));
+            revisions.truncate(end);
+        }
+
+        for revision in revisions {
+            let text = revision.text();
+            let links = links_in(text);
+            if !filter.keeps(revision) {
+                skipped += 1;
+                previous = text;
+                previous_links = links;
+                continue;
+            }
+            let (added, removed) = changes(previous, text);
+
+            writeln!(
+                out,
+                "{title},{},{},{},{added},{removed},{},{},{}",
+                revision.id.map(|id| id.to_string()).unwrap_or_default(),
+                revision.timestamp.as_deref().unwrap_or_default(),
+                text.len(),
+                links.len(),
+                links.difference(&previous_links).count(),
+                previous_links.difference(&links).count(),
+            )?;
+            count += 1;
+
+            previous = text;
+            previous_links = links;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {count} revisions, leaving out {skipped}");
+    Ok(())
+}
+
+/// Bytes that `new` adds to `old` and removes from it.
+///
+/// Whatever the two share at either end is left out, which pins down a single edit to the
+/// byte. What remains is compared as lines, counting each repeat of a line separately.
+fn changes(old: &str, new: &str) -> (usize, usize) {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .take_while(|((_, u), v)| u == v)
+        .last()
+        .map_or(0, |((idx, u), _)| idx + u.len_utf8());
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix: usize = old
+        .chars()
+        .rev()
+        .zip(new.chars().rev())
+        .take_while(|(u, v)| u == v)
+        .map(|(u, _)| u.len_utf8())
+        .sum();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    let mut lines: HashMap<&str, isize> = HashMap::new();
+    for line in new.split_inclusive('\n') {
+        *lines.entry(line).or_default() += 1;
+    }
+    for line in old.split_inclusive('\n') {
+        *lines.entry(line).or_default() -= 1;
+    }
+
+    let (mut added, mut removed) = (0, 0);
+    for (line, surplus) in lines {
+        let bytes = line.len() * surplus.unsigned_abs();
+        if surplus > 0 {
+            added += bytes;
+        } else {
+            removed += bytes;
+        }
+    }
+    (added, removed)
+}
@@ -0,0 +1,540 @@
+//! A small HTTP/1.1 client on tokio, for builds with the `async` feature: dump downloads and
+//! live API requests then wait on the runtime's reactor rather than each holding a thread in a
+//! blocking read. `https` goes over rustls, trusting Mozilla's root certificates.
+//!
+//! It speaks just enough HTTP for a GET: one request per connection, bodies with a length,
+//! chunked or running to the end of the connection, and redirects.
+
+use std::{
+    io::{self, Read, Write},
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{ready, Context, Poll},
+};
+
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf},
+    net::TcpStream,
+    runtime::{self, Runtime},
+    sync::mpsc,
+};
+use tracing::debug;
+
+/// Redirects followed before giving up on a request.
+const MAX_REDIRECTS: usize = 5;
+
+/// The most of a body handed over at once.
+const CHUNK: usize = 64 * 1024;
+
+/// The runtime requests run on, started the first time one is made.
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        runtime::Builder::new_multi_thread()
+            .enable_io()
+            .build()
+            .expect("failed to start the async runtime")
+    })
+}
+
+/// A response whose headers have been read, with its body still to come.
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    headers: Vec<(String, String)>,
+    body: Body,
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+/// How much of a body is left, by the way its length is given.
+enum Body {
+    Length(u64),
+    /// In chunks, with what's left of the current one; `None` before its size is read.
+    Chunked(Option<u64>),
+    /// Up to the end of the connection.
+    Close,
+    Done,
+}
+
+impl Response {
+    /// The value of a header, if the response has it.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads the whole body.
+    pub async fn bytes(mut self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Streams the body, from a task on the runtime, to a reader for code that isn't async.
+    /// A few chunks are read ahead of the reader.
+    pub fn into_reader(mut self) -> Box<dyn Read + Send> {
+        let (sender, receiver) = mpsc::channel(16);
+        runtime().spawn(async move {
+            loop {
+                let chunk = self.chunk().await;
+                let last = !matches!(chunk, Ok(Some(_)));
+                if sender.send(chunk).await.is_err() || last {
+                    break;
+                }
+            }
+        });
+        Box::new(ChannelReader {
+            receiver,
+            chunk: Vec::new(),
+            at: 0,
+            done: false,
+        })
+    }
+
+    /// The next piece of the body, or `None` once it's all been read.
+    async fn chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let want = loop {
+            match self.body {
+                Body::Done | Body::Length(0) => {
+                    self.body = Body::Done;
+                    return Ok(None);
+                }
+                Body::Chunked(None) => {
+                    let line = read_line(&mut self.stream).await?;
+                    let size = line.split(';').next().unwrap_or_default().trim();
+                    let size = u64::from_str_radix(size, 16)
+                        .map_err(|_| invalid(format!("{size:?} isn't the size of a chunk")))?;
+                    if size == 0 {
+                        // Trailers end at a blank line, like the headers.
+                        while !read_line(&mut self.stream).await?.is_empty() {}
+                        self.body = Body::Done;
+                        return Ok(None);
+                    }
+                    self.body = Body::Chunked(Some(size));
+                }
+                Body::Chunked(Some(0)) => {
+                    // Each chunk ends in a line break of its own.
+                    read_line(&mut self.stream).await?;
+                    self.body = Body::Chunked(None);
+                }
+                Body::Length(left) | Body::Chunked(Some(left)) => {
+                    break left.min(CHUNK as u64) as usize
+                }
+                Body::Close => break CHUNK,
+            }
+        };
+
+        let mut chunk = vec![0; want];
+        let read = match self.stream.read(&mut chunk).await {
+            // Servers often close TLS connections without saying so first, which is as good as
+            // an end when nothing says how long the body is.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+            read => read?,
+        };
+        chunk.truncate(read);
+        match &mut self.body {
+            Body::Close if read == 0 => {
+                self.body = Body::Done;
+                return Ok(None);
+            }
+            _ if read == 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Body::Length(left) | Body::Chunked(Some(left)) => *left -= read as u64,
+            _ => {}
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// Sends a GET for `url`, with `headers` besides the ones every request gets, and reads the
+/// headers of the response. Redirects are followed; any other status is left to the caller.
+pub async fn get(url: &url::Url, headers: &[(&str, String)]) -> io::Result<Response> {
+    let mut url = url.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = request(&url, headers).await?;
+        match (response.status, response.header("Location")) {
+            (301 | 302 | 303 | 307 | 308, Some(location)) => {
+                url = url.join(location).map_err(invalid)?;
+                debug!("redirected to {url}");
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err(io::Error::other(format!(
+        "{url} redirected more than {MAX_REDIRECTS} times"
+    )))
+}
+
+async fn request(url: &url::Url, headers: &[(&str, String)]) -> io::Result<Response> {
+    let tls = match url.scheme() {
+        "http" => false,
+        "https" => true,
+        scheme => return Err(invalid(format!("{scheme}:// URLs can't be fetched"))),
+    };
+    let host = match url.host() {
+        Some(url::Host::Domain(domain)) => domain.to_owned(),
+        Some(url::Host::Ipv4(ip)) => ip.to_string(),
+        Some(url::Host::Ipv6(ip)) => ip.to_string(),
+        None => return Err(invalid(format!("{url} has no host"))),
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let tcp = TcpStream::connect((host.as_str(), port)).await?;
+    tcp.set_nodelay(true)?;
+    let stream: Box<dyn Stream> = match tls {
+        true => {
+            let name = ServerName::try_from(host).map_err(invalid)?;
+            let connection = ClientConnection::new(tls_config(), name).map_err(invalid)?;
+            Box::new(TlsStream { tcp, connection })
+        }
+        false => Box::new(tcp),
+    };
+    let mut stream = BufReader::new(stream);
+
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept-Encoding: identity\r\n",
+        &url[url::Position::BeforePath..url::Position::AfterQuery],
+        &url[url::Position::BeforeHost..url::Position::AfterPort],
+    );
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("User-Agent"))
+    {
+        head.push_str(concat!(
+            "User-Agent: wiki-crawler/",
+            env!("CARGO_PKG_VERSION"),
+            "\r\n"
+        ));
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.get_mut().write_all(head.as_bytes()).await?;
+    stream.get_mut().flush().await?;
+
+    // The headers end at a blank line.
+    let mut raw = Vec::new();
+    loop {
+        let start = raw.len();
+        if stream.read_until(b'\n', &mut raw).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{url} hung up before answering"),
+            ));
+        }
+        if matches!(&raw[start..], b"\r\n" | b"\n") {
+            break;
+        }
+    }
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut parsed);
+    response.parse(&raw).map_err(invalid)?;
+    let status = response.code.unwrap_or_default();
+    let reason = response.reason.unwrap_or_default().to_owned();
+    let headers: Vec<_> = response
+        .headers
+        .iter()
+        .map(|header| {
+            let value = String::from_utf8_lossy(header.value).into_owned();
+            (header.name.to_owned(), value)
+        })
+        .collect();
+
+    let mut response = Response {
+        status,
+        reason,
+        headers,
+        body: Body::Close,
+        stream,
+    };
+    response.body = match response.header("Transfer-Encoding") {
+        Some(coding) if coding.to_ascii_lowercase().contains("chunked") => Body::Chunked(None),
+        _ => match response.header("Content-Length") {
+            Some(len) => Body::Length(
+                len.trim()
+                    .parse()
+                    .map_err(|_| invalid(format!("{len:?} isn't a length")))?,
+            ),
+            None if status == 204 || status == 304 => Body::Length(0),
+            None => Body::Close,
+        },
+    };
+    Ok(response)
+}
+
+/// Reads a line, without its line break.
+async fn read_line<R: AsyncBufReadExt + Unpin>(stream: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+fn invalid(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Hands over a body streamed by [`Response::into_reader`].
+struct ChannelReader {
+    receiver: mpsc::Receiver<io::Result<Option<Vec<u8>>>>,
+    chunk: Vec<u8>,
+    at: usize,
+    done: bool,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.at == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.receiver.blocking_recv() {
+                Some(Ok(Some(chunk))) => (self.chunk, self.at) = (chunk, 0),
+                Some(Ok(None)) => self.done = true,
+                Some(Err(e)) => return Err(e),
+                None => return Err(io::Error::other("the download stopped")),
+            }
+        }
+        let read = buf.len().min(self.chunk.len() - self.at);
+        buf[..read].copy_from_slice(&self.chunk[self.at..self.at + read]);
+        self.at += read;
+        Ok(read)
+    }
+}
+
+/// A connection a request can go over, with or without TLS.
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let mut config = ClientConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .expect("ring supports the default protocol versions")
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+            Arc::new(config)
+        })
+        .clone()
+}
+
+/// A TLS connection over a socket, driving rustls as the socket is ready.
+struct TlsStream {
+    tcp: TcpStream,
+    connection: ClientConnection,
+}
+
+impl TlsStream {
+    /// Sends what rustls has waiting, as far as the socket will take it.
+    fn poll_send(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while self.connection.wants_write() {
+            let mut socket = Socket {
+                tcp: &mut self.tcp,
+                cx,
+            };
+            match self.connection.write_tls(&mut socket) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for TlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.connection.reader().read(buf.initialize_unfilled()) {
+                Ok(read) => {
+                    buf.advance(read);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+
+            // The handshake has its own messages to send before there's anything to read.
+            if let Poll::Ready(Err(e)) = this.poll_send(cx) {
+                return Poll::Ready(Err(e));
+            }
+            let mut socket = Socket {
+                tcp: &mut this.tcp,
+                cx,
+            };
+            match this.connection.read_tls(&mut socket) {
+                Ok(_) => {
+                    this.connection.process_new_packets().map_err(invalid)?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = this.connection.writer().write(buf)?;
+        // What the socket won't take yet goes out on the next flush.
+        if let Poll::Ready(Err(e)) = this.poll_send(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.connection.writer().flush()?;
+        ready!(this.poll_send(cx))?;
+        Pin::new(&mut this.tcp).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.connection.send_close_notify();
+        ready!(this.poll_send(cx))?;
+        Pin::new(&mut this.tcp).poll_shutdown(cx)
+    }
+}
+
+/// A socket as the blocking reader and writer rustls expects: an operation that would wait
+/// fails with `WouldBlock`, having asked to be woken when it can go on.
+struct Socket<'a, 'b> {
+    tcp: &'a mut TcpStream,
+    cx: &'a mut Context<'b>,
+}
+
+impl Read for Socket<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buf = ReadBuf::new(buf);
+        match Pin::new(&mut *self.tcp).poll_read(self.cx, &mut buf) {
+            Poll::Ready(Ok(())) => Ok(buf.filled().len()),
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl Write for Socket<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.tcp).poll_write(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match Pin::new(&mut *self.tcp).poll_flush(self.cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufRead, net::TcpListener, sync::mpsc, thread};
+
+    use super::*;
+
+    /// Answers a connection with each of `responses` in turn, sending back the request lines.
+    fn serve(responses: &[&'static str]) -> (url::Url, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        let responses = responses.to_vec();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = io::BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while request.read_line(&mut line).unwrap() > 2 {
+                    // Tests that don't look at the request have dropped the receiver.
+                    sender.send(line.trim_end().to_owned()).ok();
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url::Url::parse(&url).unwrap(), receiver)
+    }
+
+    fn fetch(url: &url::Url) -> io::Result<(u16, Vec<u8>)> {
+        runtime().block_on(async {
+            let response = get(url, &[("Range", "bytes=3-".to_owned())]).await?;
+            Ok((response.status, response.bytes().await?))
+        })
+    }
+
+    #[test]
+    fn reads_bodies_however_their_length_is_given() {
+        let (url, requests) = serve(&[
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\r\nhello, and more",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             5;name=value\r\nhello\r\n7\r\n, world\r\n0\r\nExpires: never\r\n\r\n",
+            "HTTP/1.0 200 OK\r\n\r\nto the end",
+        ]);
+        assert_eq!(fetch(&url).unwrap(), (206, b"hello".to_vec()));
+        assert_eq!(fetch(&url).unwrap(), (200, b"hello, world".to_vec()));
+        assert_eq!(fetch(&url).unwrap(), (200, b"to the end".to_vec()));
+
+        let request: Vec<_> = requests.try_iter().take(6).collect();
+        assert_eq!(request[0], "GET / HTTP/1.1");
+        assert_eq!(request[1], format!("Host: {}", url.authority()));
+        assert!(request.contains(&"Range: bytes=3-".to_owned()));
+    }
+
+    #[test]
+    fn follows_redirects() {
+        let (url, requests) = serve(&[
+            "HTTP/1.1 302 Found\r\nLocation: /dumps/latest?page=2\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ndump",
+        ]);
+        assert_eq!(fetch(&url).unwrap(), (200, b"dump".to_vec()));
+        let paths: Vec<_> = requests
+            .try_iter()
+            .filter(|line| line.starts_with("GET"))
+            .collect();
+        assert_eq!(
+            paths,
+            ["GET / HTTP/1.1", "GET /dumps/latest?page=2 HTTP/1.1"]
+        );
+    }
+
+    #[test]
+    fn notices_a_body_cut_short() {
+        let (url, _) = serve(&["HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello"]);
+        let e = fetch(&url).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn streams_bodies_to_a_reader() {
+        let (url, _) = serve(&["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n"]);
+        let response = runtime().block_on(get(&url, &[])).unwrap();
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "abcdef");
+    }
+}
@@ -0,0 +1,59 @@
+use std::cmp::Ordering;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// number of titles to list in each ranking
+    #[clap(short = 'n', long, default_value_t = 20)]
+    top: usize,
+    /// also compute HITS hub and authority scores
+    #[clap(long)]
+    hits: bool,
+    /// number of HITS iterations
+    #[clap(short, long, default_value_t = 50)]
+    iterations: usize,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+
+    let in_degrees = graph.in_degrees();
+    let out_degrees: Vec<_> = (0..graph.len() as u32)
+        .map(|id| graph.links(id).len())
+        .collect();
+
+    println!("most linked to");
+    print_top(&graph, &in_degrees, args.top, usize::cmp);
+    println!();
+    println!("most linking");
+    print_top(&graph, &out_degrees, args.top, usize::cmp);
+
+    if args.hits {
+        let (hubs, authorities) = graph.hits(args.iterations);
+        println!();
+        println!("authorities");
+        print_top(&graph, &authorities, args.top, f64::total_cmp);
+        println!();
+        println!("hubs");
+        print_top(&graph, &hubs, args.top, f64::total_cmp);
+    }
+
+    Ok(())
+}
+
+/// Lists the pages with the highest scores, as ordered by `order`, which for scores that can
+/// be NaN has to be a total order.
+fn print_top<T>(graph: &Graph, scores: &[T], top: usize, order: fn(&T, &T) -> Ordering)
+where
+    T: Copy + std::fmt::Display,
+{
+    let mut ranked: Vec<_> = scores.iter().copied().enumerate().collect();
+    ranked.sort_by(|a, b| order(&b.1, &a.1).then(a.0.cmp(&b.0)));
+
+    for (id, score) in ranked.into_iter().take(top) {
+        println!("{score:.4}\t{}", graph.title(id as u32));
+    }
+}
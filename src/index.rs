@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+use tantivy::{
+    doc,
+    schema::{Schema, STORED, TEXT},
+    Index,
+};
+use tracing::info;
+
+use crate::{
+    memory::ByteSize,
+    page,
+    plain::{self, PlainText},
+    progress::Bar,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to index
+    dump: String,
+    /// directory to build the index in; must be empty or not yet exist
+    index: PathBuf,
+    /// memory for the index writer, e.g. 512M
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+    /// don't show a progress bar
+    #[clap(long)]
+    no_progress: bool,
+}
+
+/// Builds a full-text index of every article's title and cleaned text.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    fs::create_dir_all(&args.index)?;
+    let index = Index::create_in_dir(&args.index, schema())?;
+    let schema = index.schema();
+    let title = schema.get_field("title")?;
+    let body = schema.get_field("body")?;
+
+    let budget = args.max_memory.map_or(256 << 20, |size| size.0);
+    let mut writer = index.writer(budget)?;
+    let plain = PlainText::new();
+
+    let pages = page::read_pages(&args.dump)?;
+    let bar =
+        (io::stderr().is_terminal() && !args.no_progress).then(|| Bar::spawn(pages.progress()));
+
+    let mut indexed = 0;
+    for page in pages {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+        // Headings are just more text as far as search is concerned.
+        let text: Vec<_> = plain
+            .convert(text)
+            .lines()
+            .map(|line| plain::heading(line).map_or(line, |(_, heading)| heading))
+            .map(String::from)
+            .collect();
+        writer.add_document(doc!(
+            title => page.title.as_str(),
+            body => text.join("\n"),
+        ))?;
+        indexed += 1;
+    }
+    writer.commit()?;
+
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+    info!("indexed {indexed} pages in {}", args.index.display());
+    Ok(())
+}
+
+/// Both fields are stored so that searches can show titles and snippets.
+pub fn schema() -> Schema {
+    let mut schema = Schema::builder();
+    schema.add_text_field("title", TEXT | STORED);
+    schema.add_text_field("body", TEXT | STORED);
+    schema.build()
+}
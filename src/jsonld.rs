@@ -0,0 +1,131 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use serde_json::{json, Map, Value};
+use tracing::info;
+use wiki_crawler::title::Rules;
+
+use crate::{
+    extract,
+    filter::{LinkExtractor, TextFilter},
+    page,
+    plain::{self, PlainText},
+    rdf,
+    titles::article_url,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// list everything each page links to, rather than only its first link
+    #[clap(long)]
+    all_links: bool,
+    /// prefix for article URLs; defaults to the one in the dump's site information
+    #[clap(long)]
+    base_url: Option<String>,
+}
+
+/// Writes a schema.org `Article` in JSON-LD for each article in a dump, one per line: its
+/// title and URL, its lead section as the abstract, its categories as the collections it's
+/// part of, and its first link as its most significant, or all its links with
+/// `--all-links`. Redirects and disambiguation pages are left out.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let site = page::site_info(&args.dump)?;
+    let base = match &args.base_url {
+        Some(base) => base.clone(),
+        None => site.base.clone().ok_or_else(|| {
+            anyhow::anyhow!("the dump has no base URL in its site information; pass --base-url")
+        })?,
+    };
+    let rules = Rules::new().with_namespaces(&site.namespaces);
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+    let plain = PlainText::new();
+
+    let mut written = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        if page.title.ends_with("(disambiguation)") {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let url = article_url(&base, &page.title);
+        let mut record = Map::new();
+        record.insert("@context".into(), json!("https://schema.org"));
+        record.insert("@type".into(), json!("Article"));
+        record.insert("@id".into(), json!(url));
+        record.insert("url".into(), json!(url));
+        record.insert("name".into(), json!(page.title));
+        if let Some(id) = page.id {
+            record.insert("identifier".into(), json!(id));
+        }
+        if let Some(lang) = &site.lang {
+            record.insert("inLanguage".into(), json!(lang));
+        }
+        if let Some(timestamp) = page.latest().and_then(|rev| rev.timestamp.as_deref()) {
+            record.insert("dateModified".into(), json!(timestamp));
+        }
+
+        let lead = plain::sections(&plain.convert(text))
+            .into_iter()
+            .next()
+            .filter(|section| section.path.is_empty());
+        if let Some(lead) = lead {
+            record.insert("abstract".into(), json!(lead.text));
+        }
+
+        let categories: Vec<Value> = rdf::categories(&rules, text)
+            .iter()
+            .map(|category| {
+                json!({
+                    "@type": "CollectionPage",
+                    "@id": article_url(&base, category),
+                    "name": category,
+                })
+            })
+            .collect();
+        if !categories.is_empty() {
+            record.insert("isPartOf".into(), Value::Array(categories));
+        }
+
+        let links = extract::page_links(&tf, &ex, &page, args.all_links);
+        if let Some(first) = links.first() {
+            record.insert(
+                "significantLink".into(),
+                json!(article_url(&base, &first.target)),
+            );
+        }
+        if args.all_links && !links.is_empty() {
+            let mut related: Vec<String> = Vec::new();
+            for link in &links {
+                let url = article_url(&base, &link.target);
+                if !related.contains(&url) {
+                    related.push(url);
+                }
+            }
+            record.insert("relatedLink".into(), json!(related));
+        }
+
+        writeln!(out, "{}", Value::Object(record))?;
+        written += 1;
+    }
+    out.flush()?;
+
+    info!("wrote {written} JSON-LD records");
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::anyhow;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// first-link map produced by the extract command
+    map: String,
+    /// title at which chains end
+    #[clap(short, long, default_value = "Philosophy")]
+    target: String,
+    /// write a length,count histogram to this file
+    #[clap(long)]
+    histogram: Option<String>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.map)?;
+    let target = graph
+        .id(&args.target)
+        .ok_or_else(|| anyhow!("unknown title: {}", args.target))?;
+
+    let lengths = graph.chain_lengths(target);
+    let pages = lengths.len();
+    let mut reached: Vec<u32> = lengths.into_iter().flatten().collect();
+    reached.sort_unstable();
+
+    println!("pages: {pages}");
+    println!("reached {}: {}", args.target, reached.len());
+    println!("unreached: {}", pages - reached.len());
+
+    if let Some(&max) = reached.last() {
+        let mean = reached.iter().map(|&len| len as f64).sum::<f64>() / reached.len() as f64;
+        println!("mean: {mean:.2}");
+        println!("median: {}", median(&reached));
+        println!("max: {max}");
+    }
+
+    if let Some(path) = &args.histogram {
+        let mut histogram = vec![0usize; reached.last().map_or(0, |&max| max as usize + 1)];
+        for &len in &reached {
+            histogram[len as usize] += 1;
+        }
+
+        let mut out = File::create(path).map(BufWriter::new)?;
+        writeln!(out, "length,count")?;
+        for (len, count) in histogram.into_iter().enumerate() {
+            writeln!(out, "{len},{count}")?;
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+fn median(sorted: &[u32]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
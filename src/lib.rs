@@ -0,0 +1,9 @@
+//! The parts of the crawler that other tools can use to agree with it.
+
+pub mod error;
+#[cfg(feature = "petgraph")]
+pub mod links;
+pub mod model;
+pub mod title;
+
+pub use error::Error;
@@ -0,0 +1,82 @@
+//! Link graphs as petgraph's types, for running graph algorithms beyond the crawler's own
+//! over the links it extracts, without writing them out and reading them back in.
+//!
+//! Nodes are weighted with the titles they stand for, normalized as [`crate::title::normalize`]
+//! does, and edges with their place among the links of the page they leave, so that the edge
+//! weighted 0 is the page's first link.
+
+use std::collections::HashMap;
+
+use petgraph::{csr::Csr, graph::NodeIndex, Graph};
+
+use crate::title::normalize;
+
+/// Builds a graph from `(source, target)` pairs of titles, in the order the links appear on
+/// their pages. Nodes are numbered in the order their titles are first seen, and links that
+/// appear more than once on a page are kept as parallel edges.
+pub fn graph<S: AsRef<str>>(edges: impl IntoIterator<Item = (S, S)>) -> Graph<String, u32> {
+    let mut graph = Graph::new();
+    let mut numbering = Numbering::default();
+    let mut ranks: Vec<u32> = Vec::new();
+    for (source, target) in edges {
+        let source = numbering.id(source.as_ref(), |title| graph.add_node(title).index());
+        let target = numbering.id(target.as_ref(), |title| graph.add_node(title).index());
+        ranks.resize(graph.node_count(), 0);
+        graph.add_edge(
+            NodeIndex::new(source),
+            NodeIndex::new(target),
+            ranks[source],
+        );
+        ranks[source] += 1;
+    }
+    graph
+}
+
+/// Builds a graph in compressed sparse row form from `(source, target)` pairs of titles, as
+/// for [`graph`]. This takes less memory and is quicker to walk, but a node's edges are
+/// sorted by target rather than kept in order, and each page links to another once, weighted
+/// with the place of its first link there.
+pub fn csr<S: AsRef<str>>(edges: impl IntoIterator<Item = (S, S)>) -> Csr<String, u32> {
+    let mut titles = Vec::new();
+    let mut numbering = Numbering::default();
+    let mut ranks: Vec<u32> = Vec::new();
+    let mut pairs = Vec::new();
+    for (source, target) in edges {
+        let mut add = |title| {
+            titles.push(title);
+            titles.len() - 1
+        };
+        let source = numbering.id(source.as_ref(), &mut add);
+        let target = numbering.id(target.as_ref(), &mut add);
+        ranks.resize(titles.len(), 0);
+        pairs.push((source as u32, target as u32, ranks[source]));
+        ranks[source] += 1;
+    }
+
+    pairs.sort_unstable();
+    pairs.dedup_by_key(|&mut (source, target, _)| (source, target));
+    let mut csr = Csr::from_sorted_edges(&pairs).expect("edges are sorted and unique");
+    for (id, title) in titles.into_iter().enumerate() {
+        csr[id as u32] = title;
+    }
+    csr
+}
+
+/// The ids handed out to titles so far.
+#[derive(Default)]
+struct Numbering {
+    ids: HashMap<String, usize>,
+}
+
+impl Numbering {
+    /// The id of a title, adding a node for it with `add` if it doesn't have one yet.
+    fn id(&mut self, title: &str, add: impl FnOnce(String) -> usize) -> usize {
+        let title = normalize(title);
+        if let Some(&id) = self.ids.get(&title) {
+            return id;
+        }
+        let id = add(title.clone());
+        self.ids.insert(title, id);
+        id
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    cell::Cell,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::cache::{Cache, CachedPage};
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Options for talking to a MediaWiki API politely.
+#[derive(Debug, clap::Args)]
+pub struct ClientArgs {
+    /// MediaWiki API endpoint
+    #[clap(long, default_value = "https://en.wikipedia.org/w/api.php")]
+    api: String,
+    /// maximum requests per second
+    #[clap(long, default_value_t = 1.0)]
+    rate: f64,
+    /// number of times to retry a throttled or failed request
+    #[clap(long, default_value_t = 5)]
+    retries: u32,
+    /// User-Agent header; Wikimedia asks that this include contact details
+    #[clap(long, default_value = concat!(
+        "wiki-crawler/",
+        env!("CARGO_PKG_VERSION"),
+        " (https://github.com/archer884/wiki-crawler)"
+    ))]
+    user_agent: String,
+    /// seconds of replication lag at which the server should refuse requests
+    #[clap(long, default_value_t = 5)]
+    maxlag: u32,
+    /// directory for cached pages; defaults to the user's cache directory
+    #[clap(long)]
+    cache_dir: Option<PathBuf>,
+    /// don't read or write the page cache
+    #[clap(long)]
+    no_cache: bool,
+    /// fetch every page again, updating the cache
+    #[clap(long)]
+    refresh: bool,
+}
+
+/// Fetches current page wikitext from the MediaWiki action API.
+pub struct Client {
+    #[cfg(not(feature = "async"))]
+    agent: ureq::Agent,
+    #[cfg(feature = "async")]
+    user_agent: String,
+    api: String,
+    interval: Duration,
+    retries: u32,
+    maxlag: u32,
+    last_request: Cell<Option<Instant>>,
+    cache: Option<Cache>,
+    refresh: bool,
+}
+
+/// An answer from the API, whatever its status.
+struct Reply {
+    status: u16,
+    retry_after: Option<Duration>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LivePage {
+    /// The canonical title, after normalization and redirects.
+    pub title: String,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    error: Option<ApiError>,
+    query: Option<Query>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: String,
+    info: String,
+}
+
+#[derive(Deserialize)]
+struct Query {
+    pages: Vec<ApiPage>,
+}
+
+#[derive(Deserialize)]
+struct ApiPage {
+    title: String,
+    #[serde(default)]
+    missing: bool,
+    #[serde(default)]
+    revisions: Vec<ApiRevision>,
+}
+
+#[derive(Deserialize)]
+struct ApiRevision {
+    revid: u64,
+    slots: Slots,
+}
+
+#[derive(Deserialize)]
+struct Slots {
+    main: Slot,
+}
+
+#[derive(Deserialize)]
+struct Slot {
+    content: String,
+}
+
+impl Client {
+    pub fn new(args: &ClientArgs) -> anyhow::Result<Self> {
+        let cache = if args.no_cache {
+            None
+        } else {
+            Cache::open(args.cache_dir.as_deref(), &args.api)
+                .context("failed to open page cache")?
+        };
+
+        let interval = if args.rate > 0.0 {
+            Duration::from_secs_f64(1.0 / args.rate)
+        } else {
+            Duration::ZERO
+        };
+
+        Ok(Self {
+            #[cfg(not(feature = "async"))]
+            agent: ureq::AgentBuilder::new()
+                .user_agent(&args.user_agent)
+                .build(),
+            #[cfg(feature = "async")]
+            user_agent: args.user_agent.clone(),
+            api: args.api.clone(),
+            interval,
+            retries: args.retries,
+            maxlag: args.maxlag,
+            last_request: Cell::new(None),
+            cache,
+            refresh: args.refresh,
+        })
+    }
+
+    /// Fetches a page's latest revision, following redirects. Returns `None` if the page
+    /// doesn't exist.
+    pub fn fetch(&self, title: &str) -> anyhow::Result<Option<LivePage>> {
+        if let (Some(cache), false) = (&self.cache, self.refresh) {
+            if let Some(page) = cache.get(title)? {
+                return Ok(Some(LivePage {
+                    title: page.title,
+                    text: page.text,
+                }));
+            }
+        }
+
+        let response = self.query(title)?;
+        let page = match response
+            .query
+            .and_then(|query| query.pages.into_iter().next())
+        {
+            Some(page) if !page.missing => page,
+            _ => return Ok(None),
+        };
+
+        let revision = match page.revisions.into_iter().next() {
+            Some(revision) => revision,
+            None => return Ok(None),
+        };
+
+        let page = CachedPage {
+            title: page.title,
+            revid: revision.revid,
+            text: revision.slots.main.content,
+        };
+        if let Some(cache) = &self.cache {
+            cache.put(title, &page)?;
+        }
+
+        Ok(Some(LivePage {
+            title: page.title,
+            text: page.text,
+        }))
+    }
+
+    /// Sends a query, backing off and retrying on throttling, server errors and lag.
+    fn query(&self, title: &str) -> anyhow::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            self.throttle();
+            debug!("fetching {title}");
+
+            let maxlag = self.maxlag.to_string();
+            let params = [
+                ("action", "query"),
+                ("prop", "revisions"),
+                ("rvprop", "ids|content"),
+                ("rvslots", "main"),
+                ("redirects", "1"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("maxlag", &maxlag),
+                ("titles", title),
+            ];
+
+            let (retry_after, reason) = match self.get(&params) {
+                Ok(reply) if (200..300).contains(&reply.status) => {
+                    let response: Response = serde_json::from_slice(&reply.body)
+                        .with_context(|| format!("unexpected response for {title}"))?;
+
+                    match &response.error {
+                        Some(error) if error.code == "maxlag" => {
+                            (reply.retry_after, error.info.clone())
+                        }
+                        Some(error) => bail!("failed to fetch {title}: {}", error.info),
+                        None => return Ok(response),
+                    }
+                }
+                Ok(reply) if reply.status == 429 || reply.status >= 500 => {
+                    (reply.retry_after, format!("status {}", reply.status))
+                }
+                Ok(reply) => bail!("failed to fetch {title}: status {}", reply.status),
+                Err(e) => (None, e),
+            };
+
+            if attempt >= self.retries {
+                bail!("failed to fetch {title} after {attempt} retries: {reason}");
+            }
+
+            let delay = retry_after
+                .unwrap_or_default()
+                .max(BASE_DELAY * 2u32.saturating_pow(attempt));
+            warn!("retrying {title} in {delay:?}: {reason}");
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request to the API, failing only when no answer comes back at all.
+    #[cfg(not(feature = "async"))]
+    fn get(&self, params: &[(&str, &str)]) -> Result<Reply, String> {
+        let request = params
+            .iter()
+            .fold(self.agent.get(&self.api), |request, (name, value)| {
+                request.query(name, value)
+            });
+        let response = match request.call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(e)) => return Err(e.to_string()),
+        };
+        let status = response.status();
+        let retry_after = retry_after(response.header("Retry-After"));
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        Ok(Reply {
+            status,
+            retry_after,
+            body,
+        })
+    }
+
+    /// Sends a request to the API on the async runtime, failing only when no answer comes back
+    /// at all.
+    #[cfg(feature = "async")]
+    fn get(&self, params: &[(&str, &str)]) -> Result<Reply, String> {
+        let url = url::Url::parse_with_params(&self.api, params).map_err(|e| e.to_string())?;
+        let headers = [("User-Agent", self.user_agent.clone())];
+        crate::http::runtime()
+            .block_on(async {
+                let response = crate::http::get(&url, &headers).await?;
+                let status = response.status;
+                let retry_after = retry_after(response.header("Retry-After"));
+                let body = response.bytes().await?;
+                Ok(Reply {
+                    status,
+                    retry_after,
+                    body,
+                })
+            })
+            .map_err(|e: std::io::Error| e.to_string())
+    }
+
+    /// Sleeps as long as necessary to keep under the configured request rate.
+    fn throttle(&self) {
+        if let Some(last) = self.last_request.get() {
+            let elapsed = last.elapsed();
+            if elapsed < self.interval {
+                thread::sleep(self.interval - elapsed);
+            }
+        }
+        self.last_request.set(Some(Instant::now()));
+    }
+}
+
+fn retry_after(header: Option<&str>) -> Option<Duration> {
+    let seconds = header?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
@@ -0,0 +1,23 @@
+use std::cmp::Reverse;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// first-link map produced by the extract command
+    map: String,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.map)?;
+    let mut loops = graph.loops();
+    loops.sort_by_key(|cycle| Reverse(cycle.feeders));
+
+    for cycle in loops {
+        let mut members: Vec<_> = cycle.members.iter().map(|&id| graph.title(id)).collect();
+        members.push(members[0]);
+        println!("{}\t{}", cycle.feeders, members.join(" -> "));
+    }
+
+    Ok(())
+}
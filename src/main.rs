@@ -1,182 +1,289 @@
-use std::{
-    fmt,
-    fs::{File},
-    io::{self, BufRead, BufReader},
-    ops::Not,
-    process,
-};
+mod abstracts;
+mod backlinks;
+mod betweenness;
+mod cache;
+mod chain;
+mod checkpoint;
+mod components;
+mod compress;
+mod contributors;
+mod corpus;
+mod csv;
+mod deadends;
+mod definitions;
+mod dictionary;
+mod diff;
+mod distances;
+mod documents;
+mod duplicates;
+mod exit;
+mod explore;
+mod extract;
+mod features;
+mod filter;
+mod fixture;
+mod freq;
+mod graph;
+mod history;
+#[cfg(feature = "async")]
+mod http;
+mod hubs;
+mod index;
+mod jsonld;
+mod lengths;
+mod live;
+mod loops;
+mod memory;
+mod merge;
+mod metrics;
+mod ngrams;
+mod number;
+mod orphans;
+mod page;
+mod pagelinks;
+mod parts;
+mod path;
+mod plain;
+mod progress;
+mod rank;
+mod rdf;
+mod remote;
+mod search;
+mod sentences;
+mod serve;
+mod sink;
+mod sort;
+mod sql;
+mod talk;
+mod targets;
+mod template_usage;
+mod templates;
+mod terms;
+mod titles;
+mod tui;
+mod walk;
+mod warc;
+mod wikidata;
+mod wikitext;
+mod wiktionary;
 
-use clap::Parser;
-use regex::Regex;
-use serde::Deserialize;
-use serde_xml_rs as xml;
+use std::process;
+
+use clap::{Parser, Subcommand};
+use tracing::Level;
+use tracing_subscriber::{filter::Targets, prelude::*};
 
 #[derive(Debug, Parser)]
+#[clap(after_help = exit::HELP)]
 struct Args {
-    path: String,
-}
-
-#[derive(Deserialize)]
-struct Page {
-    title: String,
-    revision: Vec<Revision>,
-}
-
-impl Page {
-    fn text(&self) -> Option<&str> {
-        let candidate = &self.revision.first()?.text;
-        candidate
-            .starts_with("#REDIRECT")
-            .not()
-            .then_some(candidate)
-    }
+    #[clap(subcommand)]
+    command: Command,
+    /// log more detail; repeat for even more
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// log only errors
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// write logs as JSON lines
+    #[clap(long, global = true)]
+    log_json: bool,
+    /// stop at the first page that can't be read or parsed rather than skipping it
+    #[clap(long, global = true)]
+    fail_fast: bool,
+    /// check revision text against the SHA-1 in the dump, skipping pages that don't match
+    #[clap(long, global = true)]
+    verify_sha1: bool,
 }
 
-#[derive(Deserialize)]
-struct Revision {
-    text: String,
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// extract the first link of each page in a dump
+    Extract(Box<extract::Args>),
+    /// extract links from a dump split over several files, running the parts side by side
+    Parts(parts::Args),
+    /// list pages in a dump from which no link can be extracted
+    Deadends(deadends::Args),
+    /// follow first links from a title to a target
+    Chain(chain::Args),
+    /// list the pages that link to a title
+    Backlinks(backlinks::Args),
+    /// compute distances from a seed title to every reachable page
+    Distances(distances::Args),
+    /// report strongly or weakly connected components
+    Components(components::Args),
+    /// report the distribution of chain lengths to a target
+    Lengths(lengths::Args),
+    /// rank pages by links in and out, and optionally by HITS score
+    Hubs(hubs::Args),
+    /// rank the most common link targets, overall and in each namespace
+    Targets(targets::Args),
+    /// report loops in the first-link graph
+    Loops(loops::Args),
+    /// list pages that nothing links to
+    Orphans(orphans::Args),
+    /// find the shortest path between two titles
+    Path(path::Args),
+    /// rank pages by PageRank
+    Rank(rank::Args),
+    /// estimate betweenness centrality from sampled source pages
+    Betweenness(betweenness::Args),
+    /// count page visits over random walks
+    Walk(walk::Args),
+    /// compare link graphs from two dumps
+    Diff(diff::Args),
+    /// apply a dump of changed pages to an existing link graph
+    Merge(merge::Args),
+    /// answer graph queries over HTTP
+    Serve(serve::Args),
+    /// step through first links interactively
+    Explore(explore::Args),
+    /// sort an edge list and drop duplicate edges
+    Sort(sort::Args),
+    /// rewrite a link graph as integer ids with an on-disk title dictionary
+    Number(number::Args),
+    /// write a link graph in a compact form the analysis commands can read
+    Compress(compress::Args),
+    /// copy chosen or sampled pages from a dump into a small standalone dump
+    Fixture(fixture::Args),
+    /// build a full-text search index over the articles in a dump
+    Index(index::Args),
+    /// search an index built by the index command
+    Search(search::Args),
+    /// export cleaned article text as chunked JSON records
+    Corpus(corpus::Args),
+    /// export each article's id, title and cleaned lead or full text as JSON records
+    Documents(documents::Args),
+    /// export cleaned article text one sentence per line
+    Sentences(sentences::Args),
+    /// count word frequencies across the articles in a dump
+    Freq(freq::Args),
+    /// count n-grams across the articles in a dump
+    Ngrams(ngrams::Args),
+    /// count how often each template is used across a dump, and how often it's expanded
+    TemplateUsage(template_usage::Args),
+    /// find clusters of near-duplicate articles in a dump
+    Duplicates(duplicates::Args),
+    /// measure the structure of each article in a dump as CSV
+    Features(features::Args),
+    /// list who made each revision in a dump as CSV
+    Contributors(contributors::Args),
+    /// chart what each revision in a history dump added and removed as CSV
+    History(history::Args),
+    /// stream sitelinks, labels or claims from a Wikidata entity dump
+    Wikidata(wikidata::Args),
+    /// build the link graph from the pagelinks table of a SQL dump
+    Pagelinks(pagelinks::Args),
+    /// export the article summaries in an abstract dump as JSON records
+    Abstracts(abstracts::Args),
+    /// list the terms set in bold in the first sentence of each article
+    Terms(terms::Args),
+    /// export each article's subject and defining first sentence as JSON records
+    Definitions(definitions::Args),
+    /// export the entries of a Wiktionary dump as JSON records
+    Wiktionary(wiktionary::Args),
+    /// export the discussions on talk pages as JSON records, one per thread
+    Talk(talk::Args),
+    /// export links, categories and coordinates as N-Triples with DBpedia-style IRIs
+    Rdf(rdf::Args),
+    /// export each article's title, abstract, categories and links as schema.org JSON-LD
+    Jsonld(jsonld::Args),
+    /// export pages as WARC records, rendered as HTML or as wikitext
+    Warc(warc::Args),
 }
 
-impl fmt::Debug for Page {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Page").field("title", &self.title).finish()
-    }
-}
-
-struct PageBuffer<T> {
-    reader: T,
-}
-
-impl<T> PageBuffer<T>
-where
-    T: BufRead,
-{
-    fn new(reader: T) -> Self {
-        Self { reader }
-    }
-}
-
-impl<T> Iterator for PageBuffer<T>
-where
-    T: BufRead,
-{
-    type Item = io::Result<String>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut take = false;
-        let mut buf = String::new();
-
-        for line in self.reader.by_ref().lines() {
-            let text = match line {
-                Ok(text) => text,
-                Err(e) => return Some(Err(e)),
-            };
-
-            if text.trim() == "<page>" {
-                take = true;
-                buf += &text;
-                buf += "\n";
-                continue;
-            }
-
-            if text.trim() == "</page>" {
-                buf += &text;
-                buf += "\n";
-                return Some(Ok(buf));
-            }
-
-            if take {
-                buf += &text;
-                buf += "\n";
-            }
-        }
+fn main() {
+    let args = Args::parse();
+    init_logging(&args);
+    exit::set_fail_fast(args.fail_fast);
+    page::set_verify_sha1(args.verify_sha1);
 
-        buf.is_empty().not().then_some(Ok(buf))
+    let run = run(&args)
+        .and_then(|()| Ok(exit::stopped()?))
+        .and_then(|()| Ok(exit::read_error()?));
+    if let Err(e) = run {
+        tracing::error!("{e:#}");
+        process::exit(exit::code(&e));
     }
-}
 
-#[derive(Debug)]
-struct TextFilter {
-    braces: Regex,
-    parens: Regex,
-    source: Regex,
-}
-
-impl TextFilter {
-    fn new() -> Self {
-        Self {
-            braces: Regex::new(r#"(?sm)\{\{.*?\}\}"#).unwrap(),
-            parens: Regex::new(r#"\(.+?\)"#).unwrap(),
-            source: Regex::new(r#"<ref>.+?</ref>"#).unwrap(),
-        }
+    if exit::incomplete() {
+        process::exit(exit::INCOMPLETE);
     }
-
-    fn filter(&self, text: &str) -> String {
-        let text = self.parens.replace_all(&text, "");
-        let text = self.braces.replace_all(&text, "");
-        let text = self.source.replace_all(&text, "");
-        text.into()
+    let skipped = exit::skipped();
+    if skipped > 0 {
+        tracing::warn!("skipped {skipped} pages that couldn't be read or parsed");
+        process::exit(exit::PARTIAL);
     }
+    process::exit(exit::SUCCESS);
 }
 
-#[derive(Debug)]
-struct LinkExtractor {
-    expr: Regex,
-}
+fn init_logging(args: &Args) {
+    let level = match (args.quiet, args.verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::INFO,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
 
-impl LinkExtractor {
-    fn new() -> Self {
-        Self {
-            expr: Regex::new(r#"\[\[([^|]+?)(\|.+)?\]\]"#).unwrap(),
-        }
-    }
-
-    fn extract<'a>(&self, text: &'a str) -> Option<&'a str> {
-        let paragraphs = text
-            .lines()
-            .filter(|&text| text.starts_with(|u: char| u.is_alphanumeric() || u == '\''));
-
-        let candidates = paragraphs.flat_map(|paragraph| {
-            self.expr
-                .captures_iter(paragraph)
-                .filter_map(|cx| cx.get(1).map(|cx| cx.as_str()))
-        });
-
-        for candidate in candidates {
-            // if candidate.starts_with("File:") {
-            //     continue;
-            // }
-
-            return Some(candidate);
-        }
-
-        None
-    }
-}
-
-fn main() {
-    if let Err(e) = run(&Args::parse()) {
-        eprintln!("{e}");
-        process::exit(1);
+    // Dependencies are chatty at debug level, so verbosity only applies to our own logs.
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(level.min(Level::WARN));
+    let layer = tracing_subscriber::fmt::layer().with_writer(tui::log_writer);
+    let registry = tracing_subscriber::registry().with(filter);
+    if args.log_json {
+        registry.with(layer.json()).init();
+    } else {
+        registry.with(layer).init();
     }
 }
 
 fn run(args: &Args) -> anyhow::Result<()> {
-    let tf = TextFilter::new();
-    let ex = LinkExtractor::new();
-
-    let file = File::open(&args.path).map(BufReader::new)?;
-    let pages = PageBuffer::new(file)
-        .filter_map(|text| xml::from_str::<Page>(&text.ok()?).ok())
-        .filter(|page| !page.title.ends_with("(disambiguation)"))
-        .filter_map(|page| {
-            ex.extract(&tf.filter(page.text()?))
-                .map(|link| (page.title, link.to_string()))
-        });
-
-    for (title, link) in pages {
-        println!("{title} -> {link}")
+    match &args.command {
+        Command::Extract(args) => extract::run(args),
+        Command::Parts(args) => parts::run(args),
+        Command::Deadends(args) => deadends::run(args),
+        Command::Chain(args) => chain::run(args),
+        Command::Backlinks(args) => backlinks::run(args),
+        Command::Distances(args) => distances::run(args),
+        Command::Components(args) => components::run(args),
+        Command::Lengths(args) => lengths::run(args),
+        Command::Hubs(args) => hubs::run(args),
+        Command::Targets(args) => targets::run(args),
+        Command::Loops(args) => loops::run(args),
+        Command::Orphans(args) => orphans::run(args),
+        Command::Path(args) => path::run(args),
+        Command::Rank(args) => rank::run(args),
+        Command::Betweenness(args) => betweenness::run(args),
+        Command::Walk(args) => walk::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Merge(args) => merge::run(args),
+        Command::Serve(args) => serve::run(args),
+        Command::Explore(args) => explore::run(args),
+        Command::Sort(args) => sort::run(args),
+        Command::Number(args) => number::run(args),
+        Command::Compress(args) => compress::run(args),
+        Command::Fixture(args) => fixture::run(args),
+        Command::Index(args) => index::run(args),
+        Command::Search(args) => search::run(args),
+        Command::Corpus(args) => corpus::run(args),
+        Command::Documents(args) => documents::run(args),
+        Command::Sentences(args) => sentences::run(args),
+        Command::Freq(args) => freq::run(args),
+        Command::Ngrams(args) => ngrams::run(args),
+        Command::TemplateUsage(args) => template_usage::run(args),
+        Command::Duplicates(args) => duplicates::run(args),
+        Command::Features(args) => features::run(args),
+        Command::Contributors(args) => contributors::run(args),
+        Command::History(args) => history::run(args),
+        Command::Wikidata(args) => wikidata::run(args),
+        Command::Pagelinks(args) => pagelinks::run(args),
+        Command::Abstracts(args) => abstracts::run(args),
+        Command::Terms(args) => terms::run(args),
+        Command::Definitions(args) => definitions::run(args),
+        Command::Wiktionary(args) => wiktionary::run(args),
+        Command::Talk(args) => talk::run(args),
+        Command::Rdf(args) => rdf::run(args),
+        Command::Jsonld(args) => jsonld::run(args),
+        Command::Warc(args) => warc::run(args),
     }
-
-    Ok(())
 }
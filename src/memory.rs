@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+/// What `--low-memory` holds anything that spills to disk to, leaving the rest of 512M to
+/// reading pages and writing output.
+const LOW_MEMORY_BUDGET: usize = 192 << 20;
+
+/// The most of each revision's text `--low-memory` keeps.
+pub const LOW_MEMORY_MAX_TEXT: usize = 4 << 20;
+
+/// Options for commands whose working set can outgrow memory.
+#[derive(Debug, clap::Args)]
+pub struct MemoryArgs {
+    /// memory to use before spilling to disk, e.g. 512M or 4G
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+    /// directory for spilled data; defaults to the system temp directory
+    #[clap(long)]
+    temp_dir: Option<std::path::PathBuf>,
+    /// spill to disk once 192M is in use, whatever --max-memory says; extract also keeps less
+    /// of each revision and refuses outputs and options that build up in memory, so as to stay
+    /// within about 512M, though pages with many revisions, as in history dumps, can still go
+    /// over
+    #[clap(long)]
+    low_memory: bool,
+}
+
+impl MemoryArgs {
+    /// The memory budget in bytes, or `default` if none was given.
+    pub fn budget_or(&self, default: usize) -> usize {
+        self.budget().unwrap_or(default)
+    }
+
+    /// The memory budget in bytes, held to what `--low-memory` allows.
+    pub fn budget(&self) -> Option<usize> {
+        let given = self.max_memory.map(|size| size.0);
+        match self.low_memory {
+            true => Some(given.map_or(LOW_MEMORY_BUDGET, |given| given.min(LOW_MEMORY_BUDGET))),
+            false => given,
+        }
+    }
+
+    pub fn low_memory(&self) -> bool {
+        self.low_memory
+    }
+
+    pub fn temp_dir(&self) -> Option<&std::path::Path> {
+        self.temp_dir.as_deref()
+    }
+}
+
+/// A size in bytes, written as a number with an optional K, M, or G suffix.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(pub usize);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (digits, shift) = match s.char_indices().last() {
+            Some((idx, 'k' | 'K')) => (&s[..idx], 10),
+            Some((idx, 'm' | 'M')) => (&s[..idx], 20),
+            Some((idx, 'g' | 'G')) => (&s[..idx], 30),
+            _ => (s, 0),
+        };
+        let n: usize = digits
+            .trim()
+            .parse()
+            .map_err(|_| format!("not a size: {s}"))?;
+        n.checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(|| format!("size too large: {s}"))
+    }
+}
+
+/// Roughly what a string costs to keep in a hash table, counting its heap allocation.
+pub fn string_cost(s: &str) -> usize {
+    s.len() + 2 * std::mem::size_of::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(s: &str) -> Result<usize, String> {
+        s.parse::<ByteSize>().map(|size| size.0)
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(size("512"), Ok(512));
+        assert_eq!(size("4k"), Ok(4 << 10));
+        assert_eq!(size(" 512M "), Ok(512 << 20));
+        assert_eq!(size("2 G"), Ok(2 << 30));
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_size() {
+        assert_eq!(size("").unwrap_err(), "not a size: ");
+        assert_eq!(size("1.5G").unwrap_err(), "not a size: 1.5G");
+        assert_eq!(size("12T").unwrap_err(), "not a size: 12T");
+        let huge = format!("{}G", usize::MAX >> 10);
+        assert_eq!(size(&huge).unwrap_err(), format!("size too large: {huge}"));
+    }
+}
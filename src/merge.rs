@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{
+    extract,
+    filter::{LinkExtractor, TextFilter},
+    graph::normalize,
+    page,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// dump containing new or changed pages
+    dump: String,
+    /// extract every link on each page; should match how the graph was built
+    #[clap(long)]
+    all_links: bool,
+    /// write the merged graph to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Replaces the links of every page in the dump, leaving the rest of the graph untouched.
+/// Pages in the dump that are now redirects or have no links lose their edges entirely.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let site = page::site_info(&args.dump)?;
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+
+    // Each page's links are taken the first time the page turns up in the old graph; whatever
+    // is left afterward belongs to pages the old graph didn't have.
+    let mut updates: HashMap<String, (String, Option<Vec<String>>)> = page::read_pages(&args.dump)?
+        .map(|page| {
+            let links = extract::page_links(&tf, &ex, &page, args.all_links)
+                .into_iter()
+                .map(|link| link.target)
+                .collect();
+            (normalize(&page.title), (page.title, Some(links)))
+        })
+        .collect();
+
+    // Written beside the output and renamed into place once done, since the output may well be
+    // the graph being read.
+    let temp = args.output.as_ref().map(|path| format!("{path}.tmp"));
+    let mut out: Box<dyn Write> = match &temp {
+        Some(temp) => Box::new(File::create(temp).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let reader = File::open(&args.graph).map(BufReader::new)?;
+    let mut replaced = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let source = match line.split_once(" -> ") {
+            Some((source, _)) => normalize(source),
+            None => continue,
+        };
+
+        let (title, links) = match updates.get_mut(&source) {
+            Some(update) => update,
+            None => {
+                writeln!(out, "{line}")?;
+                continue;
+            }
+        };
+
+        // A changed page's new links go where its old ones first appeared, keeping the graph
+        // grouped by source.
+        if let Some(links) = links.take() {
+            for link in links {
+                writeln!(out, "{title} -> {link}")?;
+            }
+            replaced += 1;
+        }
+    }
+
+    let mut added: Vec<_> = updates
+        .into_values()
+        .filter_map(|(title, links)| Some((title, links?)))
+        .filter(|(_, links)| !links.is_empty())
+        .collect();
+    added.sort_unstable();
+    for (title, links) in &added {
+        for link in links {
+            writeln!(out, "{title} -> {link}")?;
+        }
+    }
+    out.flush()?;
+    drop(out);
+    if let (Some(temp), Some(path)) = (temp, &args.output) {
+        fs::rename(temp, path)?;
+    }
+
+    info!("{replaced} pages updated, {} pages added", added.len());
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::{fmt::Display, sync::Arc, thread};
+
+use tiny_http::{Header, Response, Server};
+use tracing::info;
+
+use crate::progress::Progress;
+
+/// The content type of the text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Builds a page of metrics in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    pub fn counter(&mut self, name: &str, help: &str, value: impl Display) {
+        self.labeled(name, "counter", help, [("", value)]);
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: impl Display) {
+        self.labeled(name, "gauge", help, [("", value)]);
+    }
+
+    /// Writes one sample per label set, where each label set is written as it would appear
+    /// between braces, e.g. `endpoint="/path"`.
+    pub fn labeled<L, V>(&mut self, name: &str, kind: &str, help: &str, samples: L)
+    where
+        L: IntoIterator<Item = (&'static str, V)>,
+        V: Display,
+    {
+        use std::fmt::Write;
+
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                let _ = writeln!(self.text, "{name} {value}");
+            } else {
+                let _ = writeln!(self.text, "{name}{{{labels}}} {value}");
+            }
+        }
+    }
+
+    pub fn into_response(self) -> Response<std::io::Cursor<Vec<u8>>> {
+        let content_type = Header::from_bytes("Content-Type", CONTENT_TYPE).unwrap();
+        Response::from_string(self.text).with_header(content_type)
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+/// Describes a pass over a dump.
+pub fn progress(out: &mut Exposition, progress: &Progress) {
+    let elapsed = progress.elapsed_secs().max(f64::EPSILON);
+    out.counter(
+        "wiki_crawler_bytes_read_total",
+        "Bytes of input read so far.",
+        progress.bytes(),
+    );
+    out.gauge(
+        "wiki_crawler_input_bytes",
+        "Size of the input in bytes.",
+        progress.total(),
+    );
+    out.counter(
+        "wiki_crawler_pages_total",
+        "Pages parsed so far.",
+        progress.pages(),
+    );
+    out.counter(
+        "wiki_crawler_errors_total",
+        "Pages that could not be read or parsed.",
+        progress.errors(),
+    );
+    out.gauge(
+        "wiki_crawler_pages_per_second",
+        "Average pages parsed per second.",
+        progress.pages() as f64 / elapsed,
+    );
+    out.gauge(
+        "wiki_crawler_elapsed_seconds",
+        "Seconds since the pass began.",
+        elapsed,
+    );
+}
+
+/// Serves progress metrics from a background thread for as long as the process runs.
+pub fn spawn_exporter(addr: &str, state: Arc<Progress>) -> anyhow::Result<()> {
+    let server = Server::http(addr)
+        .map_err(|e| anyhow::anyhow!("failed to bind metrics address {addr}: {e}"))?;
+    info!("serving metrics on {addr}");
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let mut out = Exposition::default();
+            progress(&mut out, &state);
+            let _ = request.respond(out.into_response());
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,289 @@
+//! Pages as MediaWiki's XML export writes them, field for field, so that what the crawler
+//! reads can be handed on or written back out as it was.
+//!
+//! Every field but a page's title is optional, since exporters and schema versions differ in
+//! what they include, and deleted or suppressed parts of a revision come through empty.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::Error;
+
+/// A `<page>` element, with its revisions.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Page {
+    /// The title, with its namespace prefix and spaces rather than underscores.
+    pub title: String,
+    /// The namespace, `0` for articles. Dumps older than schema 0.6 leave it out.
+    #[serde(default)]
+    pub ns: Option<i64>,
+    /// The page id.
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// The `<redirect>` tag of a redirect page. Dumps from before schema 0.5 don't mark
+    /// redirects, and not every exporter names the target, so the text has the final say, as
+    /// in [`Page::redirect`].
+    #[serde(default)]
+    pub redirect: Option<Redirect>,
+    /// Protection levels, as in `edit=sysop:move=sysop`, in dumps old enough to have them.
+    #[serde(default)]
+    pub restrictions: Option<String>,
+    #[serde(default, rename = "revision")]
+    pub revisions: Vec<Revision>,
+}
+
+/// A redirect page's `<redirect>` tag.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Redirect {
+    /// The page redirected to, when the exporter names it.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl Page {
+    /// Reads a page from its `<page>` element, found `offset` bytes into a dump.
+    pub fn from_xml(xml: &str, offset: u64) -> Result<Page, Error> {
+        let mut page: Page = serde_xml_rs::from_str(xml).map_err(|e| Error::XmlParse {
+            offset,
+            title: title_of(xml),
+            message: e.to_string(),
+        })?;
+        // The parser trims whitespace from either end of an element's text, but a revision's
+        // text keeps it, as its length and SHA-1 do.
+        let raw = xml.split("<revision>").skip(1).map(raw_text);
+        for (revision, raw) in page.revisions.iter_mut().zip(raw) {
+            if let Some(raw) = raw {
+                untrim(&mut revision.text, raw);
+            }
+        }
+        Ok(page)
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        let candidate = &self.latest()?.text;
+        (self.redirect.is_none() && redirect_text(candidate).is_none()).then_some(candidate)
+    }
+
+    /// The target of a redirect page, as its `<redirect>` tag names it or as read from its
+    /// `#REDIRECT [[Target]]` text.
+    pub fn redirect(&self) -> Option<&str> {
+        let tagged = self.redirect.as_ref().and_then(|tag| tag.title.as_deref());
+        if let Some(title) = tagged.filter(|title| !title.is_empty()) {
+            return Some(title);
+        }
+
+        let text = redirect_text(&self.latest()?.text)?;
+        let (_, rest) = text.split_once("[[")?;
+        let end = rest.find([']', '|'])?;
+        Some(rest[..end].trim())
+    }
+
+    /// Every revision in the dump, in the order they appear. History dumps list them oldest
+    /// first, but Special:Export can be asked for the reverse.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    /// The newest revision. Dumps of current pages have only the one, but history dumps and
+    /// Special:Export have as many as were asked for.
+    pub fn latest(&self) -> Option<&Revision> {
+        self.revisions
+            .iter()
+            .max_by_key(|revision| revision.timestamp.as_deref())
+    }
+}
+
+impl fmt::Debug for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Page").field("title", &self.title).finish()
+    }
+}
+
+/// The title of a page that failed to parse, if the trouble is elsewhere.
+fn title_of(xml: &str) -> Option<String> {
+    let (_, rest) = xml.split_once("<title>")?;
+    let (title, _) = rest.split_once("</title>")?;
+    Some(title.to_owned())
+}
+
+/// The content of the `<text>` element in a revision's XML, still escaped, unless it's empty.
+fn raw_text(revision: &str) -> Option<&str> {
+    let revision = &revision[..revision.find("</revision>").unwrap_or(revision.len())];
+    let (_, rest) = revision.split_once("<text")?;
+    let (tag, rest) = rest.split_once('>')?;
+    if tag.ends_with('/') {
+        return None;
+    }
+    Some(&rest[..rest.find("</text>")?])
+}
+
+/// Puts back the whitespace the parser trimmed from either end of `text`, as `raw` has it.
+fn untrim(text: &mut String, raw: &str) {
+    // The same whitespace as the parser trims, which Unicode's is more than.
+    let space = |c: char| matches!(c, ' ' | '\t' | '\n' | '\r');
+    let trimmed = raw.trim_matches(space);
+    if trimmed.len() == raw.len() {
+        return;
+    }
+    if trimmed.is_empty() {
+        *text = raw.to_owned();
+        return;
+    }
+    let leading = &raw[..raw.len() - raw.trim_start_matches(space).len()];
+    let trailing = &raw[raw.trim_end_matches(space).len()..];
+    *text = format!("{leading}{text}{trailing}");
+}
+
+/// What follows the `#REDIRECT` that starts a redirect's text, which wikis write in any case
+/// and sometimes after blank lines.
+fn redirect_text(text: &str) -> Option<&str> {
+    let text = text.trim_start();
+    let magic = text.get(..9)?;
+    magic.eq_ignore_ascii_case("#redirect").then(|| &text[9..])
+}
+
+/// A `<revision>` element.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Revision {
+    /// The revision id.
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// The revision this one was made on top of, missing for a page's first.
+    #[serde(default)]
+    pub parentid: Option<u64>,
+    /// When the revision was saved, as in `2020-06-05T12:34:00Z`.
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Who saved the revision, empty when it has been suppressed.
+    #[serde(default)]
+    pub contributor: Option<Contributor>,
+    /// Whether the editor marked the edit as minor, with an empty `<minor/>`.
+    #[serde(default, deserialize_with = "present")]
+    pub minor: bool,
+    /// The edit summary.
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// The content model, as in `wikitext`.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The content format, as in `text/x-wiki`.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// The SHA-1 of the text in base 36, as MediaWiki keeps it.
+    #[serde(default)]
+    pub sha1: Option<String>,
+    /// The wikitext, empty for revisions whose text was deleted, which have an empty
+    /// `<text deleted="deleted" />`.
+    #[serde(default)]
+    pub text: String,
+}
+
+impl Revision {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the text is the one the revision's SHA-1 was taken of, or `None` if the dump
+    /// gives no SHA-1 or the text isn't there to check, as for deleted revisions.
+    pub fn verify(&self) -> Option<bool> {
+        let expected = self.sha1.as_deref().filter(|sha1| !sha1.is_empty())?;
+        if self.text.is_empty() {
+            return None;
+        }
+        Some(sha1_base36(&self.text).eq_ignore_ascii_case(expected.trim()))
+    }
+}
+
+/// The SHA-1 of some text as MediaWiki writes it: in base 36, padded to 31 digits.
+pub fn sha1_base36(text: &str) -> String {
+    let mut number: Vec<u8> = Sha1::digest(text.as_bytes()).to_vec();
+    let mut digits = Vec::with_capacity(31);
+    // Long division of the big-endian number by 36, a digit at a time.
+    while number.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0u32;
+        for byte in &mut number {
+            let value = remainder << 8 | u32::from(*byte);
+            *byte = (value / 36) as u8;
+            remainder = value % 36;
+        }
+        digits.push(char::from_digit(remainder, 36).unwrap());
+    }
+    while digits.len() < 31 {
+        digits.push('0');
+    }
+    digits.iter().rev().collect()
+}
+
+/// Who made a revision: a registered user with a name and id, or an anonymous editor's IP
+/// address. Both are missing where the contributor has been suppressed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Contributor {
+    /// The account name of a registered editor.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The account id of a registered editor.
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// The address an anonymous editor edited from.
+    #[serde(default)]
+    pub ip: Option<String>,
+}
+
+/// Reads an element that means something by being there at all.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    serde::de::IgnoredAny::deserialize(deserializer).map(|_| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(revision: &str) -> Page {
+        let xml = format!("<page><title>T</title><revision><id>1</id>{revision}</revision></page>");
+        Page::from_xml(&xml, 0).unwrap()
+    }
+
+    #[test]
+    fn text_keeps_its_whitespace() {
+        let page = parse("<text xml:space=\"preserve\">\n [[A]] &amp; b\n\n</text>");
+        assert_eq!(page.revisions()[0].text(), "\n [[A]] & b\n\n");
+        let page = parse("<text xml:space=\"preserve\"> \n</text>");
+        assert_eq!(page.revisions()[0].text(), " \n");
+    }
+
+    #[test]
+    fn deleted_text_is_empty() {
+        let page = parse("<text bytes=\"5\" deleted=\"deleted\" />");
+        assert_eq!(page.revisions()[0].text(), "");
+        assert_eq!(page.revisions()[0].verify(), None);
+    }
+
+    #[test]
+    fn sha1_is_base36_padded_to_31_digits() {
+        assert_eq!(sha1_base36(""), "phoiac9h4m842xq45sp7s6u21eteeq1");
+        assert_eq!(
+            sha1_base36("[[Alpha]] ends with a newline.\n"),
+            "s0e9pbnnz6zy8sjasa11dp35jzvwxod"
+        );
+    }
+
+    #[test]
+    fn verifies_text_ending_in_whitespace() {
+        let page = parse(
+            "<sha1>s0e9pbnnz6zy8sjasa11dp35jzvwxod</sha1>\
+             <text>[[Alpha]] ends with a newline.\n</text>",
+        );
+        assert_eq!(page.revisions()[0].verify(), Some(true));
+        let page = parse("<sha1>s0e9pbnnz6zy8sjasa11dp35jzvwxod</sha1><text>x</text>");
+        assert_eq!(page.revisions()[0].verify(), Some(false));
+    }
+
+    #[test]
+    fn redirects_are_read_from_the_text() {
+        let page = parse("<text>#redirect [[Target|shown]]</text>");
+        assert_eq!(page.redirect(), Some("Target"));
+        assert_eq!(page.text(), None);
+    }
+}
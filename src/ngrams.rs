@@ -0,0 +1,240 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Lines, Write},
+    path::{Path, PathBuf},
+};
+
+use tempfile::TempDir;
+use tracing::{debug, info};
+
+use crate::{
+    memory::{self, MemoryArgs},
+    page,
+    plain::{self, PlainText},
+    sentences,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to count n-grams in
+    dump: String,
+    /// words per n-gram
+    #[clap(short, default_value_t = 2)]
+    n: usize,
+    /// leave out n-grams seen fewer times than this
+    #[clap(long, default_value_t = 1)]
+    min_count: u64,
+    /// count words as written rather than lowercased
+    #[clap(long)]
+    keep_case: bool,
+    /// write n-grams to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    #[clap(flatten)]
+    memory: MemoryArgs,
+}
+
+/// Counts n-grams in the cleaned text of every article, writing `ngram<TAB>count<TAB>pages`
+/// lines in the manner of the Google Books n-gram tables, sorted by n-gram. N-grams don't
+/// cross sentence boundaries.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    anyhow::ensure!(args.n > 0, "n-grams need at least one word");
+
+    let plain = PlainText::new();
+    let budget = args.memory.budget_or(512 << 20);
+    let mut counter = Counter::new(budget, args.memory.temp_dir())?;
+    let mut pages = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+        pages += 1;
+
+        let text = plain.convert(text);
+        let paragraphs = text
+            .lines()
+            .filter(|line| !line.is_empty() && plain::heading(line).is_none());
+
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for sentence in paragraphs.flat_map(sentences::split) {
+            let words: Vec<String> = plain::words(sentence)
+                .map(|word| match args.keep_case {
+                    true => word.to_owned(),
+                    false => word.to_lowercase(),
+                })
+                .collect();
+            for gram in words.windows(args.n) {
+                *counts.entry(gram.join(" ")).or_default() += 1;
+            }
+        }
+        counter.add_page(counts)?;
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let written = counter.finish(args.min_count, &mut out)?;
+    out.flush()?;
+
+    info!(
+        "wrote {written} distinct {}-grams over {pages} pages",
+        args.n
+    );
+    Ok(())
+}
+
+/// Totals of how often an n-gram appears and on how many pages.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    count: u64,
+    pages: u64,
+}
+
+/// Tallies more n-grams than fit in memory by writing sorted runs of partial tallies to disk
+/// once a budget is used up, then merging them and adding up the tallies of each n-gram.
+struct Counter {
+    budget: usize,
+    used: usize,
+    tallies: HashMap<String, Tally>,
+    dir: TempDir,
+    runs: Vec<PathBuf>,
+}
+
+impl Counter {
+    fn new(budget: usize, temp_dir: Option<&Path>) -> io::Result<Self> {
+        let dir = match temp_dir {
+            Some(parent) => TempDir::new_in(parent)?,
+            None => TempDir::new()?,
+        };
+        Ok(Self {
+            budget,
+            used: 0,
+            tallies: HashMap::new(),
+            dir,
+            runs: Vec::new(),
+        })
+    }
+
+    /// Adds the counts of one page's n-grams.
+    fn add_page(&mut self, counts: HashMap<String, u64>) -> io::Result<()> {
+        for (gram, count) in counts {
+            let cost = memory::string_cost(&gram);
+            let tally = self.tallies.entry(gram).or_insert_with(|| {
+                self.used += cost;
+                Tally::default()
+            });
+            tally.count += count;
+            tally.pages += 1;
+        }
+        if self.used >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the n-grams seen at least `min_count` times in order and returns how many were
+    /// written.
+    fn finish(mut self, min_count: u64, out: &mut impl Write) -> io::Result<u64> {
+        let mut written = 0;
+        let mut emit = |gram: &str, tally: Tally| {
+            if tally.count < min_count {
+                return Ok(());
+            }
+            written += 1;
+            writeln!(out, "{gram}\t{}\t{}", tally.count, tally.pages)
+        };
+
+        // Everything fit in memory, so there is nothing to merge.
+        if self.runs.is_empty() {
+            let mut tallies: Vec<_> = self.tallies.into_iter().collect();
+            tallies.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            for (gram, tally) in tallies {
+                emit(&gram, tally)?;
+            }
+            return Ok(written);
+        }
+
+        if !self.tallies.is_empty() {
+            self.spill()?;
+        }
+        debug!("merging {} runs", self.runs.len());
+
+        let mut runs = self
+            .runs
+            .iter()
+            .map(|path| File::open(path).map(|file| BufReader::new(file).lines()))
+            .collect::<io::Result<Vec<Lines<_>>>>()?;
+
+        let mut heap = BinaryHeap::new();
+        for (idx, run) in runs.iter_mut().enumerate() {
+            if let Some(entry) = next_entry(run)? {
+                heap.push(Reverse((entry, idx)));
+            }
+        }
+
+        let mut current: Option<(String, Tally)> = None;
+        while let Some(Reverse(((gram, count, pages), idx))) = heap.pop() {
+            if let Some(entry) = next_entry(&mut runs[idx])? {
+                heap.push(Reverse((entry, idx)));
+            }
+            match &mut current {
+                Some((last, tally)) if *last == gram => {
+                    tally.count += count;
+                    tally.pages += pages;
+                }
+                _ => {
+                    if let Some((last, tally)) = current.take() {
+                        emit(&last, tally)?;
+                    }
+                    current = Some((gram, Tally { count, pages }));
+                }
+            }
+        }
+        if let Some((last, tally)) = current {
+            emit(&last, tally)?;
+        }
+        Ok(written)
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let mut tallies: Vec<_> = self.tallies.drain().collect();
+        tallies.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let path = self.dir.path().join(format!("run-{}", self.runs.len()));
+        debug!("spilling {} n-grams to {}", tallies.len(), path.display());
+        let mut file = File::create(&path).map(BufWriter::new)?;
+        for (gram, tally) in tallies {
+            writeln!(file, "{gram}\t{}\t{}", tally.count, tally.pages)?;
+        }
+        file.flush()?;
+
+        self.runs.push(path);
+        self.used = 0;
+        Ok(())
+    }
+}
+
+/// Reads the next `ngram<TAB>count<TAB>pages` line of a run.
+fn next_entry(run: &mut Lines<BufReader<File>>) -> io::Result<Option<(String, u64, u64)>> {
+    let Some(line) = run.next().transpose()? else {
+        return Ok(None);
+    };
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("bad run line: {line}"));
+    let mut fields = line.rsplitn(3, '\t');
+    let pages = fields
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+    let count = fields
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+    let gram = fields.next().ok_or_else(invalid)?;
+    Ok(Some((gram.to_owned(), count, pages)))
+}
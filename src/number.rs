@@ -0,0 +1,69 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use tracing::info;
+
+use crate::{dictionary::Dictionary, memory::ByteSize};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// directory holding the title dictionary; defaults to GRAPH.ids
+    #[clap(long)]
+    dictionary: Option<PathBuf>,
+    /// memory to spend caching the dictionary, e.g. 64M
+    #[clap(long)]
+    max_memory: Option<ByteSize>,
+    /// write numbered edges to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// also write the dictionary to this file as `id\ttitle` lines
+    #[clap(long)]
+    titles: Option<String>,
+}
+
+/// Rewrites a graph as `source target` id pairs, keeping the titles they stand for in an
+/// on-disk dictionary. An existing dictionary is added to rather than replaced, so graphs
+/// numbered against the same dictionary share ids.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let path = match &args.dictionary {
+        Some(path) => path.clone(),
+        None => PathBuf::from(format!("{}.ids", args.graph)),
+    };
+    // The dictionary lives on disk anyway, so the budget only bounds its cache.
+    let cache = args.max_memory.map_or(64 << 20, |size| size.0) as u64;
+    let mut dictionary = Dictionary::open(&path, cache)?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let reader = File::open(&args.graph).map(BufReader::new)?;
+    for line in reader.lines() {
+        let line = line?;
+        if let Some((source, target)) = line.split_once(" -> ") {
+            let source = dictionary.insert(source)?;
+            let target = dictionary.insert(target)?;
+            writeln!(out, "{source} {target}")?;
+        }
+    }
+    out.flush()?;
+    dictionary.flush()?;
+
+    if let Some(path) = &args.titles {
+        let mut out = File::create(path).map(BufWriter::new)?;
+        for entry in dictionary.titles() {
+            let (id, title) = entry?;
+            writeln!(out, "{id}\t{title}")?;
+        }
+        out.flush()?;
+    }
+
+    info!("{} titles in {}", dictionary.len(), path.display());
+    Ok(())
+}
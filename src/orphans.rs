@@ -0,0 +1,25 @@
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let mut orphans: Vec<_> = graph
+        .in_degrees()
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, degree)| degree == 0)
+        .map(|(id, _)| graph.title(id as u32))
+        .collect();
+    orphans.sort_unstable();
+
+    for title in orphans {
+        println!("{title}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,817 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    mem,
+    ops::Not,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use tracing::{error, warn};
+
+use wiki_crawler::Error;
+
+use crate::{
+    exit,
+    progress::{CountingReader, Progress},
+    remote,
+};
+
+pub use wiki_crawler::model::Page;
+
+/// Opens a dump, decompressing it if its name ends in `.bz2` or `.gz`. An `https://` or
+/// `s3://` URL in place of a path streams the dump from there.
+///
+/// Progress counts bytes of the file itself, ahead of any decompression, so that it can be
+/// measured against the file's size.
+pub fn open_dump(path: impl AsRef<Path>) -> io::Result<(Box<dyn BufRead + Send>, Arc<Progress>)> {
+    open_dump_at(path, 0)
+}
+
+/// Opens a dump positioned `offset` bytes into its decompressed text.
+///
+/// Plain dumps seek straight there; compressed ones have to be decompressed and discarded up to
+/// that point, which still beats parsing it all again.
+pub fn open_dump_at(
+    path: impl AsRef<Path>,
+    offset: u64,
+) -> io::Result<(Box<dyn BufRead + Send>, Arc<Progress>)> {
+    let (mut reader, progress) = open_raw(path, offset)?;
+    if offset == 0 {
+        skip_bom(&mut reader)?;
+    }
+    Ok((reader, progress))
+}
+
+/// Dumps that have been through Windows tooling can start with a byte order mark. Returns
+/// how many bytes were skipped.
+fn skip_bom(reader: &mut impl BufRead) -> io::Result<u64> {
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+    // A decompressor can hand over fewer bytes than the mark at a time, so it's matched as
+    // they come. No dump starts with part of the mark and then something else.
+    let mut matched = 0;
+    while matched < BOM.len() {
+        let buf = reader.fill_buf()?;
+        let len = buf.len().min(BOM.len() - matched);
+        if len == 0 || buf[..len] != BOM[matched..matched + len] {
+            break;
+        }
+        reader.consume(len);
+        matched += len;
+    }
+    Ok(matched as u64)
+}
+
+fn open_raw(
+    path: impl AsRef<Path>,
+    offset: u64,
+) -> io::Result<(Box<dyn BufRead + Send>, Arc<Progress>)> {
+    let path = path.as_ref();
+    let unreadable = |e| Error::unreadable(path, e);
+    if let Some(location) = path.to_str().filter(|path| remote::is_remote(path)) {
+        return open_remote(location, offset).map_err(unreadable);
+    }
+
+    let mut file = File::open(path).map_err(unreadable)?;
+    let progress = Arc::new(Progress::new(file.metadata().map_err(unreadable)?.len()));
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let compressed = matches!(extension, Some("bz2" | "gz"));
+    if !compressed && offset > 0 {
+        file.seek(SeekFrom::Start(offset)).map_err(unreadable)?;
+        progress.add_bytes(offset);
+    }
+    let file = BufReader::new(CountingReader::new(file, progress.clone()));
+    let reader = decompress(file, extension);
+    skip_decompressed(reader, compressed, offset, progress).map_err(unreadable)
+}
+
+/// Streams a dump from a URL, as [`remote::open`] does, without it touching the disk. Dumps
+/// without compression start at `offset` by asking the server for the rest of the file.
+fn open_remote(
+    location: &str,
+    offset: u64,
+) -> io::Result<(Box<dyn BufRead + Send>, Arc<Progress>)> {
+    let extension = Path::new(location_name(location)).extension();
+    let extension = extension.and_then(|ext| ext.to_str());
+    let compressed = matches!(extension, Some("bz2" | "gz"));
+    let remote = remote::open(location, if compressed { 0 } else { offset })?;
+
+    // Servers that don't give a length get a bar that counts bytes without an end.
+    let progress = Arc::new(Progress::new(remote.len.unwrap_or(0)));
+    if !compressed {
+        progress.add_bytes(offset);
+    }
+    let file = BufReader::new(CountingReader::new(remote.reader, progress.clone()));
+    let reader = decompress(file, extension);
+    skip_decompressed(reader, compressed, offset, progress)
+}
+
+/// The file name at the end of a URL, less any query.
+fn location_name(location: &str) -> &str {
+    let location = location.split(['?', '#']).next().unwrap_or(location);
+    location.rsplit('/').next().unwrap_or(location)
+}
+
+fn decompress<R: BufRead + Send + 'static>(
+    file: R,
+    extension: Option<&str>,
+) -> Box<dyn BufRead + Send> {
+    match extension {
+        // Wikimedia's dumps are multistream: many bzip2 streams back to back.
+        Some("bz2") => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+        Some("gz") => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        _ => Box::new(file),
+    }
+}
+
+/// Compressed dumps can't be started partway, so they're decompressed and discarded up to
+/// `offset`.
+fn skip_decompressed(
+    mut reader: Box<dyn BufRead + Send>,
+    compressed: bool,
+    offset: u64,
+    progress: Arc<Progress>,
+) -> io::Result<(Box<dyn BufRead + Send>, Arc<Progress>)> {
+    if compressed && offset > 0 {
+        io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+    }
+    Ok((reader, progress))
+}
+
+/// The newest export schema this reader knows about.
+const SCHEMA_VERSION: (u32, u32) = (0, 11);
+
+/// What a dump says about its wiki ahead of the first page.
+#[derive(Debug, Default)]
+pub struct SiteInfo {
+    /// The export schema version, as in `0.11`.
+    pub version: Option<String>,
+    /// The wiki's language code, as in `en`.
+    pub lang: Option<String>,
+    /// The prefix of the wiki's article URLs, taken from the `<base>` URL of its main page.
+    pub base: Option<String>,
+    /// The names of every namespace besides the main one. Wikis beyond Wikimedia's have
+    /// ones of their own, such as Fandom's `User blog`.
+    pub namespaces: Vec<String>,
+}
+
+/// Reads the site information at the head of a dump, warning if it was written with an
+/// export schema newer than this reader knows about.
+pub fn site_info(path: impl AsRef<Path>) -> io::Result<SiteInfo> {
+    let (reader, _) = open_dump(path)?;
+    let mut info = SiteInfo::default();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line == "<page>" {
+            break;
+        }
+
+        if line.starts_with("<mediawiki ") {
+            info.version = attribute(line, "version").map(String::from);
+            info.lang = attribute(line, "xml:lang").map(String::from);
+        } else if let Some(base) = line
+            .strip_prefix("<base>")
+            .and_then(|line| line.strip_suffix("</base>"))
+        {
+            let end = base.rfind('/').map_or(base.len(), |idx| idx + 1);
+            info.base = Some(base[..end].to_owned());
+        } else if let Some(namespace) = line
+            .strip_prefix("<namespace ")
+            .and_then(|line| line.strip_suffix("</namespace>"))
+            .and_then(|line| line.split_once('>'))
+            .map(|(_, name)| name)
+        {
+            info.namespaces.push(crate::titles::unescape(namespace));
+        }
+    }
+
+    let version = info.version.as_deref().and_then(|version| {
+        let (major, minor) = version.split_once('.')?;
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    });
+    if version.is_some_and(|version: (u32, u32)| version > SCHEMA_VERSION) {
+        warn!(
+            version = info.version.as_deref(),
+            "dump uses a newer export schema than this tool knows; pages may be misread"
+        );
+    }
+    Ok(info)
+}
+
+/// The value of an attribute on an XML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let (_, rest) = tag.split_once(&format!(" {name}=\""))?;
+    rest.split('"').next()
+}
+
+/// Reads every page from a dump, skipping pages that fail to parse.
+pub fn read_pages(path: impl AsRef<Path>) -> io::Result<Pages> {
+    read_pages_range(path, 0, None)
+}
+
+/// Reads the pages whose opening `<page>` tag begins within `start..end` bytes of a dump's
+/// decompressed text. Neither end has to fall on a page boundary, so a dump can be cut into
+/// contiguous ranges that together cover every page exactly once.
+pub fn read_pages_range(path: impl AsRef<Path>, start: u64, end: Option<u64>) -> io::Result<Pages> {
+    // Starting a byte early and skipping to the end of that line lands on the first line that
+    // begins at or after `start`.
+    let path = path.as_ref();
+    let (mut reader, progress) = open_raw(path, start.saturating_sub(1))?;
+    let mut offset = start.saturating_sub(1);
+    if start > 0 {
+        offset += reader.read_until(b'\n', &mut Vec::new())? as u64;
+    } else {
+        offset += skip_bom(&mut reader)?;
+    }
+
+    Ok(Pages {
+        path: path.to_owned(),
+        buffer: PageBuffer::starting_at(reader, offset)
+            .ending_at(end)
+            .max_text(Some(MAX_REVISION)),
+        progress,
+        failed: None,
+        finished: false,
+    })
+}
+
+/// Revision text beyond this many bytes is dropped as a dump is read, unless a reader is
+/// given a limit of its own. MediaWiki itself refuses to save revisions past 2 MiB, so only
+/// broken or imported pages come near it.
+pub const MAX_REVISION: usize = 16 << 20;
+
+pub struct Pages {
+    path: PathBuf,
+    buffer: PageBuffer<Box<dyn BufRead + Send>>,
+    progress: Arc<Progress>,
+    /// Pages that failed to parse since they were last taken, when asked to keep them.
+    failed: Option<Vec<Error>>,
+    /// Whether the end of the dump has been reached and checked for.
+    finished: bool,
+}
+
+impl Pages {
+    pub fn progress(&self) -> Arc<Progress> {
+        self.progress.clone()
+    }
+
+    /// Keeps no more than `max_text` bytes of each revision's text, or all of it for `None`.
+    pub fn max_text(self, max_text: Option<usize>) -> Self {
+        Self {
+            buffer: self.buffer.max_text(max_text),
+            ..self
+        }
+    }
+
+    /// Keeps the errors for pages that fail to parse, for [`take_failed`](Self::take_failed).
+    pub fn keeping_failed(self) -> Self {
+        Self {
+            failed: Some(Vec::new()),
+            ..self
+        }
+    }
+
+    /// The errors for pages that failed to parse since the last call, in the order they
+    /// turned up.
+    pub fn take_failed(&mut self) -> Vec<Error> {
+        self.failed.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Offset into the decompressed dump at which the last page read begins.
+    pub fn page_offset(&self) -> u64 {
+        self.buffer.page_start()
+    }
+
+    /// Offset into the decompressed dump just past the last page read.
+    pub fn offset(&self) -> u64 {
+        self.buffer.offset()
+    }
+}
+
+impl Pages {
+    /// Leaves out a page, failing if the run is to stop there.
+    fn skip(&mut self, e: Error) -> Result<(), Error> {
+        warn!("skipping page: {e}");
+        self.progress.add_error();
+        if let Some(failed) = &mut self.failed {
+            failed.push(e);
+        }
+        exit::page_skipped()
+    }
+}
+
+static VERIFY_SHA1: AtomicBool = AtomicBool::new(false);
+
+/// Makes every dump read check each revision's text against the SHA-1 given for it, and skip
+/// pages where one doesn't match.
+pub fn set_verify_sha1(verify: bool) {
+    VERIFY_SHA1.store(verify, Ordering::Relaxed);
+}
+
+/// The error for a page with a revision whose text is shorter than the `bytes` attribute of
+/// its `<text>` element says, which only a damaged or cut-off dump has. Text deleted from a
+/// revision is gone from the dump but not from the count, and is passed over.
+fn short_text(page: &Page, xml: &str, offset: u64) -> Option<Error> {
+    let declared = xml.match_indices("<text").map(|(idx, _)| {
+        let tag = &xml[idx..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        match tag.contains(" deleted=") {
+            true => None,
+            false => attribute(tag, "bytes").and_then(|bytes| bytes.parse::<usize>().ok()),
+        }
+    });
+    let (expected, found) = declared
+        .zip(page.revisions())
+        .filter_map(|(expected, revision)| Some((expected?, revision.text().len())))
+        .find(|&(expected, found)| found < expected)?;
+    Some(Error::TruncatedText {
+        offset,
+        title: page.title.clone(),
+        expected,
+        found,
+    })
+}
+
+/// The error for a page with a revision that fails its SHA-1 check, when checking.
+fn mismatch(page: &Page, offset: u64) -> Option<Error> {
+    if !VERIFY_SHA1.load(Ordering::Relaxed) {
+        return None;
+    }
+    let revision = page
+        .revisions()
+        .iter()
+        .find(|revision| revision.verify() == Some(false))?;
+    Some(Error::Checksum {
+        offset,
+        title: page.title.clone(),
+        revision: revision.id,
+    })
+}
+
+impl Iterator for Pages {
+    type Item = Page;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // A run stopped under --fail-fast reads no further, in this dump or any other.
+        if exit::stopped().is_err() {
+            return None;
+        }
+        while let Some(read) = self.buffer.read_page() {
+            let offset = self.buffer.page_start();
+            match read.map(|()| Page::from_xml(self.buffer.page(), offset)) {
+                Ok(Ok(page)) => {
+                    if self.buffer.truncated() {
+                        warn!(title = %page.title, "revision text cut short for size");
+                    } else if let Some(e) = short_text(&page, self.buffer.page(), offset)
+                        .or_else(|| mismatch(&page, offset))
+                    {
+                        self.skip(e).ok()?;
+                        continue;
+                    }
+                    self.progress.add_page(&page.title);
+                    return Some(page);
+                }
+                Ok(Err(e)) => self.skip(e).ok()?,
+                // A page cut off by the end of the dump, or one that isn't UTF-8, is left out
+                // like one that fails to parse.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof || is_utf8_error(&e) => {
+                    warn!("failed to read page: {e}");
+                    self.progress.add_error();
+                    exit::page_skipped().ok()?;
+                }
+                // Anything else is the reader failing, which reading on from would only fail
+                // again, so the dump is read no further and the run ends with the error.
+                Err(e) => {
+                    error!(
+                        offset = self.buffer.offset(),
+                        "the dump couldn't be read past this point, so pages after it are missing"
+                    );
+                    exit::read_failed(Error::unreadable(&self.path, e));
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+
+        if !self.buffer.closed() && !self.buffer.ends_early() && !self.finished {
+            error!(
+                offset = self.buffer.offset(),
+                "the dump ends without its closing </mediawiki> tag, so it looks cut short \
+                 and pages after this point are missing"
+            );
+            exit::dump_incomplete();
+        }
+        self.finished = true;
+        None
+    }
+}
+
+fn is_utf8_error(e: &io::Error) -> bool {
+    e.get_ref()
+        .is_some_and(|inner| inner.is::<std::str::Utf8Error>())
+}
+
+/// Reads a dump a page at a time, holding on to the memory it reads into so that going from
+/// one page to the next doesn't allocate once it has seen the largest page so far.
+pub struct PageBuffer<T> {
+    reader: T,
+    /// The line being read, no longer than a [`CHUNK`].
+    line: Vec<u8>,
+    /// The last page read, which lends its memory to `buf` for the next.
+    page: String,
+    offset: u64,
+    page_start: u64,
+    end: Option<u64>,
+    max_text: Option<usize>,
+    truncated: bool,
+    /// Whether the dump's closing `</mediawiki>` tag has been read.
+    closed: bool,
+}
+
+/// Lines are read at most this many bytes at a time, so that a page written on one enormous
+/// line doesn't have to be held all at once to be cut down to size.
+const CHUNK: u64 = 64 << 10;
+
+impl<T> PageBuffer<T>
+where
+    T: BufRead,
+{
+    /// Wraps a reader already `offset` bytes into its input, so offsets stay relative to the
+    /// start of the input.
+    pub fn starting_at(reader: T, offset: u64) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            page: String::new(),
+            offset,
+            page_start: offset,
+            end: None,
+            max_text: None,
+            truncated: false,
+            closed: false,
+        }
+    }
+
+    /// Stops before any page whose opening tag begins at or past `end`.
+    pub fn ending_at(self, end: Option<u64>) -> Self {
+        Self { end, ..self }
+    }
+
+    /// Keeps no more than `max_text` bytes of each revision's text, dropping the rest as it
+    /// is read.
+    pub fn max_text(self, max_text: Option<usize>) -> Self {
+        Self { max_text, ..self }
+    }
+
+    /// Bytes consumed so far, which is just past the last page returned.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Where the opening tag of the last page returned begins.
+    pub fn page_start(&self) -> u64 {
+        self.page_start
+    }
+
+    /// Whether the text of a revision in the last page returned was cut short.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Whether the whole dump has been read, up to its closing tag. One that ends without it
+    /// was cut short, as by a download that didn't finish.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Whether reading stops at an offset short of the end of the dump.
+    pub fn ends_early(&self) -> bool {
+        self.end.is_some()
+    }
+
+    /// The last page read by [`read_page`](Self::read_page).
+    pub fn page(&self) -> &str {
+        &self.page
+    }
+
+    /// Reads the next page, for [`page`](Self::page), into the memory the last one was in.
+    pub fn read_page(&mut self) -> Option<io::Result<()>> {
+        let mut take = false;
+        let mut buf = mem::take(&mut self.page).into_bytes();
+        buf.clear();
+        let line = &mut self.line;
+        // Whether the chunk at hand carries on a line begun in the one before.
+        let mut continued = false;
+        let mut text = Text::default();
+        self.truncated = false;
+
+        loop {
+            line.clear();
+            let line_start = self.offset;
+            match self.reader.by_ref().take(CHUNK).read_until(b'\n', line) {
+                Ok(0) => break,
+                Ok(n) => self.offset += n as u64,
+                Err(e) => return Some(Err(e)),
+            }
+            let was_continued = continued;
+            continued = !line.ends_with(b"\n");
+
+            // The last piece of a line, whether or not it's the first, ends it.
+            let chunk = match continued {
+                false => trim_line_end(line),
+                true => &line[..],
+            };
+            // A `\r\n` split between pieces leaves its `\r` at the end of the one before.
+            if was_continued && take && line.as_slice() == b"\n" && buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            if !was_continued {
+                let trimmed = chunk.trim_ascii();
+                if trimmed == b"<page>" {
+                    if self.end.is_some_and(|end| line_start >= end) {
+                        return None;
+                    }
+                    take = true;
+                    self.page_start = line_start;
+                    buf.extend_from_slice(chunk);
+                    buf.push(b'\n');
+                    continue;
+                }
+
+                if trimmed == b"</page>" {
+                    buf.extend_from_slice(chunk);
+                    buf.push(b'\n');
+                    return Some(self.finish(buf));
+                }
+
+                if !take && trimmed == b"</mediawiki>" {
+                    self.closed = true;
+                }
+            }
+
+            if take {
+                let cut = text.append(&mut buf, chunk, self.max_text);
+                self.truncated |= cut;
+                if !continued {
+                    buf.push(b'\n');
+                }
+            }
+        }
+
+        // A page still open at the end of the input was cut off partway.
+        take.then(|| {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "the dump ends partway through the page at byte {}",
+                    self.page_start
+                ),
+            ))
+        })
+    }
+
+    /// Keeps a page that has been read in full as the current one, if it's UTF-8.
+    fn finish(&mut self, buf: Vec<u8>) -> io::Result<()> {
+        self.page = String::from_utf8(buf)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.utf8_error()))?;
+        Ok(())
+    }
+}
+
+/// Each page as a string of its own. [`PageBuffer::read_page`] saves allocating them.
+impl<T> Iterator for PageBuffer<T>
+where
+    T: BufRead,
+{
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let read = self.read_page()?;
+        Some(read.map(|()| mem::take(&mut self.page)))
+    }
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Tracks the `<text>` element being read, to hold its length to a limit.
+#[derive(Default)]
+struct Text {
+    open: bool,
+    len: usize,
+}
+
+impl Text {
+    /// Appends a chunk of a page, less whatever revision text in it goes past the limit.
+    /// Returns whether anything was left out.
+    fn append(&mut self, buf: &mut Vec<u8>, mut chunk: &[u8], limit: Option<usize>) -> bool {
+        let Some(limit) = limit else {
+            buf.extend_from_slice(chunk);
+            return false;
+        };
+
+        let mut cut = false;
+        while !chunk.is_empty() {
+            if !self.open {
+                // Markup outside of text is small, and always kept.
+                let Some(start) = find(chunk, b"<text") else {
+                    buf.extend_from_slice(chunk);
+                    break;
+                };
+                let Some(end) = chunk[start..].iter().position(|&u| u == b'>') else {
+                    buf.extend_from_slice(chunk);
+                    break;
+                };
+                let end = start + end + 1;
+                buf.extend_from_slice(&chunk[..end]);
+                self.open = chunk[..end].ends_with(b"/>").not();
+                self.len = 0;
+                chunk = &chunk[end..];
+                continue;
+            }
+
+            // Text is escaped, so the first `<` is where `</text>` begins, even if the rest of
+            // it is in the next chunk.
+            let (content, rest) = match chunk.iter().position(|&u| u == b'<') {
+                Some(end) => (&chunk[..end], &chunk[end..]),
+                None => (chunk, &b""[..]),
+            };
+            let room = limit.saturating_sub(self.len);
+            let kept = &content[..fit(content, room)];
+            cut |= kept.len() < content.len();
+            buf.extend_from_slice(kept);
+            self.len += content.len();
+
+            if rest.is_empty() {
+                break;
+            }
+            self.open = false;
+            chunk = rest;
+        }
+        cut
+    }
+}
+
+/// How much of escaped XML text fits in `room` bytes without splitting a character or an
+/// entity like `&amp;`.
+fn fit(text: &[u8], room: usize) -> usize {
+    if text.len() <= room {
+        return text.len();
+    }
+    let mut end = room;
+    while end > 0 && (text[end] & 0xC0) == 0x80 {
+        end -= 1;
+    }
+    match text[..end].iter().rposition(|&u| u == b'&') {
+        Some(amp) if !text[amp..end].contains(&b';') => amp,
+        _ => end,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "  <page>\n    <title>A</title>\n    <revision>\n      \
+                        <text bytes=\"8\">[[B]] c\n</text>\n    </revision>\n  </page>\n";
+
+    fn reading(xml: &str) -> PageBuffer<&[u8]> {
+        PageBuffer::starting_at(xml.as_bytes(), 0)
+    }
+
+    #[test]
+    fn reads_pages_up_to_the_closing_tag() {
+        let xml = format!("<mediawiki>\n{PAGE}{PAGE}</mediawiki>\n");
+        let mut buffer = reading(&xml);
+        for _ in 0..2 {
+            assert!(matches!(buffer.read_page(), Some(Ok(()))));
+            assert_eq!(buffer.page(), PAGE);
+        }
+        assert!(buffer.read_page().is_none());
+        assert!(buffer.closed());
+    }
+
+    #[test]
+    fn notices_a_dump_cut_off() {
+        let xml = format!("<mediawiki>\n{PAGE}");
+        let mut buffer = reading(&xml);
+        assert!(matches!(buffer.read_page(), Some(Ok(()))));
+        assert!(buffer.read_page().is_none());
+        assert!(!buffer.closed());
+
+        let xml = format!("<mediawiki>\n{}", &PAGE[..PAGE.len() - 20]);
+        let mut buffer = reading(&xml);
+        let e = buffer.read_page().unwrap().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn cuts_revision_text_down_to_size() {
+        let mut buffer = reading(PAGE).max_text(Some(3));
+        assert!(matches!(buffer.read_page(), Some(Ok(()))));
+        assert!(buffer.truncated());
+        assert!(buffer.page().contains("<text bytes=\"8\">[[B\n</text>"));
+    }
+
+    #[test]
+    fn reads_lines_longer_than_a_chunk() {
+        // The second puts a line's `\r` last in one piece and its `\n` alone in the next.
+        for len in [2 * CHUNK as usize + 10, CHUNK as usize - 14] {
+            let text = "x".repeat(len);
+            for end in ["\n", "\r\n"] {
+                let lines = ["<page>", "<title>A</title>", "<revision>"];
+                let mut xml = lines.join(end) + end;
+                xml += &format!("<text>{text}</text>{end}</revision>{end}</page>{end}");
+                let mut buffer = reading(&xml);
+                assert!(matches!(buffer.read_page(), Some(Ok(()))));
+                assert_eq!(buffer.page(), xml.replace("\r\n", "\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn finds_text_shorter_than_its_count() {
+        let page = Page::from_xml(PAGE, 0).unwrap();
+        assert!(short_text(&page, PAGE, 0).is_none());
+
+        let short = PAGE.replace("bytes=\"8\"", "bytes=\"9\"");
+        let page = Page::from_xml(&short, 0).unwrap();
+        assert!(matches!(
+            short_text(&page, &short, 0),
+            Some(Error::TruncatedText {
+                expected: 9,
+                found: 8,
+                ..
+            })
+        ));
+
+        let deleted = "<page><title>A</title><revision>\
+                       <text bytes=\"9\" deleted=\"deleted\" /></revision></page>";
+        let page = Page::from_xml(deleted, 0).unwrap();
+        assert!(short_text(&page, deleted, 0).is_none());
+    }
+
+    #[test]
+    fn ends_the_pass_when_the_reader_fails() {
+        let failing =
+            io::Cursor::new(format!("<mediawiki>\n{PAGE}  <page>\n")).chain(FailingReader);
+        let mut pages = Pages {
+            path: "dump.xml".into(),
+            buffer: PageBuffer::starting_at(Box::new(BufReader::new(failing)), 0),
+            progress: Arc::new(Progress::new(0)),
+            failed: None,
+            finished: false,
+        };
+        assert_eq!(pages.next().map(|page| page.title), Some("A".to_owned()));
+        assert!(pages.next().is_none());
+        let e = exit::read_error().unwrap_err();
+        assert!(matches!(
+            Error::find(Some(&e)),
+            Some(Error::Io { path, .. }) if path.as_os_str() == "dump.xml"
+        ));
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("bzip2: invalid data"))
+        }
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark_and_carriage_returns() {
+        let xml = format!(
+            "\u{feff}<mediawiki>\r\n{}</mediawiki>\r\n",
+            PAGE.replace('\n', "\r\n")
+        );
+        for capacity in [1, 2, 64] {
+            let mut reader = BufReader::with_capacity(capacity, xml.as_bytes());
+            assert_eq!(skip_bom(&mut reader).unwrap(), 3);
+            let mut buffer = PageBuffer::starting_at(reader, 3);
+            assert!(matches!(buffer.read_page(), Some(Ok(()))));
+            assert_eq!(buffer.page(), PAGE);
+            assert_eq!(buffer.page_start(), xml.find("  <page>").unwrap() as u64);
+            assert!(buffer.read_page().is_none());
+            assert!(buffer.closed());
+        }
+
+        let mut reader = BufReader::with_capacity(1, &b"<mediawiki>"[..]);
+        assert_eq!(skip_bom(&mut reader).unwrap(), 0);
+        assert_eq!(reader.fill_buf().unwrap(), b"<");
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, io::IsTerminal};
+
+use tracing::info;
+
+use crate::{
+    progress::Bar,
+    sink::{Edge, Output},
+    sql::{self, Value},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// pagelinks.sql dump, optionally compressed
+    pagelinks: String,
+    /// page.sql dump of the same wiki, to name the pages links come from
+    #[clap(long)]
+    pages: String,
+    /// linktarget.sql dump of the same wiki, to name the pages links go to; needed for
+    /// dumps from 2024 on, whose pagelinks refer to link targets by id
+    #[clap(long)]
+    linktarget: Option<String>,
+    /// where to send links, as for extract
+    #[clap(short, long, default_value = "-")]
+    output: Output,
+    /// don't show a progress bar
+    #[clap(long)]
+    no_progress: bool,
+}
+
+/// Builds the link graph from MediaWiki's own table of links rather than from wikitext,
+/// which covers every link on a page, including those that templates add. Only links
+/// between pages in the main namespace are kept, red links among them.
+///
+/// Edges go out in the table's order, which groups them by source page, so an extracted
+/// all-links graph can be checked against them with diff.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let pages = sql::page_titles(&args.pages)?;
+    info!("read {} page titles", pages.len());
+    let targets = match &args.linktarget {
+        Some(path) => Some(link_targets(path)?),
+        None => None,
+    };
+
+    let rows = sql::read_rows(&args.pagelinks)?;
+    let bar =
+        (std::io::stderr().is_terminal() && !args.no_progress).then(|| Bar::spawn(rows.progress()));
+    let mut out = args.output.open(0)?;
+
+    let mut edges = 0;
+    for row in rows {
+        let row = row?;
+        let target = match (row.as_slice(), &targets) {
+            // pl_from, pl_namespace, pl_title, pl_from_namespace
+            ([from, Value::Int(0), Value::Str(title), Value::Int(0), ..], _) => {
+                from.as_int().map(|from| (from, title.replace('_', " ")))
+            }
+            // pl_from, pl_from_namespace, pl_target_id
+            ([from, Value::Int(0), Value::Int(target)], Some(targets)) => {
+                from.as_int().zip(targets.get(target).cloned())
+            }
+            ([_, Value::Int(_), Value::Int(_)], None) => {
+                anyhow::bail!("this pagelinks dump refers to link targets by id; pass --linktarget")
+            }
+            _ => None,
+        };
+        let Some((from, target)) = target else {
+            continue;
+        };
+        let Some(source) = pages.get(&from) else {
+            continue;
+        };
+
+        out.edge(Edge::new(source, &target))?;
+        edges += 1;
+    }
+    out.finish()?;
+
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+
+    info!("wrote {edges} links");
+    Ok(())
+}
+
+/// The titles of the main namespace link targets in a `linktarget.sql` dump by id.
+fn link_targets(path: &str) -> anyhow::Result<HashMap<i64, String>> {
+    let mut targets = HashMap::new();
+    for row in sql::read_rows(path)? {
+        // lt_id, lt_namespace, lt_title
+        if let [Value::Int(id), Value::Int(0), Value::Str(title), ..] = row?.as_slice() {
+            targets.insert(*id, title.replace('_', " "));
+        }
+    }
+    info!("read {} link targets", targets.len());
+    Ok(targets)
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tracing::{error, info, info_span};
+
+use crate::{
+    extract,
+    progress::{Bar, Progress},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// files the dump is split into, such as the pages-articles parts Wikimedia publishes
+    #[clap(required = true)]
+    parts: Vec<String>,
+    /// where to send each part's links, as for extract's --output, with {part} standing for
+    /// the part's file name less its extensions
+    #[clap(short, long)]
+    output: String,
+    /// parts to run at once; defaults to the number of CPUs
+    #[clap(short, long)]
+    jobs: Option<usize>,
+    /// don't show a progress bar
+    #[clap(long)]
+    no_progress: bool,
+    /// options for extract, after a `--`, given to every part with {part} filled in as for
+    /// --output
+    #[clap(last = true)]
+    extract: Vec<String>,
+}
+
+/// One of the files a dump is split into.
+struct Part {
+    path: String,
+    name: String,
+    size: u64,
+}
+
+/// Runs extract over each part of a dump split into several files, as many at once as asked.
+/// The largest parts go first and each worker takes the next part as it finishes one, so that
+/// a few large parts don't leave the rest of the workers idle at the end; Wikimedia's parts
+/// differ tenfold in size. One progress bar covers them all, and a part that fails doesn't
+/// stop the others.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    if !args.output.contains("{part}") {
+        anyhow::bail!("--output needs a {{part}} in it, to give each part an output of its own");
+    }
+
+    let mut parts = Vec::new();
+    for path in &args.parts {
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) => anyhow::bail!("failed to read {path}: {e}"),
+        };
+        parts.push(Part {
+            name: part_name(path),
+            path: path.clone(),
+            size,
+        });
+    }
+    let mut names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+    names.sort_unstable();
+    if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+        anyhow::bail!(
+            "more than one part is named {}, so their outputs would collide",
+            pair[0]
+        );
+    }
+
+    // Each part's options are checked before starting any of them.
+    let mut runs = Vec::new();
+    for part in parts {
+        let fill = |option: &str| option.replace("{part}", &part.name);
+        let options: Vec<String> = args.extract.iter().map(|option| fill(option)).collect();
+        let options = extract::Args::for_part(&part.path, &fill(&args.output), &options)?;
+        runs.push((part, options));
+    }
+    runs.sort_by_key(|(part, _)| std::cmp::Reverse(part.size));
+
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from))
+        .clamp(1, runs.len());
+    let total = runs.iter().map(|(part, _)| part.size).sum();
+    let progress = Arc::new(Progress::new(total));
+    let bar =
+        (io::stderr().is_terminal() && !args.no_progress).then(|| Bar::spawn(progress.clone()));
+    info!("extracting {} parts, {jobs} at a time", runs.len());
+
+    // Taken from the end, so reversed to hand out the largest first.
+    runs.reverse();
+    let queue = Mutex::new(runs);
+    let failed = Mutex::new(Vec::new());
+    let (pages, links) = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let (mut pages, mut links) = (0, 0);
+                    loop {
+                        let Some((part, options)) = queue.lock().unwrap().pop() else {
+                            break;
+                        };
+                        let _span = info_span!("part", name = %part.name).entered();
+                        let watch = |part_progress| progress.add_part(part_progress);
+                        match extract::run_watched(&options, watch) {
+                            Ok(stages) => {
+                                info!("wrote {} links from {} pages", stages.emitted, stages.pages);
+                                pages += stages.pages;
+                                links += stages.emitted;
+                            }
+                            Err(e) => {
+                                error!("{e:#}");
+                                failed.lock().unwrap().push(part.path);
+                            }
+                        }
+                    }
+                    (pages, links)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("worker panicked"))
+            .fold((0, 0), |(pages, links), (p, l)| (pages + p, links + l))
+    });
+
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+    info!(
+        "wrote {links} links from {pages} pages over {} parts",
+        args.parts.len()
+    );
+    let failed = failed.into_inner().unwrap();
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} of {} parts failed: {}",
+            failed.len(),
+            args.parts.len(),
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// A part's file name without the `.xml` and compression extensions dumps carry, so that
+/// `enwiki-20240101-pages-articles1.xml-p1p41242.bz2` becomes
+/// `enwiki-20240101-pages-articles1-p1p41242`.
+fn part_name(path: &str) -> String {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let name = [".bz2", ".gz"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(name);
+    name.replacen(".xml", "", 1)
+}
@@ -0,0 +1,45 @@
+use anyhow::{anyhow, bail};
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// title to start from
+    source: String,
+    /// title to reach
+    target: String,
+    /// print every shortest path rather than just one
+    #[clap(short, long)]
+    all: bool,
+    /// with --all, stop after this many paths
+    #[clap(long, requires = "all")]
+    limit: Option<usize>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let source = graph
+        .id(&args.source)
+        .ok_or_else(|| anyhow!("unknown title: {}", args.source))?;
+    let target = graph
+        .id(&args.target)
+        .ok_or_else(|| anyhow!("unknown title: {}", args.target))?;
+
+    let limit = match args.all {
+        true => args.limit.unwrap_or(usize::MAX),
+        false => 1,
+    };
+    let paths = graph.shortest_paths(source, target, limit);
+    if paths.is_empty() {
+        bail!("no path from {} to {}", args.source, args.target);
+    }
+
+    for path in paths {
+        let titles: Vec<_> = path.into_iter().map(|id| graph.title(id)).collect();
+        println!("{}", titles.join(" -> "));
+    }
+
+    Ok(())
+}
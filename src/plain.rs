@@ -0,0 +1,211 @@
+use std::time::Instant;
+
+use regex::{Captures, Regex};
+
+use crate::{
+    filter::{self, TIME_BUDGET},
+    templates::Templates,
+    wikitext,
+};
+
+/// Turns wikitext into readable plain text: templates, tables, references, comments and
+/// markup go, while links are replaced by the text they display.
+///
+/// Headings are kept as lines of their own, still marked up as `== Heading ==`, so that
+/// text can be split into sections afterward; [`heading`] reads them back.
+#[derive(Debug)]
+pub struct PlainText {
+    links: Regex,
+    external: Regex,
+    tags: Regex,
+    emphasis: Regex,
+}
+
+impl PlainText {
+    pub fn new() -> Self {
+        Self {
+            // File captions can hold links of their own, one level deep.
+            links: Regex::new(r#"\[\[([^|\[\]]+)(?:\|((?:[^\[\]]|\[\[[^\]]*\]\])*))?\]\]"#)
+                .unwrap(),
+            external: Regex::new(r#"\[(?:https?:)?//[^\s\]]+(?: ([^\]]*))?\]"#).unwrap(),
+            tags: Regex::new(r#"</?[a-zA-Z][^>]*>"#).unwrap(),
+            emphasis: Regex::new(r#"'{2,}"#).unwrap(),
+        }
+    }
+
+    pub fn convert(&self, text: &str) -> String {
+        self.render(text, false)
+    }
+
+    /// Converts text as [`convert`](Self::convert) does, but with bold text between pairs of
+    /// [`BOLD`] marks.
+    pub fn convert_marking_bold(&self, text: &str) -> String {
+        self.render(text, true)
+    }
+
+    fn render(&self, text: &str, mark_bold: bool) -> String {
+        let deadline = Instant::now() + TIME_BUDGET;
+        let (text, _) = wikitext::clean(text, &Templates::default(), false, deadline);
+        let text = strip_tables(&text);
+
+        let text = self.links.replace_all(&text, |cx: &Captures| {
+            let target = &cx[1];
+            if filter::is_skipped(target) {
+                return String::new();
+            }
+            match cx.get(2) {
+                Some(label) => label.as_str().to_owned(),
+                None => target.trim_start_matches(':').to_owned(),
+            }
+        });
+        let text = self.external.replace_all(&text, |cx: &Captures| {
+            cx.get(1).map_or("", |label| label.as_str()).to_owned()
+        });
+        let text = self.tags.replace_all(&text, "");
+        let text = self.emphasis.replace_all(&text, |cx: &Captures| {
+            // Three quotes start or end bold, and five bold italics.
+            match cx[0].len() {
+                3 | 5 if mark_bold => BOLD.to_string(),
+                _ => String::new(),
+            }
+        });
+
+        let mut out = String::with_capacity(text.len());
+        let mut blank = true;
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches(['*', '#', ':', ';']).trim();
+            if line.is_empty() {
+                if !blank {
+                    out.push('\n');
+                }
+                blank = true;
+                continue;
+            }
+
+            // Removed templates and references leave runs of spaces behind.
+            let line = unescape(line);
+            for (idx, word) in line.split_whitespace().enumerate() {
+                if idx > 0 {
+                    out.push(' ');
+                }
+                out.push_str(word);
+            }
+            out.push('\n');
+            blank = false;
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
+
+/// Marks the start and end of bold text, from the Unicode private use area so that it can't
+/// turn up in an article.
+pub const BOLD: char = '\u{E000}';
+
+/// Reads a heading line, returning its level (2 for `== Heading ==`) and text.
+pub fn heading(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim();
+    let level = line.bytes().take_while(|&u| u == b'=').count();
+    if level < 2 || !line.ends_with('=') || line.len() <= 2 * level {
+        return None;
+    }
+    let text = line[level..].trim_end_matches('=').trim();
+    (!text.is_empty()).then_some((level, text))
+}
+
+/// Splits plain text into words: runs of letters and digits, along with any apostrophes
+/// inside them, as in "don't".
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|u: char| !(u.is_alphanumeric() || u == '\'' || u == '’'))
+        .map(|word| word.trim_matches(['\'', '’']))
+        .filter(|word| !word.is_empty())
+}
+
+/// The language of plain text as an ISO 639-3 code such as `eng`, if the detector is
+/// confident of it. Lists, tables and other text that isn't prose seldom are.
+pub fn language(text: &str) -> Option<&'static str> {
+    let info = whatlang::detect(text)?;
+    info.is_reliable().then(|| info.lang().code())
+}
+
+/// A stretch of plain text under the headings that lead to it, outermost first. The lead
+/// section, before any heading, has none.
+#[derive(Debug)]
+pub struct Section {
+    pub path: Vec<String>,
+    pub text: String,
+}
+
+/// Splits plain text from [`PlainText::convert`] at its headings, leaving out sections with
+/// no text of their own.
+pub fn sections(text: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut current = String::new();
+
+    let mut finish = |path: &[(usize, String)], current: &mut String| {
+        if !current.trim().is_empty() {
+            sections.push(Section {
+                path: path.iter().map(|(_, heading)| heading.clone()).collect(),
+                text: current.trim().to_owned(),
+            });
+        }
+        current.clear();
+    };
+
+    for line in text.lines() {
+        match heading(line) {
+            Some((level, heading)) => {
+                finish(&path, &mut current);
+                path.retain(|&(outer, _)| outer < level);
+                path.push((level, heading.to_owned()));
+            }
+            None => {
+                current.push_str(line);
+                current.push('\n');
+            }
+        }
+    }
+    finish(&path, &mut current);
+
+    sections
+}
+
+/// Removes `{| ... |}` tables, nested ones included.
+fn strip_tables(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("{|") {
+            depth += 1;
+            continue;
+        }
+        if depth > 0 {
+            if trimmed.starts_with("|}") {
+                depth -= 1;
+            }
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Decodes the entities that turn up in article prose.
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.into();
+    }
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&ndash;", "–")
+        .replace("&mdash;", "—")
+        .replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&#123;", "{")
+        .replace("&#125;", "}");
+    crate::titles::unescape(&text)
+}
@@ -0,0 +1,171 @@
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Counters describing how far a pass over a dump has gotten, shared with whatever is
+/// displaying them. Passes over several dumps at once count as one, with each dump's own
+/// progress added to it as a part.
+#[derive(Debug)]
+pub struct Progress {
+    started: Instant,
+    total: u64,
+    bytes: AtomicU64,
+    pages: AtomicU64,
+    errors: AtomicU64,
+    title: Mutex<String>,
+    parts: Mutex<Vec<Arc<Progress>>>,
+}
+
+impl Progress {
+    pub fn new(total: u64) -> Self {
+        Self {
+            started: Instant::now(),
+            total,
+            bytes: AtomicU64::new(0),
+            pages: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            title: Mutex::new(String::new()),
+            parts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Counts a dump's progress toward this, which should have the dump in its total.
+    pub fn add_part(&self, part: Arc<Progress>) {
+        self.parts.lock().unwrap().push(part);
+    }
+
+    fn sum_parts(&self, count: impl Fn(&Progress) -> u64) -> u64 {
+        self.parts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|part| count(part))
+            .sum()
+    }
+
+    /// Size of the input in bytes, if known.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed) + self.sum_parts(Progress::bytes)
+    }
+
+    pub fn pages(&self) -> u64 {
+        self.pages.load(Ordering::Relaxed) + self.sum_parts(Progress::pages)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed) + self.sum_parts(Progress::errors)
+    }
+
+    /// The title of the page last read, or with parts, of the page the latest part last read.
+    pub fn title(&self) -> String {
+        match self.parts.lock().unwrap().last() {
+            Some(part) => part.title(),
+            None => self.title.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn elapsed_secs(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// Estimated seconds remaining, based on the byte rate so far.
+    pub fn eta_secs(&self) -> Option<f64> {
+        let bytes = self.bytes();
+        if self.total == 0 || bytes == 0 {
+            return None;
+        }
+        let rate = bytes as f64 / self.elapsed_secs();
+        Some(self.total.saturating_sub(bytes) as f64 / rate)
+    }
+
+    pub(crate) fn add_page(&self, title: &str) {
+        self.pages.fetch_add(1, Ordering::Relaxed);
+        let mut current = self.title.lock().unwrap();
+        current.clear();
+        current.push_str(title);
+    }
+
+    /// Counts bytes that were skipped over rather than read.
+    pub(crate) fn add_bytes(&self, n: u64) {
+        self.bytes.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts bytes as they are read from the underlying input.
+pub struct CountingReader<R> {
+    inner: R,
+    progress: Arc<Progress>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R, progress: Arc<Progress>) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// A progress bar on stderr, measured in input bytes.
+pub struct Bar {
+    done: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Bar {
+    pub fn spawn(progress: Arc<Progress>) -> Self {
+        let bar = ProgressBar::new(progress.total());
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{bar:40} {bytes}/{total_bytes} {bytes_per_sec} eta {eta} {msg}",
+            )
+            .unwrap(),
+        );
+
+        let done = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let done = done.clone();
+            move || loop {
+                let finished = done.load(Ordering::Relaxed);
+                let pages = progress.pages();
+                let rate = pages as f64 / progress.elapsed_secs().max(f64::EPSILON);
+                bar.set_position(progress.bytes());
+                bar.set_message(format!("{pages} pages, {rate:.0}/s"));
+
+                if finished {
+                    bar.finish();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+
+        Self { done, handle }
+    }
+
+    pub fn finish(self) {
+        self.done.store(true, Ordering::Relaxed);
+        self.handle.join().expect("progress thread panicked");
+    }
+}
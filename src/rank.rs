@@ -0,0 +1,49 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// probability of following a link rather than jumping to a random page
+    #[clap(short, long, default_value_t = 0.85)]
+    damping: f64,
+    /// number of power iterations
+    #[clap(short, long, default_value_t = 50)]
+    iterations: usize,
+    /// number of top-ranked titles to print
+    #[clap(short = 'n', long, default_value_t = 20)]
+    top: usize,
+    /// write every title's score to this file
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let mut ranks: Vec<_> = graph
+        .pagerank(args.damping, args.iterations)
+        .into_iter()
+        .enumerate()
+        .map(|(id, rank)| (graph.title(id as u32), rank))
+        .collect();
+    ranks.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+
+    for &(title, rank) in ranks.iter().take(args.top) {
+        println!("{rank:.6}\t{title}");
+    }
+
+    if let Some(path) = &args.output {
+        let mut out = File::create(path).map(BufWriter::new)?;
+        for (title, rank) in ranks {
+            writeln!(out, "{title}\t{rank}")?;
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
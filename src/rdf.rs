@@ -0,0 +1,191 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+use wiki_crawler::title::Rules;
+
+use crate::{
+    extract,
+    filter::{LinkExtractor, TextFilter},
+    page,
+    templates::{self, is_named, split_args},
+    titles::article_url,
+};
+
+const LINK: &str = "http://dbpedia.org/ontology/wikiPageWikiLink";
+const REDIRECT: &str = "http://dbpedia.org/ontology/wikiPageRedirects";
+const LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const SUBJECT: &str = "http://purl.org/dc/terms/subject";
+const LAT: &str = "http://www.w3.org/2003/01/geo/wgs84_pos#lat";
+const LONG: &str = "http://www.w3.org/2003/01/geo/wgs84_pos#long";
+const FLOAT: &str = "http://www.w3.org/2001/XMLSchema#float";
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// write triples to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// link each page to everything it links to, rather than only its first link
+    #[clap(long)]
+    all_links: bool,
+    /// the prefix of the IRIs that name pages
+    #[clap(long, default_value = "http://dbpedia.org/resource/")]
+    base: String,
+}
+
+/// Writes the link graph as N-Triples, naming pages with IRIs in the style of DBpedia's.
+/// Each page has its title as a label, its links and categories, and the coordinates its
+/// `{{coord}}` template puts by the title, if any. Redirects get only their label and target.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let site = page::site_info(&args.dump)?;
+    let lang = site.lang.as_deref().unwrap_or("en");
+    let rules = Rules::new().with_namespaces(&site.namespaces);
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+    let iri = |title: &str| format!("<{}>", article_url(&args.base, title));
+
+    let (mut pages, mut triples) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        let subject = iri(&page.title);
+        let mut triple = |predicate: &str, object: &str| {
+            triples += 1;
+            writeln!(out, "{subject} <{predicate}> {object} .")
+        };
+        triple(LABEL, &format!("{}@{lang}", literal(&page.title)))?;
+        pages += 1;
+
+        if let Some(target) = page.redirect() {
+            triple(REDIRECT, &iri(target))?;
+            continue;
+        }
+        for link in extract::page_links(&tf, &ex, &page, args.all_links) {
+            triple(LINK, &iri(&link.target))?;
+        }
+
+        let Some(text) = page.text() else {
+            continue;
+        };
+        for category in categories(&rules, text) {
+            triple(SUBJECT, &iri(&category))?;
+        }
+        if let Some((lat, long)) = coordinates(text) {
+            triple(LAT, &format!("\"{lat}\"^^<{FLOAT}>"))?;
+            triple(LONG, &format!("\"{long}\"^^<{FLOAT}>"))?;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {triples} triples about {pages} pages");
+    Ok(())
+}
+
+/// The categories a page's text puts it in, as full titles such as `Category:Fruits`. Links
+/// written with a leading colon, as in `[[:Category:Fruits]]`, lead to a category without
+/// joining it.
+pub fn categories(rules: &Rules, text: &str) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for (idx, _) in text.match_indices("[[") {
+        let rest = &text[idx + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let target = rest[..end].split('|').next().unwrap_or_default();
+        if target.trim_start().starts_with(':') {
+            continue;
+        }
+        let title = rules.parse(target);
+        if title.namespace.as_deref() == Some("Category") && !title.name.is_empty() {
+            let title = title.to_string();
+            if !categories.contains(&title) {
+                categories.push(title);
+            }
+        }
+    }
+    categories
+}
+
+/// The latitude and longitude in degrees that a page's `{{coord}}` template gives for the
+/// subject of the page, as opposed to places mentioned in it, which have their coordinates
+/// shown inline and not by the title.
+pub fn coordinates(text: &str) -> Option<(f64, f64)> {
+    templates::invocations(text)
+        .filter(|inner| templates::name(inner) == "coord")
+        .map(split_args)
+        .filter(|args| {
+            args.iter().any(|arg| match arg.split_once('=') {
+                Some((name, value)) => {
+                    name.trim() == "display"
+                        && value
+                            .split(',')
+                            .any(|value| matches!(value.trim(), "title" | "t"))
+                }
+                None => false,
+            })
+        })
+        .find_map(|args| {
+            let positional: Vec<&str> = args[1..]
+                .iter()
+                .copied()
+                .filter(|arg| !is_named(arg))
+                .map(str::trim)
+                .collect();
+            position(&positional)
+        })
+}
+
+/// Reads `{{coord}}`'s positional arguments: decimal degrees, as in `51.5|-0.12`, or
+/// degrees with minutes and seconds and a hemisphere, as in `51|30|N|0|7|W`.
+fn position(args: &[&str]) -> Option<(f64, f64)> {
+    let (lat, long) = match args.iter().position(|&arg| matches!(arg, "N" | "S")) {
+        Some(idx) => {
+            let rest = &args[idx + 1..];
+            let end = rest.iter().position(|&arg| matches!(arg, "E" | "W"))?;
+            (
+                degrees(&args[..idx], args[idx] == "S")?,
+                degrees(&rest[..end], rest[end] == "W")?,
+            )
+        }
+        None => match args {
+            [lat, long, ..] => (lat.parse().ok()?, long.parse().ok()?),
+            _ => return None,
+        },
+    };
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&long)).then_some((lat, long))
+}
+
+/// Degrees, and optionally minutes and seconds, as one number of degrees.
+fn degrees(parts: &[&str], negative: bool) -> Option<f64> {
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let mut degrees = 0.0;
+    for (part, scale) in parts.iter().zip([1.0, 60.0, 3600.0]) {
+        degrees += part.parse::<f64>().ok()? / scale;
+    }
+    Some(if negative { -degrees } else { degrees })
+}
+
+/// Writes text as an N-Triples string literal.
+fn literal(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
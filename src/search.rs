@@ -0,0 +1,96 @@
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+use tantivy::{
+    collector::TopDocs, query::QueryParser, schema::Value, snippet::SnippetGenerator, Index,
+    TantivyDocument,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// index built by the index command
+    index: PathBuf,
+    /// what to search for, in tantivy's query syntax
+    query: String,
+    /// number of results to show
+    #[clap(short = 'n', long, default_value_t = 10)]
+    limit: usize,
+    /// longest snippet to show, in characters
+    #[clap(long, default_value_t = 200)]
+    snippet: usize,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let index = Index::open_in_dir(&args.index)?;
+    let schema = index.schema();
+    let title = schema.get_field("title")?;
+    let body = schema.get_field("body")?;
+
+    let searcher = index.reader()?.searcher();
+    let query = QueryParser::for_index(&index, vec![title, body]).parse_query(&args.query)?;
+    let mut snippets = SnippetGenerator::create(&searcher, &*query, body)?;
+    snippets.set_max_num_chars(args.snippet);
+
+    let results = searcher.search(
+        &query,
+        &TopDocs::with_limit(args.limit.max(1)).order_by_score(),
+    )?;
+    let marks = Marks::new(io::stdout().is_terminal());
+    for (score, address) in results {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let name = doc
+            .get_first(title)
+            .and_then(|value| value.as_str())
+            .unwrap_or_default();
+        println!("{score:.2}\t{name}");
+
+        let snippet = snippets.snippet_from_doc(&doc);
+        if !snippet.is_empty() {
+            println!(
+                "\t{}",
+                marks.highlight(snippet.fragment(), snippet.highlighted())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// What to put around matched terms: bold on a terminal, asterisks otherwise.
+struct Marks {
+    open: &'static str,
+    close: &'static str,
+}
+
+impl Marks {
+    fn new(terminal: bool) -> Self {
+        if terminal {
+            Self {
+                open: "\x1b[1m",
+                close: "\x1b[0m",
+            }
+        } else {
+            Self {
+                open: "*",
+                close: "*",
+            }
+        }
+    }
+
+    fn highlight(&self, fragment: &str, ranges: &[std::ops::Range<usize>]) -> String {
+        let mut out = String::with_capacity(fragment.len());
+        let mut last = 0;
+        for range in ranges {
+            out.push_str(&fragment[last..range.start]);
+            out.push_str(self.open);
+            out.push_str(&fragment[range.clone()]);
+            out.push_str(self.close);
+            last = range.end;
+        }
+        out.push_str(&fragment[last..]);
+        // Snippets can span lines, but each result gets just one.
+        out.replace('\n', " ")
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// write sentences to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// start each line with the page title and a tab
+    #[clap(long)]
+    with_title: bool,
+    /// start each line with the language detected in the page and a tab, or - where it's
+    /// unclear
+    #[clap(long)]
+    with_language: bool,
+}
+
+/// Writes cleaned article text one sentence per line, with a blank line between pages.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+
+    let mut count = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        let text = plain.convert(text);
+        let language = args
+            .with_language
+            .then(|| plain::language(&text).unwrap_or("-"));
+        let paragraphs = text
+            .lines()
+            .filter(|line| !line.is_empty() && plain::heading(line).is_none());
+        for sentence in paragraphs.flat_map(split) {
+            if args.with_title {
+                write!(out, "{}\t", page.title)?;
+            }
+            if let Some(language) = language {
+                write!(out, "{language}\t")?;
+            }
+            writeln!(out, "{sentence}")?;
+            count += 1;
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+
+    info!("wrote {count} sentences");
+    Ok(())
+}
+
+/// Abbreviations that are usually followed by a capitalized name rather than a new sentence.
+static ABBREVIATIONS: &[&str] = &[
+    "c", "ca", "capt", "col", "dr", "fig", "gen", "gov", "hon", "jr", "lt", "mr", "mrs", "ms",
+    "mt", "no", "pp", "prof", "rev", "sgt", "sr", "st", "vol", "vs",
+];
+
+/// Splits a paragraph into sentences.
+///
+/// A sentence ends at `.`, `!` or `?`, along with any closing quotes or brackets, when what
+/// follows is a space and then a capital letter, a digit or an opening quote. A full stop
+/// after a known abbreviation or a lone initial, as in "J. R. R. Tolkien", doesn't count.
+/// Decimal points never have a space after them, so they take care of themselves.
+pub fn split(paragraph: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (pos, u) = chars[idx];
+        if !matches!(u, '.' | '!' | '?') {
+            idx += 1;
+            continue;
+        }
+
+        // Take in trailing punctuation, as in `?!` or `."` or `.)`.
+        let mut end = idx + 1;
+        while end < chars.len()
+            && matches!(chars[end].1, '.' | '!' | '?' | '"' | '\'' | ')' | '”' | '’')
+        {
+            end += 1;
+        }
+        let boundary = chars.get(end).is_some_and(|&(_, u)| u.is_whitespace())
+            && chars[end..]
+                .iter()
+                .find(|(_, u)| !u.is_whitespace())
+                .is_some_and(|&(_, u)| {
+                    u.is_uppercase() || u.is_ascii_digit() || matches!(u, '"' | '“' | '\'' | '(')
+                });
+
+        if boundary && !(u == '.' && is_abbreviation(&paragraph[start..pos])) {
+            let byte_end = chars.get(end).map_or(paragraph.len(), |&(pos, _)| pos);
+            sentences.push(paragraph[start..byte_end].trim());
+            start = byte_end;
+        }
+        idx = end;
+    }
+
+    let rest = paragraph[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// Whether the text before a full stop ends in an abbreviation or an initial.
+fn is_abbreviation(before: &str) -> bool {
+    let word = before
+        .rsplit(|u: char| u.is_whitespace() || u == '(')
+        .next()
+        .unwrap_or_default();
+    let word = word.rsplit('.').next().unwrap_or_default();
+
+    let mut letters = word.chars();
+    let initial = matches!((letters.next(), letters.next()), (Some(u), None) if u.is_uppercase());
+    initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::info;
+
+use crate::{
+    graph::{Graph, Outcome},
+    metrics::{self, Exposition},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    #[clap(short, long)]
+    graph: String,
+    /// port to listen on
+    #[clap(short, long, default_value_t = 8080)]
+    port: u16,
+    /// address to bind
+    #[clap(long, default_value = "127.0.0.1")]
+    host: String,
+    /// answer requests on an async runtime, taking any number of connections at once on a
+    /// thread per CPU
+    #[cfg(feature = "async")]
+    #[clap(long = "async")]
+    use_async: bool,
+}
+
+struct State {
+    graph: Graph,
+    backlinks: Vec<Vec<u32>>,
+    stats: Stats,
+}
+
+/// Request counts by endpoint and outcome, for the metrics endpoint.
+#[derive(Debug, Default)]
+struct Stats {
+    first_link: AtomicU64,
+    backlinks: AtomicU64,
+    path: AtomicU64,
+    chain: AtomicU64,
+    other: AtomicU64,
+    client_errors: AtomicU64,
+    not_found: AtomicU64,
+    micros: AtomicU64,
+}
+
+impl Stats {
+    fn record(&self, path: &str, status: u16, elapsed: Duration) {
+        let counter = match path {
+            "/first-link" => &self.first_link,
+            "/backlinks" => &self.backlinks,
+            "/path" => &self.path,
+            "/chain" => &self.chain,
+            _ => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        match status {
+            404 => {
+                self.not_found.fetch_add(1, Ordering::Relaxed);
+            }
+            400..=499 => {
+                self.client_errors.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        self.micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Paths `/path?all=1` gives at most, unless asked for some other `limit`.
+const DEFAULT_PATHS: usize = 100;
+
+type Reply = Result<Value, (u16, String)>;
+
+/// A response to a request, whichever server it came in on.
+struct Answer {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let backlinks = graph.backlinks();
+    let state = State {
+        graph,
+        backlinks,
+        stats: Stats::default(),
+    };
+
+    #[cfg(feature = "async")]
+    if args.use_async {
+        return asynchronous::run(state, &args.host, args.port);
+    }
+
+    let server = Server::http((args.host.as_str(), args.port))
+        .map_err(|e| anyhow::anyhow!("failed to bind {}:{}: {e}", args.host, args.port))?;
+    info!(
+        "serving {} pages on {}:{}",
+        state.graph.len(),
+        args.host,
+        args.port
+    );
+
+    for request in server.incoming_requests() {
+        handle(&state, request);
+    }
+
+    Ok(())
+}
+
+fn handle(state: &State, request: Request) {
+    let answer = answer(state, request.method() == &Method::Get, request.url());
+    let content_type = Header::from_bytes("Content-Type", answer.content_type).unwrap();
+    let response = Response::from_string(answer.body)
+        .with_status_code(answer.status)
+        .with_header(content_type);
+
+    // A client hanging up early is its own problem.
+    let _ = request.respond(response);
+}
+
+/// Answers a request for `url`, which is a GET request or something the server doesn't
+/// serve.
+fn answer(state: &State, get: bool, url: &str) -> Answer {
+    let started = Instant::now();
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params: HashMap<_, _> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+
+    if (get, path) == (true, "/metrics") {
+        return Answer {
+            status: 200,
+            content_type: metrics::CONTENT_TYPE,
+            body: metrics(state).into_text(),
+        };
+    }
+
+    let reply = match (get, path) {
+        (true, "/first-link") => first_link(state, &params),
+        (true, "/backlinks") => backlinks(state, &params),
+        (true, "/path") => shortest_path(state, &params),
+        (true, "/chain") => chain(state, &params),
+        _ => Err((404, format!("no such endpoint: {path}"))),
+    };
+
+    let (status, body) = match reply {
+        Ok(body) => (200, body),
+        Err((status, message)) => (status, json!({ "error": message })),
+    };
+
+    state.stats.record(path, status, started.elapsed());
+    Answer {
+        status,
+        content_type: "application/json",
+        body: body.to_string(),
+    }
+}
+
+/// Serving on tokio rather than a blocking server, so that connections cost a task each
+/// rather than a thread. Queries run on tokio's blocking pool, since a path search over a
+/// large graph can take long enough to hold up every connection on a worker.
+#[cfg(feature = "async")]
+mod asynchronous {
+    use std::{convert::Infallible, sync::Arc};
+
+    use http_body_util::Full;
+    use hyper::{body::Bytes, server::conn::http1, service::service_fn, Method, Request};
+    use hyper_util::rt::TokioIo;
+    use tokio::{net::TcpListener, runtime, task};
+    use tracing::{debug, info, warn};
+
+    use super::State;
+
+    pub fn run(state: State, host: &str, port: u16) -> anyhow::Result<()> {
+        let runtime = runtime::Builder::new_multi_thread().enable_io().build()?;
+        let state = Arc::new(state);
+
+        runtime.block_on(async move {
+            let listener = TcpListener::bind((host, port))
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to bind {host}:{port}: {e}"))?;
+            info!(
+                "serving {} pages on {host}:{port}, asynchronously",
+                state.graph.len()
+            );
+
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    // Running out of file descriptors passes as connections close.
+                    Err(e) => {
+                        warn!("failed to accept a connection: {e}");
+                        continue;
+                    }
+                };
+
+                let state = state.clone();
+                let service = service_fn(move |request: Request<_>| {
+                    let state = state.clone();
+                    let get = request.method() == Method::GET;
+                    let url = request
+                        .uri()
+                        .path_and_query()
+                        .map_or("/", |url| url.as_str())
+                        .to_owned();
+                    async move {
+                        let answer = task::spawn_blocking(move || super::answer(&state, get, &url))
+                            .await
+                            .expect("query panicked");
+                        let response = hyper::Response::builder()
+                            .status(answer.status)
+                            .header("Content-Type", answer.content_type)
+                            .body(Full::new(Bytes::from(answer.body)));
+                        Ok::<_, Infallible>(response.unwrap())
+                    }
+                });
+                tokio::spawn(async move {
+                    let connection =
+                        http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+                    // A client hanging up early is its own problem.
+                    if let Err(e) = connection.await {
+                        debug!("connection ended: {e}");
+                    }
+                });
+            }
+        })
+    }
+}
+
+fn metrics(state: &State) -> Exposition {
+    let stats = &state.stats;
+    let mut out = Exposition::default();
+    out.gauge(
+        "wiki_crawler_graph_pages",
+        "Pages in the loaded graph.",
+        state.graph.len(),
+    );
+    out.gauge(
+        "wiki_crawler_graph_links",
+        "Links in the loaded graph.",
+        state.graph.link_count(),
+    );
+    out.labeled(
+        "wiki_crawler_requests_total",
+        "counter",
+        "Requests received, by endpoint.",
+        [
+            (
+                "endpoint=\"/first-link\"",
+                stats.first_link.load(Ordering::Relaxed),
+            ),
+            (
+                "endpoint=\"/backlinks\"",
+                stats.backlinks.load(Ordering::Relaxed),
+            ),
+            ("endpoint=\"/path\"", stats.path.load(Ordering::Relaxed)),
+            ("endpoint=\"/chain\"", stats.chain.load(Ordering::Relaxed)),
+            ("endpoint=\"other\"", stats.other.load(Ordering::Relaxed)),
+        ],
+    );
+    out.labeled(
+        "wiki_crawler_request_errors_total",
+        "counter",
+        "Requests that failed, by kind.",
+        [
+            (
+                "kind=\"not_found\"",
+                stats.not_found.load(Ordering::Relaxed),
+            ),
+            (
+                "kind=\"bad_request\"",
+                stats.client_errors.load(Ordering::Relaxed),
+            ),
+        ],
+    );
+    out.counter(
+        "wiki_crawler_request_seconds_total",
+        "Time spent answering requests.",
+        stats.micros.load(Ordering::Relaxed) as f64 / 1e6,
+    );
+    out
+}
+
+fn first_link(state: &State, params: &HashMap<String, String>) -> Reply {
+    let (title, id) = lookup(state, params, "title")?;
+    let link = state.graph.first_link(id).map(|id| state.graph.title(id));
+    Ok(json!({ "title": title, "link": link }))
+}
+
+fn backlinks(state: &State, params: &HashMap<String, String>) -> Reply {
+    let (title, id) = lookup(state, params, "title")?;
+    let mut sources: Vec<_> = state.backlinks[id as usize]
+        .iter()
+        .map(|&id| state.graph.title(id))
+        .collect();
+    sources.sort_unstable();
+    Ok(json!({ "title": title, "backlinks": sources }))
+}
+
+fn shortest_path(state: &State, params: &HashMap<String, String>) -> Reply {
+    let (_, source) = lookup(state, params, "source")?;
+    let (_, target) = lookup(state, params, "target")?;
+    let all = params
+        .get("all")
+        .is_some_and(|all| all == "true" || all == "1");
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse()
+            .map_err(|_| (400, format!("bad limit: {limit}")))?,
+        None => DEFAULT_PATHS,
+    };
+
+    let paths: Vec<Vec<_>> = state
+        .graph
+        .shortest_paths(source, target, if all { limit } else { 1 })
+        .into_iter()
+        .map(|path| path.into_iter().map(|id| state.graph.title(id)).collect())
+        .collect();
+    Ok(json!({ "paths": paths }))
+}
+
+fn chain(state: &State, params: &HashMap<String, String>) -> Reply {
+    let (_, start) = lookup(state, params, "title")?;
+    let target = params.get("target").map_or("Philosophy", String::as_str);
+    let target = state.graph.id(target).unwrap_or(u32::MAX);
+
+    let chain = state.graph.chain(start, target);
+    let path: Vec<_> = chain.path.iter().map(|&id| state.graph.title(id)).collect();
+    let (outcome, loop_start) = match chain.outcome {
+        Outcome::Reached => ("reached", None),
+        Outcome::DeadEnd => ("dead-end", None),
+        Outcome::Loop(idx) => ("loop", Some(idx)),
+    };
+    Ok(json!({ "path": path, "outcome": outcome, "loop_start": loop_start }))
+}
+
+fn lookup<'a>(
+    state: &'a State,
+    params: &HashMap<String, String>,
+    name: &str,
+) -> Result<(&'a str, u32), (u16, String)> {
+    let title = params
+        .get(name)
+        .ok_or_else(|| (400, format!("missing parameter: {name}")))?;
+    let id = state
+        .graph
+        .id(title)
+        .ok_or_else(|| (404, format!("unknown title: {title}")))?;
+    Ok((state.graph.title(id), id))
+}
@@ -0,0 +1,1672 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    mem,
+    net::TcpStream,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use bzip2::write::BzEncoder;
+use flate2::write::{DeflateEncoder, GzEncoder};
+use postgres::{Client, NoTls};
+use rusqlite::Connection;
+
+use wiki_crawler::{model::Page, Error};
+
+use crate::graph;
+
+/// An extracted link, along with anything else asked to go with it.
+#[derive(Debug, Clone, Copy)]
+pub struct Edge<'a> {
+    pub source: &'a str,
+    pub target: &'a str,
+    /// Article URLs for the source and target, when wanted alongside their titles.
+    pub urls: Option<(&'a str, &'a str)>,
+    /// Chosen fields, already joined into a line, for sinks that write lines to write in
+    /// place of the usual ones.
+    pub columns: Option<&'a str>,
+    /// The page the link is on, for sinks that record pages as well as links.
+    pub page: Option<&'a Page>,
+}
+
+impl<'a> Edge<'a> {
+    pub fn new(source: &'a str, target: &'a str) -> Self {
+        Self {
+            source,
+            target,
+            urls: None,
+            columns: None,
+            page: None,
+        }
+    }
+}
+
+/// Edges are written as `source -> target` lines, with any URLs in tab-separated columns
+/// after them, unless they come with columns of their own.
+impl fmt::Display for Edge<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(columns) = self.columns {
+            return f.write_str(columns);
+        }
+        write!(f, "{} -> {}", self.source, self.target)?;
+        if let Some((source, target)) = self.urls {
+            write!(f, "\t{source}\t{target}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Somewhere to send extracted edges.
+pub trait Sink {
+    /// Marks the start of a page's edges, for sinks that record pages as well as links. Every
+    /// page read gets one, including those without edges.
+    fn page(&mut self, _page: &Page) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()>;
+
+    /// Makes everything sent so far durable, returning a position the sink can later be
+    /// reopened at to pick up from here, or `None` if it can't be.
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>>;
+
+    /// Writes out whatever is still buffered.
+    fn finish(&mut self) -> anyhow::Result<()>;
+}
+
+/// Where output goes, chosen by how it's written on the command line:
+///
+/// - `-` for stdout
+/// - a path, compressed if it ends in `.gz` or `.bz2`, as id pairs if it ends in `.pairs`, as
+///   an Avro container file if it ends in `.avro`, or as MessagePack arrays if it ends in
+///   `.msgpack`
+/// - `sqlite:PATH` for a `links` table in an SQLite database
+/// - `duckdb:PATH` for a `links` table in a DuckDB database, in builds with the `duckdb`
+///   feature
+/// - `postgres://...` or `postgresql://...` for `links` and `pages` tables in PostgreSQL
+/// - `tcp://HOST:PORT` to stream lines to a socket
+/// - `nats://[USER:PASSWORD@]HOST:PORT/SUBJECT` to publish each line as a NATS message
+#[derive(Debug, Clone)]
+pub enum Output {
+    Stdout,
+    File(PathBuf),
+    Gzip(PathBuf),
+    Bzip2(PathBuf),
+    Pairs(PathBuf),
+    Avro(PathBuf),
+    Msgpack(PathBuf),
+    Sqlite(PathBuf),
+    #[cfg(feature = "duckdb")]
+    Duckdb(PathBuf),
+    Postgres(String),
+    Tcp(String),
+    Nats(String),
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            return Ok(Output::Stdout);
+        }
+        if let Some(path) = s.strip_prefix("sqlite:") {
+            return Ok(Output::Sqlite(path.into()));
+        }
+        if let Some(path) = s.strip_prefix("duckdb:") {
+            #[cfg(feature = "duckdb")]
+            return Ok(Output::Duckdb(path.into()));
+            #[cfg(not(feature = "duckdb"))]
+            return Err(format!(
+                "can't write {path}: this build has no DuckDB support; build with --features duckdb"
+            ));
+        }
+        if s.starts_with("postgres://") || s.starts_with("postgresql://") {
+            s.parse::<postgres::Config>()
+                .map_err(|e| format!("bad PostgreSQL URL: {e}"))?;
+            return Ok(Output::Postgres(s.into()));
+        }
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(Output::Tcp(addr.into()));
+        }
+        if s.starts_with("nats://") {
+            NatsTarget::parse(s)?;
+            return Ok(Output::Nats(s.into()));
+        }
+
+        let path = PathBuf::from(s);
+        Ok(match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Output::Gzip(path),
+            Some("bz2") => Output::Bzip2(path),
+            Some("pairs") => Output::Pairs(path),
+            Some("avro") => Output::Avro(path),
+            Some("msgpack") => Output::Msgpack(path),
+            _ => Output::File(path),
+        })
+    }
+}
+
+/// Written the way it was given on the command line.
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Stdout => f.write_str("-"),
+            Output::File(path)
+            | Output::Gzip(path)
+            | Output::Bzip2(path)
+            | Output::Pairs(path)
+            | Output::Avro(path)
+            | Output::Msgpack(path) => write!(f, "{}", path.display()),
+            Output::Sqlite(path) => write!(f, "sqlite:{}", path.display()),
+            #[cfg(feature = "duckdb")]
+            Output::Duckdb(path) => write!(f, "duckdb:{}", path.display()),
+            Output::Postgres(url) => f.write_str(&redact(url)),
+            Output::Tcp(addr) => write!(f, "tcp://{addr}"),
+            Output::Nats(url) => f.write_str(&NatsTarget::parse(url).unwrap().redacted),
+        }
+    }
+}
+
+impl Output {
+    /// Whether the output is written as lines of text, which can hold any columns.
+    pub fn is_lines(&self) -> bool {
+        match self {
+            Output::Pairs(_)
+            | Output::Avro(_)
+            | Output::Msgpack(_)
+            | Output::Sqlite(_)
+            | Output::Postgres(_) => false,
+            #[cfg(feature = "duckdb")]
+            Output::Duckdb(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Whether edges are written out as they come, a batch at a time at most, rather than
+    /// building up in memory: id pairs keep every title's id, and DuckDB caches as much as it
+    /// can get.
+    pub fn is_streaming(&self) -> bool {
+        match self {
+            Output::Pairs(_) => false,
+            #[cfg(feature = "duckdb")]
+            Output::Duckdb(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Checkpoints live alongside the output they describe, so outputs that aren't files on
+    /// this machine can't have them.
+    pub fn local_path(&self) -> Option<&PathBuf> {
+        match self {
+            Output::File(path)
+            | Output::Gzip(path)
+            | Output::Bzip2(path)
+            | Output::Pairs(path)
+            | Output::Avro(path)
+            | Output::Msgpack(path)
+            | Output::Sqlite(path) => Some(path),
+            #[cfg(feature = "duckdb")]
+            Output::Duckdb(path) => Some(path),
+            Output::Stdout | Output::Postgres(_) | Output::Tcp(_) | Output::Nats(_) => None,
+        }
+    }
+
+    /// Whether the output is a database that records the pages its links are on, and so can
+    /// be added to by [`Output::reopen`].
+    pub fn keeps_pages(&self) -> bool {
+        matches!(self, Output::Sqlite(_) | Output::Postgres(_))
+    }
+
+    /// Opens the output, cut back to `position` as returned by an earlier checkpoint, or
+    /// emptied if `position` is zero.
+    pub fn open(&self, position: u64) -> anyhow::Result<Box<dyn Sink>> {
+        self.open_at(position, false)
+    }
+
+    /// Opens a database output as it is, passing over pages it already holds from the same
+    /// revision and replacing the links of those it holds from another, so that a run can be
+    /// made again over one that stopped partway without emptying the database first.
+    pub fn reopen(&self) -> anyhow::Result<Box<dyn Sink>> {
+        self.open_at(0, true)
+    }
+
+    fn open_at(&self, position: u64, keep: bool) -> anyhow::Result<Box<dyn Sink>> {
+        self.open_sink(position, keep).map_err(|source| {
+            let output = self.to_string();
+            let source = source.into();
+            Error::Sink { output, source }.into()
+        })
+    }
+
+    fn open_sink(&self, position: u64, keep: bool) -> anyhow::Result<Box<dyn Sink>> {
+        Ok(match self {
+            Output::Stdout => Box::new(LineSink {
+                out: BufWriter::new(io::stdout().lock()),
+            }),
+            Output::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)
+                    .with_context(|| format!("failed to connect to {addr}"))?;
+                Box::new(LineSink {
+                    out: BufWriter::new(stream),
+                })
+            }
+            Output::File(path) => Box::new(FileSink {
+                out: BufWriter::new(open_file(path, position)?),
+                written: position,
+            }),
+            Output::Gzip(path) | Output::Bzip2(path) => {
+                let gzip = matches!(self, Output::Gzip(_));
+                let file = open_file(path, position)?;
+                Box::new(CompressedSink {
+                    file: file.try_clone()?,
+                    encoder: Some(BufWriter::new(Encoder::new(gzip, file))),
+                    gzip,
+                })
+            }
+            Output::Nats(url) => Box::new(NatsSink::connect(&NatsTarget::parse(url).unwrap())?),
+            Output::Pairs(path) => Box::new(PairSink::open(path, position)?),
+            Output::Avro(path) => Box::new(AvroSink::open(path, position)?),
+            Output::Msgpack(path) => Box::new(MsgpackSink {
+                out: BufWriter::new(open_file(path, position)?),
+                record: Vec::new(),
+                written: position,
+            }),
+            Output::Sqlite(path) => Box::new(SqliteSink::open(path, position, keep)?),
+            #[cfg(feature = "duckdb")]
+            Output::Duckdb(path) => Box::new(DuckdbSink::open(path, position)?),
+            Output::Postgres(url) => Box::new(PostgresSink::connect(url, keep)?),
+        })
+    }
+}
+
+/// A sink that writes nothing but the first few edges sent to it, to stdout, for runs that
+/// only check what would be written.
+pub fn sample(limit: usize) -> Box<dyn Sink> {
+    Box::new(SampleSink {
+        out: io::stdout(),
+        limit,
+    })
+}
+
+struct SampleSink {
+    out: io::Stdout,
+    limit: usize,
+}
+
+impl Sink for SampleSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        if self.limit > 0 {
+            self.limit -= 1;
+            writeln!(self.out, "{edge}")?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// Opens a file truncated to `len` and positioned at its end.
+fn open_file(path: &PathBuf, len: u64) -> anyhow::Result<File> {
+    if len == 0 {
+        return File::create(path).with_context(|| format!("failed to create {}", path.display()));
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to reopen {}", path.display()))?;
+    // Anything shorter than the checkpoint was lost before it reached the disk.
+    if file.metadata()?.len() < len {
+        anyhow::bail!("{} is shorter than its checkpoint records", path.display());
+    }
+    file.set_len(len)?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(file)
+}
+
+/// Writes `source -> target` lines to a stream that can't be rewound.
+struct LineSink<W: Write> {
+    out: BufWriter<W>,
+}
+
+impl<W: Write> Sink for LineSink<W> {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        writeln!(self.out, "{edge}")?;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.out.flush()?;
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// A URL with any password in it starred out, for messages.
+fn redact(s: &str) -> String {
+    match url::Url::parse(s) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            url.to_string()
+        }
+        _ => s.to_owned(),
+    }
+}
+
+/// How often a NATS sink checks in with the server, well within the two minutes servers wait
+/// by default before pinging a client themselves.
+const NATS_PING_EVERY: Duration = Duration::from_secs(30);
+
+/// Where a NATS output publishes to, as read from its URL.
+struct NatsTarget {
+    addr: String,
+    subject: String,
+    user: Option<(String, String)>,
+    /// The URL less any password, for messages.
+    redacted: String,
+}
+
+impl NatsTarget {
+    fn parse(s: &str) -> Result<Self, String> {
+        let url = url::Url::parse(s).map_err(|e| format!("bad NATS URL {s}: {e}"))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| format!("NATS URL {s} has no host"))?;
+        let subject = url.path().trim_start_matches('/');
+        if subject.is_empty() || subject.contains(char::is_whitespace) {
+            return Err(format!(
+                "NATS URL {s} needs a subject, without spaces, as its path"
+            ));
+        }
+
+        let user = (!url.username().is_empty()).then(|| {
+            let password = url.password().unwrap_or_default();
+            (url.username().to_owned(), password.to_owned())
+        });
+        Ok(Self {
+            addr: format!("{host}:{}", url.port().unwrap_or(4222)),
+            subject: subject.to_owned(),
+            user,
+            redacted: redact(s),
+        })
+    }
+}
+
+/// Publishes each line as a message on a NATS subject, speaking the client protocol
+/// directly.
+///
+/// NATS acknowledges nothing it's sent, only pings, so a checkpoint pings and waits: a reply
+/// means the server has handled every message before it. Pinging every so often as well
+/// keeps a long run from getting ahead of the server, and answers the server's own pings,
+/// which it drops connections for ignoring.
+struct NatsSink {
+    out: BufWriter<TcpStream>,
+    replies: BufReader<TcpStream>,
+    subject: String,
+    line: String,
+    last_ping: Instant,
+}
+
+impl NatsSink {
+    fn connect(target: &NatsTarget) -> anyhow::Result<Self> {
+        let stream = TcpStream::connect(&target.addr)
+            .with_context(|| format!("failed to connect to {}", target.addr))?;
+        let mut sink = Self {
+            replies: BufReader::new(stream.try_clone()?),
+            out: BufWriter::new(stream),
+            subject: target.subject.clone(),
+            line: String::new(),
+            last_ping: Instant::now(),
+        };
+
+        // The server introduces itself before anything else.
+        let info = sink.reply()?;
+        if !info.starts_with("INFO ") {
+            anyhow::bail!("{} doesn't speak NATS: {info}", target.addr);
+        }
+        let mut options = serde_json::json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "lang": "rust",
+        });
+        if let Some((user, pass)) = &target.user {
+            options["user"] = user.as_str().into();
+            options["pass"] = pass.as_str().into();
+        }
+        write!(sink.out, "CONNECT {options}\r\n")?;
+        // Bad credentials are found out here rather than at the first checkpoint.
+        sink.ping()?;
+        Ok(sink)
+    }
+
+    /// The next line from the server, less its line ending.
+    fn reply(&mut self) -> anyhow::Result<String> {
+        self.line.clear();
+        if self.replies.read_line(&mut self.line)? == 0 {
+            anyhow::bail!("NATS server closed the connection");
+        }
+        Ok(self.line.trim_end().to_owned())
+    }
+
+    /// Waits until the server has handled everything sent so far.
+    fn ping(&mut self) -> anyhow::Result<()> {
+        self.out.write_all(b"PING\r\n")?;
+        self.out.flush()?;
+        self.last_ping = Instant::now();
+        loop {
+            let reply = self.reply()?;
+            match reply.as_str() {
+                "PONG" => return Ok(()),
+                // The server checks on us too.
+                "PING" => {
+                    self.out.write_all(b"PONG\r\n")?;
+                    self.out.flush()?;
+                }
+                _ if reply.starts_with("-ERR") => {
+                    anyhow::bail!("NATS server refused: {}", &reply[4..].trim())
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Sink for NatsSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let payload = edge.to_string();
+        write!(
+            self.out,
+            "PUB {} {}\r\n{payload}\r\n",
+            self.subject,
+            payload.len()
+        )?;
+        if self.last_ping.elapsed() > NATS_PING_EVERY {
+            self.ping()?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.ping()?;
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.ping()
+    }
+}
+
+/// Writes `source -> target` lines to a file, keeping count of its length.
+struct FileSink {
+    out: BufWriter<File>,
+    written: u64,
+}
+
+impl Sink for FileSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let line = format!("{edge}\n");
+        self.out.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.out.flush()?;
+        self.out.get_ref().sync_data()?;
+        Ok(Some(self.written))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// Writes compressed `source -> target` lines to a file.
+///
+/// Each checkpoint closes off the compressed stream and starts another after it. The readers
+/// used for dumps handle such back-to-back streams, and it means a resumed run can cut the
+/// file back to a checkpoint and carry on from a clean stream boundary.
+struct CompressedSink {
+    // A second handle on the file, for syncing it to disk.
+    file: File,
+    encoder: Option<BufWriter<Encoder>>,
+    gzip: bool,
+}
+
+enum Encoder {
+    Gzip(GzEncoder<File>),
+    Bzip2(BzEncoder<File>),
+}
+
+impl Encoder {
+    fn new(gzip: bool, file: File) -> Self {
+        if gzip {
+            Encoder::Gzip(GzEncoder::new(file, flate2::Compression::default()))
+        } else {
+            Encoder::Bzip2(BzEncoder::new(file, bzip2::Compression::default()))
+        }
+    }
+
+    fn finish(self) -> io::Result<File> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Bzip2(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl CompressedSink {
+    /// Ends the current stream, handing back the file it was writing to.
+    fn end_stream(&mut self) -> anyhow::Result<File> {
+        let encoder = self.encoder.take().expect("sink already finished");
+        let encoder = encoder.into_inner().map_err(|e| e.into_error())?;
+        Ok(encoder.finish()?)
+    }
+}
+
+impl Sink for CompressedSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let out = self.encoder.as_mut().expect("sink already finished");
+        writeln!(out, "{edge}")?;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        let mut file = self.end_stream()?;
+        self.file.sync_data()?;
+        let position = file.stream_position()?;
+        self.encoder = Some(BufWriter::new(Encoder::new(self.gzip, file)));
+        Ok(Some(position))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.end_stream()?;
+        Ok(())
+    }
+}
+
+/// Writes edges as pairs of little-endian `u32` ids, with the titles they stand for written
+/// to a dictionary of `id\ttitle` lines alongside. See [`graph::titles_path`].
+struct PairSink {
+    pairs: BufWriter<File>,
+    titles: BufWriter<File>,
+    ids: HashMap<String, u32>,
+    written: u64,
+}
+
+impl PairSink {
+    fn open(path: &PathBuf, position: u64) -> anyhow::Result<Self> {
+        let titles_path = graph::titles_path(path);
+        let mut ids = HashMap::new();
+
+        // The dictionary is only ever appended to, so whatever it held at the checkpoint is
+        // still there, along with perhaps some titles no remaining pair refers to. Those are
+        // harmless, but a line cut short by a crash is not.
+        let titles = if position == 0 {
+            File::create(&titles_path)?
+        } else {
+            let mut kept = 0;
+            let text = fs::read(&titles_path)
+                .with_context(|| format!("failed to read {}", titles_path.display()))?;
+            for line in text.split_inclusive(|&b| b == b'\n') {
+                let Some(line) = line.strip_suffix(b"\n") else {
+                    break;
+                };
+                let line = std::str::from_utf8(line)?;
+                let (id, title) = line
+                    .split_once('\t')
+                    .with_context(|| format!("malformed dictionary line: {line}"))?;
+                ids.insert(title.to_owned(), id.parse()?);
+                kept += line.len() as u64 + 1;
+            }
+            open_file(&titles_path, kept)?
+        };
+
+        Ok(Self {
+            pairs: BufWriter::new(open_file(path, position)?),
+            titles: BufWriter::new(titles),
+            ids,
+            written: position,
+        })
+    }
+
+    fn id(&mut self, title: &str) -> anyhow::Result<u32> {
+        let title = graph::normalize(title);
+        if let Some(&id) = self.ids.get(&title) {
+            return Ok(id);
+        }
+
+        let id = self.ids.len() as u32;
+        writeln!(self.titles, "{id}\t{title}")?;
+        self.ids.insert(title, id);
+        Ok(id)
+    }
+}
+
+impl Sink for PairSink {
+    /// Only the titles have ids, so URLs are left out.
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let source = self.id(edge.source)?;
+        let target = self.id(edge.target)?;
+        self.pairs.write_all(&source.to_le_bytes())?;
+        self.pairs.write_all(&target.to_le_bytes())?;
+        self.written += 8;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        // Titles go to disk first so that no pair on disk refers to one that isn't.
+        self.titles.flush()?;
+        self.titles.get_ref().sync_data()?;
+        self.pairs.flush()?;
+        self.pairs.get_ref().sync_data()?;
+        Ok(Some(self.written))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.titles.flush()?;
+        self.pairs.flush()?;
+        Ok(())
+    }
+}
+
+/// The schema of the records an Avro output holds: a union of one for each link, and one for
+/// each page read, which comes before the links on it.
+const AVRO_SCHEMA: &str = r#"[
+  {
+    "type": "record",
+    "name": "Link",
+    "namespace": "wiki_crawler",
+    "fields": [
+      {"name": "source", "type": "string"},
+      {"name": "target", "type": "string"},
+      {"name": "source_url", "type": ["null", "string"], "default": null},
+      {"name": "target_url", "type": ["null", "string"], "default": null},
+      {"name": "page_id", "type": ["null", "long"], "default": null},
+      {"name": "page_ns", "type": ["null", "long"], "default": null}
+    ]
+  },
+  {
+    "type": "record",
+    "name": "Page",
+    "namespace": "wiki_crawler",
+    "fields": [
+      {"name": "id", "type": ["null", "long"], "default": null},
+      {"name": "title", "type": "string"},
+      {"name": "ns", "type": ["null", "long"], "default": null},
+      {"name": "redirect", "type": ["null", "string"], "default": null}
+    ]
+  }
+]"#;
+
+/// The branches of [`AVRO_SCHEMA`], which each record starts with.
+const AVRO_LINK: i64 = 0;
+const AVRO_PAGE: i64 = 1;
+
+/// Writes edges and the pages they're on to an Avro object container file, with
+/// [`AVRO_SCHEMA`] embedded in its header and blocks of records compressed with deflate.
+///
+/// Every block ends with the file's sync marker, so a checkpoint writes out the block under
+/// way and the file can be cut back to the end of it. Picking up again reads the marker back
+/// from the header.
+struct AvroSink {
+    out: BufWriter<File>,
+    sync: [u8; 16],
+    /// Records encoded since the last block was written.
+    block: Vec<u8>,
+    records: u64,
+    written: u64,
+}
+
+impl AvroSink {
+    const BLOCK: u64 = 10_000;
+
+    fn open(path: &PathBuf, position: u64) -> anyhow::Result<Self> {
+        if position > 0 {
+            let header = BufReader::new(File::open(path)?);
+            let sync = avro_sync_marker(header)
+                .with_context(|| format!("{} has no Avro header", path.display()))?;
+            return Ok(Self {
+                out: BufWriter::new(open_file(path, position)?),
+                sync,
+                block: Vec::new(),
+                records: 0,
+                written: position,
+            });
+        }
+
+        let mut file = BufWriter::new(open_file(path, 0)?);
+        let sync: [u8; 16] = rand::random();
+        let mut header = b"Obj\x01".to_vec();
+        // The metadata map: one block of two entries, then an empty block to end it.
+        avro_long(&mut header, 2);
+        avro_bytes(&mut header, b"avro.schema");
+        avro_bytes(&mut header, AVRO_SCHEMA.as_bytes());
+        avro_bytes(&mut header, b"avro.codec");
+        avro_bytes(&mut header, b"deflate");
+        avro_long(&mut header, 0);
+        header.extend_from_slice(&sync);
+        file.write_all(&header)?;
+
+        Ok(Self {
+            out: file,
+            sync,
+            block: Vec::new(),
+            records: 0,
+            written: header.len() as u64,
+        })
+    }
+
+    /// Counts a record just encoded, writing out the block once it's full.
+    fn record(&mut self) -> anyhow::Result<()> {
+        self.records += 1;
+        if self.records >= Self::BLOCK {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> anyhow::Result<()> {
+        if self.records == 0 {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&self.block)?;
+        let data = encoder.finish()?;
+
+        let mut head = Vec::new();
+        avro_long(&mut head, self.records as i64);
+        avro_long(&mut head, data.len() as i64);
+        for part in [&head[..], &data, &self.sync] {
+            self.out.write_all(part)?;
+            self.written += part.len() as u64;
+        }
+        self.block.clear();
+        self.records = 0;
+        Ok(())
+    }
+}
+
+impl Sink for AvroSink {
+    fn page(&mut self, page: &Page) -> anyhow::Result<()> {
+        let block = &mut self.block;
+        avro_long(block, AVRO_PAGE);
+        avro_union(block, page.id, |block, id| avro_long(block, id as i64));
+        avro_bytes(block, page.title.as_bytes());
+        avro_union(block, page.ns, avro_long);
+        avro_union(block, page.redirect(), |block, target| {
+            avro_bytes(block, target.as_bytes())
+        });
+        self.record()
+    }
+
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let (source_url, target_url) = edge.urls.unzip();
+        let block = &mut self.block;
+        avro_long(block, AVRO_LINK);
+        avro_bytes(block, edge.source.as_bytes());
+        avro_bytes(block, edge.target.as_bytes());
+        for url in [source_url, target_url] {
+            avro_union(block, url, |block, url| avro_bytes(block, url.as_bytes()));
+        }
+        let page = edge.page;
+        avro_union(block, page.and_then(|page| page.id), |block, id| {
+            avro_long(block, id as i64)
+        });
+        avro_union(block, page.and_then(|page| page.ns), avro_long);
+        self.record()
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.write_block()?;
+        self.out.flush()?;
+        self.out.get_ref().sync_data()?;
+        Ok(Some(self.written))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.write_block()?;
+        Ok(self.out.flush()?)
+    }
+}
+
+/// Writes an Avro `long`: zigzag encoded, then seven bits at a time.
+fn avro_long(out: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Writes Avro `bytes` or a `string`, which is its length and then its contents.
+fn avro_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    avro_long(out, bytes.len() as i64);
+    out.extend_from_slice(bytes);
+}
+
+/// Writes a value of a `["null", T]` union.
+fn avro_union<T>(out: &mut Vec<u8>, value: Option<T>, write: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        Some(value) => {
+            avro_long(out, 1);
+            write(out, value);
+        }
+        None => avro_long(out, 0),
+    }
+}
+
+/// Reads the sync marker that ends an Avro container file's header, reading no further.
+fn avro_sync_marker(mut input: impl Read) -> Option<[u8; 16]> {
+    fn long(input: &mut impl Read) -> Option<i64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            input.read_exact(&mut byte).ok()?;
+            n |= u64::from(byte[0] & 0x7F) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Some((n >> 1) as i64 ^ -((n & 1) as i64));
+            }
+        }
+        None
+    }
+    fn skip_bytes(input: &mut impl Read) -> Option<()> {
+        let len = u64::try_from(long(input)?).ok()?;
+        let skipped = io::copy(&mut input.take(len), &mut io::sink()).ok()?;
+        (skipped == len).then_some(())
+    }
+
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic).ok()?;
+    if &magic != b"Obj\x01" {
+        return None;
+    }
+    loop {
+        let mut count = long(&mut input)?;
+        if count == 0 {
+            break;
+        }
+        // A negative count is followed by the block's size in bytes.
+        if count < 0 {
+            count = -count;
+            long(&mut input)?;
+        }
+        for _ in 0..count {
+            skip_bytes(&mut input)?;
+            skip_bytes(&mut input)?;
+        }
+    }
+    let mut sync = [0; 16];
+    input.read_exact(&mut sync).ok()?;
+    Some(sync)
+}
+
+/// Writes each edge as a MessagePack array of its source, target, source URL, target URL, and
+/// the id and namespace of the page it's on, with nil for any that are missing. Records follow
+/// one another with nothing between them, which is how MessagePack streams are read, and
+/// leaving out the keys a map would repeat keeps them small.
+struct MsgpackSink {
+    out: BufWriter<File>,
+    record: Vec<u8>,
+    written: u64,
+}
+
+impl Sink for MsgpackSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        let (source_url, target_url) = edge.urls.unzip();
+        let page = edge.page;
+        let strings = [Some(edge.source), Some(edge.target), source_url, target_url];
+        let ints = [
+            page.and_then(|page| page.id.map(|id| id as i64)),
+            page.and_then(|page| page.ns),
+        ];
+
+        let record = &mut self.record;
+        record.clear();
+        // A fixarray of six.
+        record.push(0x96);
+        for value in strings {
+            match value {
+                Some(value) => msgpack_str(record, value),
+                None => record.push(0xC0),
+            }
+        }
+        for value in ints {
+            match value {
+                Some(value) => msgpack_int(record, value),
+                None => record.push(0xC0),
+            }
+        }
+
+        self.out.write_all(record)?;
+        self.written += record.len() as u64;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.out.flush()?;
+        self.out.get_ref().sync_data()?;
+        Ok(Some(self.written))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+fn msgpack_str(out: &mut Vec<u8>, text: &str) {
+    let len = text.len();
+    match len {
+        0..=31 => out.push(0xA0 | len as u8),
+        32..=0xFF => out.extend_from_slice(&[0xD9, len as u8]),
+        0x100..=0xFFFF => {
+            out.push(0xDA);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0xDB);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Writes an integer in the smallest form that holds it.
+fn msgpack_int(out: &mut Vec<u8>, value: i64) {
+    match value {
+        0..=0x7F => out.push(value as u8),
+        -32..=-1 => out.push(value as i8 as u8),
+        0x80..=0xFF => out.extend_from_slice(&[0xCC, value as u8]),
+        0x100..=0xFFFF => {
+            out.push(0xCD);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xFFFF_FFFF => {
+            out.push(0xCE);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        -0x80..=-33 => out.extend_from_slice(&[0xD0, value as u8]),
+        -0x8000..=-0x81 => {
+            out.push(0xD1);
+            out.extend_from_slice(&(value as i16).to_be_bytes());
+        }
+        -0x8000_0000..=-0x8001 => {
+            out.push(0xD2);
+            out.extend_from_slice(&(value as i32).to_be_bytes());
+        }
+        _ => {
+            out.push(0xD3);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Inserts edges into a `links` table, a transaction at a time, and the pages they're on into
+/// a `pages` table alongside, with the revision each was written from.
+struct SqliteSink {
+    db: Connection,
+    pending: Vec<Row>,
+    pages: Vec<PageRow>,
+    tracker: PageTracker,
+}
+
+struct Row {
+    source: String,
+    target: String,
+    source_url: Option<String>,
+    target_url: Option<String>,
+    page_id: Option<u64>,
+}
+
+impl Row {
+    fn new(edge: &Edge) -> Self {
+        let (source_url, target_url) = edge.urls.unzip();
+        Self {
+            source: edge.source.into(),
+            target: edge.target.into(),
+            source_url: source_url.map(String::from),
+            target_url: target_url.map(String::from),
+            page_id: edge.page.and_then(|page| page.id),
+        }
+    }
+}
+
+struct PageRow {
+    title: String,
+    id: Option<u64>,
+    ns: Option<i64>,
+    revision: Option<u64>,
+}
+
+impl SqliteSink {
+    const BATCH: usize = 10_000;
+
+    /// Opens the database cut back to `position`, or, with `keep`, as it is, holding on to
+    /// the pages already in it.
+    fn open(path: &PathBuf, position: u64, keep: bool) -> anyhow::Result<Self> {
+        let db = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS links (
+                source TEXT NOT NULL,
+                target TEXT NOT NULL,
+                source_url TEXT,
+                target_url TEXT,
+                page_id INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS pages (
+                title TEXT NOT NULL,
+                id INTEGER,
+                ns INTEGER,
+                revision INTEGER
+            );",
+        )?;
+        // Databases written before pages were recorded have links without them.
+        let has_page_id: bool = db.query_row(
+            "SELECT count(*) > 0 FROM pragma_table_info('links') WHERE name = 'page_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_page_id {
+            db.execute_batch("ALTER TABLE links ADD COLUMN page_id INTEGER")?;
+        }
+
+        let mut tracker = PageTracker::default();
+        if keep {
+            db.execute_batch("CREATE INDEX IF NOT EXISTS links_page_id ON links (page_id)")?;
+            let mut held = HashMap::new();
+            let mut query = db.prepare("SELECT id, revision FROM pages WHERE id IS NOT NULL")?;
+            let mut rows = query.query([])?;
+            while let Some(row) = rows.next()? {
+                held.insert(row.get(0)?, row.get(1)?);
+            }
+            drop(rows);
+            drop(query);
+            tracker.held = Some(held);
+        } else {
+            // Rows go in one after another, so a checkpoint's position is the last rowid it
+            // covered, and a page goes in with its links.
+            db.execute(
+                "DELETE FROM pages WHERE ?1 = 0 OR id IN (
+                    SELECT page_id FROM links WHERE rowid > ?1
+                )",
+                [position],
+            )?;
+            db.execute("DELETE FROM links WHERE rowid > ?1", [position])?;
+        }
+        Ok(Self {
+            db,
+            pending: Vec::new(),
+            pages: Vec::new(),
+            tracker,
+        })
+    }
+
+    fn commit(&mut self) -> anyhow::Result<()> {
+        let tx = self.db.transaction()?;
+        {
+            let mut delete_links = tx.prepare_cached("DELETE FROM links WHERE page_id = ?1")?;
+            let mut delete_page = tx.prepare_cached("DELETE FROM pages WHERE id = ?1")?;
+            for id in mem::take(&mut self.tracker.replaced) {
+                delete_links.execute([id])?;
+                delete_page.execute([id])?;
+            }
+            let mut insert = tx.prepare_cached("INSERT INTO pages VALUES (?1, ?2, ?3, ?4)")?;
+            for page in mem::take(&mut self.pages) {
+                insert.execute(rusqlite::params![
+                    page.title,
+                    page.id,
+                    page.ns,
+                    page.revision
+                ])?;
+            }
+            let mut insert = tx.prepare_cached("INSERT INTO links VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            for row in mem::take(&mut self.pending) {
+                insert.execute(rusqlite::params![
+                    row.source,
+                    row.target,
+                    row.source_url,
+                    row.target_url,
+                    row.page_id
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Sink for SqliteSink {
+    fn page(&mut self, page: &Page) -> anyhow::Result<()> {
+        if self.tracker.is_new(page) && self.pending.len() >= Self::BATCH {
+            self.commit()?;
+        }
+        self.tracker.starts(page);
+        Ok(())
+    }
+
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        if let Some(page) = edge.page {
+            self.page(page)?;
+            if self.tracker.skipping {
+                return Ok(());
+            }
+            if self.tracker.records() {
+                self.pages.push(PageRow {
+                    title: page.title.clone(),
+                    id: page.id,
+                    ns: page.ns,
+                    revision: page.latest().and_then(|rev| rev.id),
+                });
+            }
+        } else if self.pending.len() >= Self::BATCH {
+            self.commit()?;
+        }
+        self.pending.push(Row::new(&edge));
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.commit()?;
+        let last: Option<u64> = self
+            .db
+            .query_row("SELECT max(rowid) FROM links", [], |row| row.get(0))?;
+        Ok(Some(last.unwrap_or(0)))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.commit()
+    }
+}
+
+/// Follows the pages a database sink's edges are on, so that each is recorded once, and so
+/// that a run adding to a database passes over the pages it already holds.
+///
+/// A page is recorded with its first link, so a database holds the pages that have links,
+/// however it was written. One that has lost its links since the database was written still
+/// has its rows replaced, with nothing.
+#[derive(Default)]
+struct PageTracker {
+    /// The title of the last page seen, since a page's links come one after another.
+    last: Option<String>,
+    /// Whether the last page's links are being passed over, as already held.
+    skipping: bool,
+    /// Whether the last page has been recorded.
+    recorded: bool,
+    /// The pages the database held, by id, with the revisions they were written from; `None`
+    /// unless the run adds to what's there.
+    held: Option<HashMap<u64, Option<u64>>>,
+    /// Pages held from another revision, whose rows are to be deleted before they go in again.
+    replaced: Vec<u64>,
+}
+
+impl PageTracker {
+    /// Whether a page is other than the last, so that what's pending can be committed before
+    /// it without splitting a page over two transactions.
+    fn is_new(&self, page: &Page) -> bool {
+        self.last.as_deref() != Some(&page.title)
+    }
+
+    /// Notes a page read, unless it's the one before: one already held from the same
+    /// revision has its links passed over, and one held from another has its rows replaced.
+    fn starts(&mut self, page: &Page) {
+        if !self.is_new(page) {
+            return;
+        }
+        self.last = Some(page.title.clone());
+        self.skipping = false;
+        self.recorded = false;
+        if let (Some(held), Some(id)) = (&mut self.held, page.id) {
+            let revision = page.latest().and_then(|rev| rev.id);
+            match held.insert(id, revision) {
+                Some(previous) if revision.is_some() && previous == revision => {
+                    self.skipping = true
+                }
+                Some(_) => self.replaced.push(id),
+                None => {}
+            }
+        }
+    }
+
+    /// Whether the last page is still to be recorded, which it is from then on.
+    fn records(&mut self) -> bool {
+        !mem::replace(&mut self.recorded, true)
+    }
+}
+
+/// Appends edges to a `links` table in DuckDB, a batch at a time, with the same columns as
+/// [`SqliteSink`] writes there.
+#[cfg(feature = "duckdb")]
+struct DuckdbSink {
+    db: duckdb::Connection,
+    pending: Vec<Row>,
+}
+
+#[cfg(feature = "duckdb")]
+impl DuckdbSink {
+    const BATCH: usize = 100_000;
+
+    fn open(path: &PathBuf, position: u64) -> anyhow::Result<Self> {
+        let db = duckdb::Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS links (
+                source TEXT NOT NULL,
+                target TEXT NOT NULL,
+                source_url TEXT,
+                target_url TEXT,
+                page_id BIGINT
+            );
+            ALTER TABLE links ADD COLUMN IF NOT EXISTS page_id BIGINT;",
+        )?;
+        // Appended rows take rowids in order, so a checkpoint's position is one past the last
+        // rowid it covered.
+        db.execute("DELETE FROM links WHERE rowid >= ?", [position])?;
+        Ok(Self {
+            db,
+            pending: Vec::new(),
+        })
+    }
+
+    fn commit(&mut self) -> anyhow::Result<()> {
+        let mut appender = self.db.appender("links")?;
+        for row in mem::take(&mut self.pending) {
+            appender.append_row(duckdb::params![
+                row.source,
+                row.target,
+                row.source_url,
+                row.target_url,
+                row.page_id
+            ])?;
+        }
+        appender.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "duckdb")]
+impl Sink for DuckdbSink {
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        self.pending.push(Row::new(&edge));
+        if self.pending.len() >= Self::BATCH {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.commit()?;
+        self.db.execute_batch("CHECKPOINT")?;
+        let next: u64 =
+            self.db
+                .query_row("SELECT coalesce(max(rowid) + 1, 0) FROM links", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(Some(next))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.commit()
+    }
+}
+
+/// Loads edges into a `links` table in PostgreSQL with `COPY`, a transaction at a time, and
+/// the pages they're on into a `pages` table alongside. Both tables are emptied first, as a
+/// file would be, unless the run adds to what they hold.
+struct PostgresSink {
+    db: Client,
+    /// Rows not yet sent, already written in `COPY`'s text format.
+    links: String,
+    pages: String,
+    pending: usize,
+    tracker: PageTracker,
+}
+
+impl PostgresSink {
+    const BATCH: usize = 50_000;
+
+    fn connect(url: &str, keep: bool) -> anyhow::Result<Self> {
+        let mut db = Client::connect(url, NoTls)?;
+        db.batch_execute(
+            "CREATE TABLE IF NOT EXISTS links (
+                source TEXT NOT NULL,
+                target TEXT NOT NULL,
+                source_url TEXT,
+                target_url TEXT,
+                page_id BIGINT
+            );
+            CREATE TABLE IF NOT EXISTS pages (
+                title TEXT NOT NULL,
+                id BIGINT,
+                ns BIGINT,
+                revision BIGINT
+            );
+            ALTER TABLE links ADD COLUMN IF NOT EXISTS page_id BIGINT;
+            ALTER TABLE pages ADD COLUMN IF NOT EXISTS revision BIGINT;",
+        )?;
+
+        let mut tracker = PageTracker::default();
+        if keep {
+            db.batch_execute("CREATE INDEX IF NOT EXISTS links_page_id ON links (page_id)")?;
+            let rows = db.query("SELECT id, revision FROM pages WHERE id IS NOT NULL", &[])?;
+            let held = rows
+                .iter()
+                .map(|row| {
+                    let id: i64 = row.get(0);
+                    let revision: Option<i64> = row.get(1);
+                    (id as u64, revision.map(|revision| revision as u64))
+                })
+                .collect();
+            tracker.held = Some(held);
+        } else {
+            db.batch_execute("TRUNCATE links, pages")?;
+        }
+        Ok(Self {
+            db,
+            links: String::new(),
+            pages: String::new(),
+            pending: 0,
+            tracker,
+        })
+    }
+
+    fn commit(&mut self) -> anyhow::Result<()> {
+        let mut tx = self.db.transaction()?;
+        let replaced: Vec<i64> = mem::take(&mut self.tracker.replaced)
+            .into_iter()
+            .map(|id| id as i64)
+            .collect();
+        if !replaced.is_empty() {
+            tx.execute("DELETE FROM links WHERE page_id = ANY($1)", &[&replaced])?;
+            tx.execute("DELETE FROM pages WHERE id = ANY($1)", &[&replaced])?;
+        }
+        for (table, rows) in [("links", &mut self.links), ("pages", &mut self.pages)] {
+            if rows.is_empty() {
+                continue;
+            }
+            let mut copy = tx.copy_in(&format!("COPY {table} FROM STDIN"))?;
+            copy.write_all(rows.as_bytes())?;
+            copy.finish()?;
+            rows.clear();
+        }
+        tx.commit()?;
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+impl Sink for PostgresSink {
+    fn page(&mut self, page: &Page) -> anyhow::Result<()> {
+        if self.tracker.is_new(page) && self.pending >= Self::BATCH {
+            self.commit()?;
+        }
+        self.tracker.starts(page);
+        Ok(())
+    }
+
+    fn edge(&mut self, edge: Edge) -> anyhow::Result<()> {
+        if let Some(page) = edge.page {
+            self.page(page)?;
+            if self.tracker.skipping {
+                return Ok(());
+            }
+            if self.tracker.records() {
+                let id = page.id.map(|id| id.to_string());
+                let ns = page.ns.map(|ns| ns.to_string());
+                let revision = page
+                    .latest()
+                    .and_then(|rev| rev.id)
+                    .map(|id| id.to_string());
+                copy_row(
+                    &mut self.pages,
+                    [
+                        Some(page.title.as_str()),
+                        id.as_deref(),
+                        ns.as_deref(),
+                        revision.as_deref(),
+                    ],
+                );
+            }
+        } else if self.pending >= Self::BATCH {
+            self.commit()?;
+        }
+
+        let (source_url, target_url) = edge.urls.unzip();
+        let page_id = edge.page.and_then(|page| page.id).map(|id| id.to_string());
+        copy_row(
+            &mut self.links,
+            [
+                Some(edge.source),
+                Some(edge.target),
+                source_url,
+                target_url,
+                page_id.as_deref(),
+            ],
+        );
+        self.pending += 1;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<u64>> {
+        self.commit()?;
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.commit()
+    }
+}
+
+/// Writes a row in `COPY`'s text format: tab-separated, with `\N` for null.
+fn copy_row<const N: usize>(out: &mut String, fields: [Option<&str>; N]) {
+    for (idx, field) in fields.into_iter().enumerate() {
+        if idx > 0 {
+            out.push('\t');
+        }
+        let Some(field) = field else {
+            out.push_str("\\N");
+            continue;
+        };
+        for c in field.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\t' => out.push_str("\\t"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn avro_longs_are_zigzag_varints() {
+        let cases: [(i64, &[u8]); 5] = [
+            (0, &[0x00]),
+            (-1, &[0x01]),
+            (1, &[0x02]),
+            (-64, &[0x7f]),
+            (64, &[0x80, 0x01]),
+        ];
+        for (value, bytes) in cases {
+            let mut out = Vec::new();
+            avro_long(&mut out, value);
+            assert_eq!(out, bytes, "{value}");
+        }
+    }
+
+    #[test]
+    fn msgpack_ints_take_the_smallest_form() {
+        let cases: [(i64, &[u8]); 11] = [
+            (5, &[0x05]),
+            (-1, &[0xFF]),
+            (-32, &[0xE0]),
+            (0x80, &[0xCC, 0x80]),
+            (0xFF, &[0xCC, 0xFF]),
+            (0x100, &[0xCD, 0x01, 0x00]),
+            (0x1_0000, &[0xCE, 0x00, 0x01, 0x00, 0x00]),
+            (-33, &[0xD0, 0xDF]),
+            (-0x81, &[0xD1, 0xFF, 0x7F]),
+            (-0x8001, &[0xD2, 0xFF, 0xFF, 0x7F, 0xFF]),
+            (1 << 32, &[0xD3, 0, 0, 0, 0x01, 0, 0, 0, 0]),
+        ];
+        for (value, bytes) in cases {
+            let mut out = Vec::new();
+            msgpack_int(&mut out, value);
+            assert_eq!(out, bytes, "{value}");
+        }
+    }
+
+    #[test]
+    fn msgpack_strs_carry_their_length() {
+        let mut out = Vec::new();
+        msgpack_str(&mut out, "ab");
+        assert_eq!(out, b"\xA2ab");
+        let long = "x".repeat(40);
+        out.clear();
+        msgpack_str(&mut out, &long);
+        assert_eq!(out[..2], [0xD9, 40]);
+        assert_eq!(out.len(), 42);
+    }
+
+    #[test]
+    fn avro_sync_marker_is_read_back_from_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.avro");
+        let mut sink = AvroSink::open(&path, 0).unwrap();
+        sink.edge(Edge::new("A", "B")).unwrap();
+        let written = sink.checkpoint().unwrap().unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(
+            avro_sync_marker(File::open(&path).unwrap()),
+            Some(sink.sync)
+        );
+        let resumed = AvroSink::open(&path, written).unwrap();
+        assert_eq!(resumed.sync, sink.sync);
+        // A header cut off partway has no marker to find.
+        assert_eq!(avro_sync_marker(&b"Obj\x01\x04"[..]), None);
+    }
+
+    #[test]
+    fn avro_records_start_with_their_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = AvroSink::open(&dir.path().join("links.avro"), 0).unwrap();
+        let xml = "<page><title>Old</title><ns>0</ns><id>2</id><redirect title=\"A\" />\
+                   <revision><text>#REDIRECT [[A]]</text></revision></page>";
+        let page = Page::from_xml(xml, 0).unwrap();
+        sink.page(&page).unwrap();
+        assert_eq!(sink.block, b"\x02\x02\x04\x06Old\x02\x00\x02\x02A");
+
+        sink.block.clear();
+        sink.edge(Edge::new("A", "B")).unwrap();
+        assert_eq!(sink.block, b"\x00\x02A\x02B\x00\x00\x00\x00");
+        assert_eq!(sink.records, 2);
+    }
+
+    fn page(title: &str, id: u64, revision: u64) -> Page {
+        let xml = format!(
+            "<page><title>{title}</title><id>{id}</id>\
+             <revision><id>{revision}</id><text>x</text></revision></page>"
+        );
+        Page::from_xml(&xml, 0).unwrap()
+    }
+
+    /// A database's links, by source and target, and its pages, by title and revision.
+    type Rows = (Vec<(String, String)>, Vec<(String, u64)>);
+
+    /// Sends each page and its links to a SQLite database, added to with `keep`, and reads
+    /// back what it holds.
+    fn write_sqlite(path: &PathBuf, keep: bool, pages: &[(Page, &[&str])]) -> Rows {
+        let mut sink = SqliteSink::open(path, 0, keep).unwrap();
+        for (page, links) in pages {
+            sink.page(page).unwrap();
+            for target in *links {
+                let edge = Edge {
+                    page: Some(page),
+                    ..Edge::new(&page.title, target)
+                };
+                sink.edge(edge).unwrap();
+            }
+        }
+        sink.finish().unwrap();
+
+        let db = &sink.db;
+        let mut links = db.prepare("SELECT source, target FROM links").unwrap();
+        let links = links.query_map([], |row| Ok((row.get(0)?, row.get(1)?)));
+        let mut links: Vec<_> = links.unwrap().map(Result::unwrap).collect();
+        let mut pages = db.prepare("SELECT title, revision FROM pages").unwrap();
+        let pages = pages.query_map([], |row| Ok((row.get(0)?, row.get(1)?)));
+        let mut pages: Vec<_> = pages.unwrap().map(Result::unwrap).collect();
+        links.sort();
+        pages.sort();
+        (links, pages)
+    }
+
+    #[test]
+    fn upserting_replaces_pages_that_lost_their_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("links.db");
+        write_sqlite(
+            &path,
+            false,
+            &[(page("A", 1, 10), &["B", "C"]), (page("D", 2, 20), &["E"])],
+        );
+
+        let again = [
+            (page("A", 1, 11), &[][..]),
+            (page("D", 2, 20), &["E"][..]),
+            (page("F", 3, 30), &["G"][..]),
+        ];
+        let upserted = write_sqlite(&path, true, &again);
+        assert_eq!(
+            upserted,
+            (
+                vec![("D".into(), "E".into()), ("F".into(), "G".into())],
+                vec![("D".into(), 20), ("F".into(), 30)],
+            )
+        );
+        // Upserting again, or writing afresh, comes to the same.
+        assert_eq!(write_sqlite(&path, true, &again), upserted);
+        let fresh = dir.path().join("fresh.db");
+        assert_eq!(write_sqlite(&fresh, false, &again), upserted);
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Lines, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use tempfile::TempDir;
+use tracing::{debug, info};
+
+use crate::memory::MemoryArgs;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// edge list to sort, or - for stdin
+    path: String,
+    /// write the sorted edges to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    #[clap(flatten)]
+    memory: MemoryArgs,
+}
+
+/// Sorts an edge list and drops duplicate edges. Edges of a page stay together, but the order
+/// within them doesn't survive, so this is for all-links graphs rather than first-link ones.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let input: Box<dyn BufRead> = match args.path.as_str() {
+        "-" => Box::new(io::stdin().lock()),
+        path => Box::new(File::open(path).map(BufReader::new)?),
+    };
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let budget = args.memory.budget_or(512 << 20);
+    let mut sorter = ExternalSorter::new(budget, args.memory.temp_dir())?;
+    for line in input.lines() {
+        sorter.push(line?)?;
+    }
+    let written = sorter.finish(&mut out)?;
+    out.flush()?;
+
+    info!("wrote {written} unique edges");
+    Ok(())
+}
+
+/// Sorts and deduplicates more lines than fit in memory by writing sorted runs to disk once
+/// a budget is used up, then merging them.
+pub struct ExternalSorter {
+    budget: usize,
+    used: usize,
+    lines: Vec<String>,
+    dir: TempDir,
+    runs: Vec<PathBuf>,
+}
+
+impl ExternalSorter {
+    pub fn new(budget: usize, temp_dir: Option<&Path>) -> io::Result<Self> {
+        let dir = match temp_dir {
+            Some(parent) => TempDir::new_in(parent)?,
+            None => TempDir::new()?,
+        };
+        Ok(Self {
+            budget,
+            used: 0,
+            lines: Vec::new(),
+            dir,
+            runs: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, line: String) -> io::Result<()> {
+        self.used += line.capacity() + mem::size_of::<String>();
+        self.lines.push(line);
+        if self.used >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes every line in order, once each, and returns how many were written.
+    pub fn finish(mut self, out: &mut impl Write) -> io::Result<u64> {
+        // Everything fit in memory, so there is nothing to merge.
+        if self.runs.is_empty() {
+            self.lines.sort_unstable();
+            self.lines.dedup();
+            for line in &self.lines {
+                writeln!(out, "{line}")?;
+            }
+            return Ok(self.lines.len() as u64);
+        }
+
+        if !self.lines.is_empty() {
+            self.spill()?;
+        }
+        debug!("merging {} runs", self.runs.len());
+
+        let mut runs = self
+            .runs
+            .iter()
+            .map(|path| File::open(path).map(|file| BufReader::new(file).lines()))
+            .collect::<io::Result<Vec<Lines<_>>>>()?;
+
+        let mut heap = BinaryHeap::new();
+        for (idx, run) in runs.iter_mut().enumerate() {
+            if let Some(line) = run.next() {
+                heap.push(Reverse((line?, idx)));
+            }
+        }
+
+        let mut written = 0;
+        let mut last: Option<String> = None;
+        while let Some(Reverse((line, idx))) = heap.pop() {
+            if let Some(next) = runs[idx].next() {
+                heap.push(Reverse((next?, idx)));
+            }
+            if last.as_ref() == Some(&line) {
+                continue;
+            }
+            writeln!(out, "{line}")?;
+            written += 1;
+            last = Some(line);
+        }
+        Ok(written)
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.lines.sort_unstable();
+        self.lines.dedup();
+
+        let path = self.dir.path().join(format!("run-{}", self.runs.len()));
+        debug!("spilling {} lines to {}", self.lines.len(), path.display());
+        let mut file = File::create(&path).map(BufWriter::new)?;
+        for line in self.lines.drain(..) {
+            writeln!(file, "{line}")?;
+        }
+        file.flush()?;
+
+        self.runs.push(path);
+        self.used = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, BufRead},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{page, progress::Progress};
+
+/// A field of a row in a MediaWiki SQL dump.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the rows inserted by a MediaWiki SQL dump such as `page.sql.gz`, which is mysqldump
+/// output: a table definition followed by long `INSERT INTO ... VALUES (...),(...);` lines.
+/// Everything besides the inserts is ignored.
+pub fn read_rows(path: impl AsRef<Path>) -> io::Result<Rows> {
+    let (reader, progress) = page::open_dump(path)?;
+    Ok(Rows {
+        reader,
+        progress,
+        line: Vec::new(),
+        rows: VecDeque::new(),
+    })
+}
+
+pub struct Rows {
+    reader: Box<dyn BufRead + Send>,
+    progress: Arc<Progress>,
+    line: Vec<u8>,
+    rows: VecDeque<Vec<Value>>,
+}
+
+impl Rows {
+    pub fn progress(&self) -> Arc<Progress> {
+        self.progress.clone()
+    }
+}
+
+impl Iterator for Rows {
+    type Item = io::Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rows.is_empty() {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+
+            let Some(values) = self
+                .line
+                .strip_prefix(b"INSERT INTO ")
+                .and_then(|line| find(line, b" VALUES ").map(|idx| &line[idx + 8..]))
+            else {
+                continue;
+            };
+            if let Err(e) = parse_values(values, &mut self.rows) {
+                return Some(Err(e));
+            }
+        }
+
+        self.progress.add_page("");
+        self.rows.pop_front().map(Ok)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parses the `(...),(...);` list of an insert.
+fn parse_values(mut text: &[u8], rows: &mut VecDeque<Vec<Value>>) -> io::Result<()> {
+    let invalid =
+        |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad insert: {what}"));
+
+    loop {
+        text = text
+            .strip_prefix(b"(")
+            .ok_or_else(|| invalid("expected a row"))?;
+        let mut row = Vec::new();
+        loop {
+            let (value, rest) = parse_value(text).ok_or_else(|| invalid("expected a value"))?;
+            row.push(value);
+            match rest.split_first() {
+                Some((b',', rest)) => text = rest,
+                Some((b')', rest)) => {
+                    text = rest;
+                    break;
+                }
+                _ => return Err(invalid("expected , or )")),
+            }
+        }
+        rows.push_back(row);
+
+        match text.split_first() {
+            Some((b',', rest)) => text = rest,
+            Some((b';', _)) => return Ok(()),
+            _ => return Err(invalid("expected , or ;")),
+        }
+    }
+}
+
+fn parse_value(text: &[u8]) -> Option<(Value, &[u8])> {
+    if let Some(rest) = text.strip_prefix(b"NULL") {
+        return Some((Value::Null, rest));
+    }
+
+    if let Some(rest) = text.strip_prefix(b"'") {
+        let mut value = Vec::new();
+        let mut bytes = rest.iter().enumerate();
+        while let Some((idx, &byte)) = bytes.next() {
+            match byte {
+                b'\'' => {
+                    let value = String::from_utf8_lossy(&value).into_owned();
+                    return Some((Value::Str(value), &rest[idx + 1..]));
+                }
+                b'\\' => {
+                    let (_, &escaped) = bytes.next()?;
+                    value.push(match escaped {
+                        b'0' => b'\0',
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'Z' => 0x1a,
+                        other => other,
+                    });
+                }
+                _ => value.push(byte),
+            }
+        }
+        return None;
+    }
+
+    let end = text
+        .iter()
+        .position(|&byte| byte == b',' || byte == b')')
+        .unwrap_or(text.len());
+    let number = std::str::from_utf8(&text[..end]).ok()?;
+    let value = match number.parse() {
+        Ok(n) => Value::Int(n),
+        Err(_) => Value::Float(number.parse().ok()?),
+    };
+    Some((value, &text[end..]))
+}
+
+/// The titles of the main namespace pages in a `page.sql` dump by page id, with spaces in
+/// place of the underscores the tables use.
+pub fn page_titles(path: impl AsRef<Path>) -> io::Result<HashMap<i64, String>> {
+    let mut titles = HashMap::new();
+    for row in read_rows(path)? {
+        let row = row?;
+        // page_id, page_namespace, page_title, ...
+        let (Some(id), Some(0), Some(title)) = (
+            row.first().and_then(Value::as_int),
+            row.get(1).and_then(Value::as_int),
+            row.get(2).and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        titles.insert(id, title.replace('_', " "));
+    }
+    Ok(titles)
+}
@@ -0,0 +1,208 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use regex::Regex;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{
+    page::{self, Page},
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to take discussions from
+    dump: String,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+static MONTHS: &[&str] = &[
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Writes a JSON record for every thread on the talk pages in a dump: the heading it was
+/// started under, and its comments in order, each with who signed it, when, how far it was
+/// indented, and what it said as plain text.
+///
+/// A comment runs up to the signature that ends it, so text nobody signed is a comment
+/// without an author. Signatures are read the way English Wikipedia writes them, a user
+/// link followed by a time such as `12:34, 5 June 2020 (UTC)`.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let threads = Threads::new();
+
+    let (mut pages, mut written) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        if !is_talk(&page) {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+        pages += 1;
+
+        for (heading, comments) in threads.split(text) {
+            if comments.is_empty() {
+                continue;
+            }
+            let record = json!({
+                "title": page.title,
+                "thread": heading,
+                "comments": comments,
+            });
+            writeln!(out, "{record}")?;
+            written += 1;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {written} threads from {pages} talk pages");
+    Ok(())
+}
+
+/// Talk namespaces are the odd ones. Dumps that leave out namespaces are judged by title.
+fn is_talk(page: &Page) -> bool {
+    match page.ns {
+        Some(ns) => ns > 0 && ns % 2 == 1,
+        None => {
+            let prefix = page.title.split_once(':').map(|(prefix, _)| prefix);
+            prefix.is_some_and(|prefix| prefix == "Talk" || prefix.ends_with(" talk"))
+        }
+    }
+}
+
+struct Threads {
+    plain: PlainText,
+    timestamp: Regex,
+    user: Regex,
+}
+
+impl Threads {
+    fn new() -> Self {
+        Self {
+            plain: PlainText::new(),
+            timestamp: Regex::new(r#"(\d{1,2}):(\d{2}), (\d{1,2}) ([A-Z][a-z]+) (\d{4}) \(UTC\)"#)
+                .unwrap(),
+            user: Regex::new(
+                r#"(?i)\[\[\s*(?:user(?:[ _]talk)?:|special:contributions/)([^|\]/#]+)"#,
+            )
+            .unwrap(),
+        }
+    }
+
+    /// Splits a talk page into its threads, one for each level 2 heading, with whatever comes
+    /// before the first heading as a thread without one.
+    fn split<'a>(&self, text: &'a str) -> Vec<(Option<&'a str>, Vec<Value>)> {
+        let mut threads = vec![(None, Vec::new())];
+        let mut comment = String::new();
+        for line in text.lines() {
+            if let Some((2, heading)) = plain::heading(line) {
+                self.finish(&mut comment, threads.last_mut().unwrap());
+                threads.push((Some(heading), Vec::new()));
+                continue;
+            }
+
+            comment.push_str(line);
+            comment.push('\n');
+            if self.timestamp.is_match(line) {
+                self.finish(&mut comment, threads.last_mut().unwrap());
+            }
+        }
+        self.finish(&mut comment, threads.last_mut().unwrap());
+        threads
+    }
+
+    /// Adds the comment read so far to its thread, unless there's nothing to it.
+    fn finish(&self, comment: &mut String, thread: &mut (Option<&str>, Vec<Value>)) {
+        let raw = std::mem::take(comment);
+        if let Some(comment) = self.comment(&raw) {
+            thread.1.push(comment);
+        }
+    }
+
+    fn comment(&self, raw: &str) -> Option<Value> {
+        let raw = raw.trim_start_matches('\n');
+        let depth = raw
+            .bytes()
+            .take_while(|&u| matches!(u, b':' | b'*'))
+            .count();
+
+        // A signature is the user links just before the time, such as a user page and a talk
+        // page for the same user, as opposed to users mentioned in the comment.
+        let stamp = self.timestamp.captures_iter(raw).last();
+        let (body, author, timestamp) = match &stamp {
+            Some(stamp) => {
+                let at = stamp.get(0).unwrap().start();
+                let line_start = raw[..at].rfind('\n').map_or(0, |idx| idx + 1);
+                let users: Vec<_> = self
+                    .user
+                    .captures_iter(&raw[line_start..at])
+                    .map(|user| {
+                        let span = user.get(0).unwrap();
+                        (
+                            line_start + span.start(),
+                            line_start + span.end(),
+                            user[1].trim().to_owned(),
+                        )
+                    })
+                    .collect();
+                match users.split_last() {
+                    Some(((mut start, _, author), earlier)) => {
+                        for (prev_start, prev_end, name) in earlier.iter().rev() {
+                            if !name.eq_ignore_ascii_case(author) || start - prev_end > 40 {
+                                break;
+                            }
+                            start = *prev_start;
+                        }
+                        (&raw[..start], Some(author.clone()), iso_time(stamp))
+                    }
+                    None => (&raw[..at], None, iso_time(stamp)),
+                }
+            }
+            None => (raw, None, None),
+        };
+
+        let text = self.plain.convert(body);
+        let text = text.trim_end_matches(['-', '—', '–', ' ']);
+        if text.is_empty() && author.is_none() {
+            return None;
+        }
+
+        Some(json!({
+            "author": author,
+            "timestamp": timestamp,
+            "depth": depth,
+            "text": text,
+        }))
+    }
+}
+
+/// Writes a signature's time as `2020-06-05T12:34:00Z`.
+fn iso_time(stamp: &regex::Captures) -> Option<String> {
+    let month = MONTHS.iter().position(|&month| month == &stamp[4])? + 1;
+    let day: u32 = stamp[3].parse().ok()?;
+    let hour: u32 = stamp[1].parse().ok()?;
+    Some(format!(
+        "{}-{month:02}-{day:02}T{hour:02}:{}:00Z",
+        &stamp[5], &stamp[2]
+    ))
+}
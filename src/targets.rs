@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use wiki_crawler::title::Rules;
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// number of targets to list in each ranking
+    #[clap(short = 'n', long, default_value_t = 20)]
+    top: usize,
+}
+
+/// Ranks the titles links lead to by how many links lead there, over the whole graph and then
+/// within each namespace, with each target's share of the links. A single target taking a
+/// large share of them, as "Greek language" does when etymologies slip through, usually
+/// points to links the filters should have passed over.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let graph = Graph::load(&args.graph)?;
+    let rules = Rules::new();
+    let in_degrees = graph.in_degrees();
+
+    let mut namespaces: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for id in 0..graph.len() as u32 {
+        if in_degrees[id as usize] > 0 {
+            let namespace = rules.parse(graph.title(id)).namespace;
+            let namespace = namespace.unwrap_or_else(|| "(main)".to_owned());
+            namespaces.entry(namespace).or_default().push(id);
+        }
+    }
+
+    let total = graph.link_count();
+    println!("most linked to");
+    print_top(
+        &graph,
+        &in_degrees,
+        (0..graph.len() as u32).collect(),
+        total,
+        args.top,
+    );
+    for (namespace, ids) in namespaces {
+        let links = ids.iter().map(|&id| in_degrees[id as usize]).sum();
+        println!();
+        println!("most linked to in {namespace}, {links} links");
+        print_top(&graph, &in_degrees, ids, links, args.top);
+    }
+
+    Ok(())
+}
+
+fn print_top(graph: &Graph, in_degrees: &[usize], mut ids: Vec<u32>, total: usize, top: usize) {
+    ids.sort_by_key(|&id| (std::cmp::Reverse(in_degrees[id as usize]), id));
+    for id in ids.into_iter().take(top) {
+        let count = in_degrees[id as usize];
+        if count == 0 {
+            break;
+        }
+        let share = count as f64 / total as f64 * 100.0;
+        println!("{count}\t{share:.2}%\t{}", graph.title(id));
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{
+    csv, page,
+    templates::{self, Templates},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to count templates in
+    dump: String,
+    /// write the table to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// leave out templates used fewer times than this
+    #[clap(long, default_value_t = 1)]
+    min_count: u64,
+}
+
+#[derive(Default)]
+struct Usage {
+    pages: u64,
+    uses: u64,
+    /// Uses the cleaner renders as text rather than dropping.
+    expanded: u64,
+}
+
+/// Counts how often each template is used across a dump, writing a
+/// template,pages,uses,expanded table with the most used first. `expanded` is how many of the
+/// uses the cleaner puts text in place of; the rest it strips, so a template with many uses
+/// and none expanded is one to look at keeping.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let cleaner = Templates::default();
+    let mut usage: HashMap<String, Usage> = HashMap::new();
+    let mut pages = 0;
+    for page in page::read_pages(&args.dump)? {
+        let Some(text) = page.text() else {
+            continue;
+        };
+        pages += 1;
+
+        let mut seen = HashSet::new();
+        for inner in templates::invocations(text) {
+            let name = templates::name(inner);
+            if name.is_empty() {
+                continue;
+            }
+            let entry = usage.entry(name.clone()).or_default();
+            entry.uses += 1;
+            if cleaner.render(inner).is_some() {
+                entry.expanded += 1;
+            }
+            if seen.insert(name) {
+                entry.pages += 1;
+            }
+        }
+    }
+
+    let mut sorted: Vec<_> = usage.iter().collect();
+    sorted.sort_unstable_by_key(|&(name, usage)| (Reverse(usage.uses), name));
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    writeln!(out, "template,pages,uses,expanded")?;
+    for (name, usage) in sorted {
+        if usage.uses < args.min_count {
+            break;
+        }
+        writeln!(
+            out,
+            "{},{},{},{}",
+            csv::field(name),
+            usage.pages,
+            usage.uses,
+            usage.expanded
+        )?;
+    }
+    out.flush()?;
+
+    info!(
+        "counted {} distinct templates over {pages} pages",
+        usage.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+
+/// Decides what becomes of templates as they are stripped from wikitext.
+#[derive(Debug, Default)]
+pub struct Templates {
+    keep: HashSet<String>,
+}
+
+impl Templates {
+    /// Keeps the arguments of these templates as they are, joined by spaces, in place of the
+    /// template. Some wikis wrap their opening sentences in templates of their own, links
+    /// and all.
+    pub fn keeping(names: &[String]) -> Self {
+        let keep = names.iter().map(|name| normalize(name)).collect();
+        Self { keep }
+    }
+
+    /// The text to put in place of a template, given what's between its braces, or `None`
+    /// to drop it.
+    pub fn render(&self, inner: &str) -> Option<String> {
+        let args = split_args(inner);
+        if self.keep.is_empty() || !self.keep.contains(&normalize(args.first()?)) {
+            return expand(inner);
+        }
+
+        let positional: Vec<&str> = args[1..]
+            .iter()
+            .copied()
+            .filter(|arg| !is_named(arg))
+            .map(str::trim)
+            .collect();
+        Some(positional.join(" "))
+    }
+}
+
+/// Template names are written with spaces or underscores, in any case, and sometimes with
+/// their namespace.
+fn normalize(name: &str) -> String {
+    let name = name.trim().replace('_', " ").to_lowercase();
+    match name.strip_prefix("template:") {
+        Some(name) => name.trim().to_owned(),
+        None => name,
+    }
+}
+
+/// The name a template goes by, as what's between its braces starts with: lowercased and
+/// without its namespace, as for [`normalize`]. Parser functions and magic words such as
+/// `#if` and `DEFAULTSORT` go by what comes before their colon.
+pub(crate) fn name(inner: &str) -> String {
+    let first = split_args(inner)[0];
+    match first.split_once(':') {
+        Some((prefix, _)) if is_function(prefix.trim()) => prefix.trim().to_lowercase(),
+        _ => normalize(first),
+    }
+}
+
+fn is_function(prefix: &str) -> bool {
+    prefix.starts_with('#') || !prefix.is_empty() && prefix.chars().all(|u| u.is_ascii_uppercase())
+}
+
+/// What's between the braces of every template in some wikitext, including those inside
+/// others, in the order they open. Template parameters, in triple braces, are passed over.
+pub(crate) fn invocations(text: &str) -> impl Iterator<Item = &str> {
+    text.match_indices("{{").filter_map(move |(idx, _)| {
+        let inner = &text[idx + 2..];
+        if inner.starts_with('{') {
+            return None;
+        }
+
+        let bytes = inner.as_bytes();
+        let (mut depth, mut at) = (1, 0);
+        while at < bytes.len() {
+            match bytes.get(at..at + 2) {
+                Some(b"{{") => depth += 1,
+                Some(b"}}") => depth -= 1,
+                _ => {
+                    at += 1;
+                    continue;
+                }
+            }
+            if depth == 0 {
+                return Some(&inner[..at]);
+            }
+            at += 2;
+        }
+        None
+    })
+}
+
+/// Renders the text of a template that stands for part of a sentence, such as a measurement
+/// or a foreign word, which would leave a hole in the sentence if it were dropped along with
+/// the rest. Takes what's between the braces and returns `None` for any other template.
+///
+/// Only the visible text is kept, so `{{convert|5|km|mi}}` becomes `5 km` without the
+/// conversion, and `{{lang|fr|[[Paris]]}}` keeps its link.
+fn expand(inner: &str) -> Option<String> {
+    let args = split_args(inner);
+    if let Some((name, first)) = args.first()?.split_once(':') {
+        if let Some(text) = parser_function(&name.trim().to_lowercase(), first.trim(), &args[1..]) {
+            return Some(text);
+        }
+    }
+    let name = normalize(args.first()?);
+    let positional: Vec<&str> = args[1..]
+        .iter()
+        .copied()
+        .filter(|arg| !is_named(arg))
+        .map(str::trim)
+        .collect();
+    let arg = |idx: usize| positional.get(idx).copied().filter(|arg| !arg.is_empty());
+
+    let text = match name.as_str() {
+        "convert" | "cvt" => return convert(&positional),
+        "lang" => arg(1)?,
+        name if name.starts_with("lang-") => arg(0)?,
+        // English name, then the original and its romanization.
+        "nihongo" => arg(0).or(arg(2)).or(arg(1))?,
+        "transl" | "transliteration" => positional.last().copied()?,
+        "nowrap" | "nobr" | "small" | "smaller" | "big" | "em" | "strong" | "var" | "abbr"
+        | "vanchor" | "visible anchor" => arg(0)?,
+        "nbsp" => " ",
+        "ndash" => "–",
+        "mdash" => "—",
+        "snd" | "spaced ndash" => " – ",
+        _ => return None,
+    };
+    Some(text.to_owned())
+}
+
+/// Evaluates the parser functions that can be worked out from the page alone, given the
+/// function's name, what follows its colon, and the rest of its arguments. What they give is
+/// wikitext of its own, to be cleaned like the rest. Anything that depends on other pages or
+/// the time, such as `#ifexist` or `#time`, is `None` and dropped, and so are Lua modules.
+fn parser_function(name: &str, first: &str, args: &[&str]) -> Option<String> {
+    let arg = |idx: usize| args.get(idx).map_or("", |arg| arg.trim());
+    let text = match name {
+        "#if" if !first.is_empty() => arg(0),
+        "#if" => arg(1),
+        "#ifeq" if first == arg(0) => arg(1),
+        "#ifeq" => arg(2),
+        // The test is taken not to be an error, being wikitext rather than an expression.
+        "#iferror" => args.get(1).map_or(first, |arg| arg.trim()),
+        "#switch" => return switch(first, args),
+        "lc" => return Some(first.to_lowercase()),
+        "uc" => return Some(first.to_uppercase()),
+        "lcfirst" | "ucfirst" => {
+            let mut chars = first.chars();
+            let initial = chars.next()?;
+            let initial: String = match name {
+                "lcfirst" => initial.to_lowercase().collect(),
+                _ => initial.to_uppercase().collect(),
+            };
+            return Some(initial + chars.as_str());
+        }
+        _ => return None,
+    };
+    Some(text.to_owned())
+}
+
+/// `{{#switch:b|a=1|b|c=2|#default=3}}` reads "2": cases without a result fall through to
+/// the next that has one, and a last case without a result is the default.
+fn switch(value: &str, cases: &[&str]) -> Option<String> {
+    let mut default = None;
+    let mut matched = false;
+    for (idx, case) in cases.iter().enumerate() {
+        match case.split_once('=') {
+            Some((key, result)) => {
+                let key = key.trim();
+                if matched || key == value {
+                    return Some(result.trim().to_owned());
+                }
+                if key == "#default" {
+                    default = Some(result.trim());
+                }
+            }
+            None if idx + 1 == cases.len() => default = Some(case.trim()),
+            None => matched |= case.trim() == value,
+        }
+    }
+    default.map(str::to_owned)
+}
+
+/// `{{convert|5|km|mi}}` reads "5 km", and `{{convert|5|-|10|km}}` reads "5–10 km".
+fn convert(args: &[&str]) -> Option<String> {
+    let value = args.first().filter(|value| !value.is_empty())?;
+    match args.get(1..4) {
+        Some([joiner, to, unit, ..]) if is_range(joiner) => {
+            let joiner = match *joiner {
+                "-" | "–" => "–".to_owned(),
+                word => format!(" {word} "),
+            };
+            Some(format!("{value}{joiner}{to} {unit}"))
+        }
+        _ => match args.get(1) {
+            Some(unit) => Some(format!("{value} {unit}")),
+            None => Some(value.to_string()),
+        },
+    }
+}
+
+fn is_range(joiner: &str) -> bool {
+    matches!(joiner, "-" | "–" | "to" | "and" | "or" | "by" | "x")
+}
+
+/// Splits a template's text on the pipes that separate its arguments, leaving alone those
+/// inside nested templates and links.
+pub(crate) fn split_args(inner: &str) -> Vec<&str> {
+    let bytes = inner.as_bytes();
+    let mut args = Vec::new();
+    let (mut braces, mut brackets) = (0usize, 0usize);
+    let mut start = 0;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes.get(idx..idx + 2) {
+            Some(b"{{") => {
+                braces += 1;
+                idx += 2;
+                continue;
+            }
+            Some(b"}}") => {
+                braces = braces.saturating_sub(1);
+                idx += 2;
+                continue;
+            }
+            Some(b"[[") => {
+                brackets += 1;
+                idx += 2;
+                continue;
+            }
+            Some(b"]]") => {
+                brackets = brackets.saturating_sub(1);
+                idx += 2;
+                continue;
+            }
+            _ => {}
+        }
+        if bytes[idx] == b'|' && braces == 0 && brackets == 0 {
+            args.push(&inner[start..idx]);
+            start = idx + 1;
+        }
+        idx += 1;
+    }
+    args.push(&inner[start..]);
+    args
+}
+
+/// Whether an argument is given by name, as in `abbr=on`, rather than by position.
+pub(crate) fn is_named(arg: &str) -> bool {
+    arg.split_once('=').is_some_and(|(name, _)| {
+        let name = name.trim();
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|u| u.is_alphanumeric() || matches!(u, ' ' | '_' | '-'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(inner: &str) -> Option<String> {
+        Templates::default().render(inner)
+    }
+
+    #[test]
+    fn evaluates_conditionals() {
+        assert_eq!(render("#if: x | yes | no").as_deref(), Some("yes"));
+        assert_eq!(render("#if: | yes | no").as_deref(), Some("no"));
+        assert_eq!(render("#if: | yes").as_deref(), Some(""));
+        assert_eq!(
+            render("#ifeq: a | a | same | different").as_deref(),
+            Some("same")
+        );
+        assert_eq!(
+            render("#ifeq: a | b | same | different").as_deref(),
+            Some("different")
+        );
+        assert_eq!(render("#iferror: x | bad | good").as_deref(), Some("good"));
+        assert_eq!(render("#iferror: x").as_deref(), Some("x"));
+        assert_eq!(render("#ifexist: Paris | yes | no"), None);
+    }
+
+    #[test]
+    fn falls_through_switch_cases() {
+        assert_eq!(
+            render("#switch: b | a=1 | b | c=2 | #default=3").as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            render("#switch: z | a=1 | #default=3").as_deref(),
+            Some("3")
+        );
+        assert_eq!(render("#switch: z | a=1 | other").as_deref(), Some("other"));
+        assert_eq!(render("#switch: z | a=1"), None);
+    }
+
+    #[test]
+    fn changes_case() {
+        assert_eq!(render("lc: ÉCOLE").as_deref(), Some("école"));
+        assert_eq!(render("uc: straße").as_deref(), Some("STRASSE"));
+        assert_eq!(render("ucfirst: élan").as_deref(), Some("Élan"));
+        assert_eq!(render("lcfirst: ABC").as_deref(), Some("aBC"));
+        assert_eq!(render("ucfirst:"), None);
+    }
+
+    #[test]
+    fn keeps_the_visible_text_of_inline_templates() {
+        assert_eq!(render("convert|5|km|mi").as_deref(), Some("5 km"));
+        assert_eq!(render("convert|5|-|10|km").as_deref(), Some("5–10 km"));
+        assert_eq!(
+            render("cvt|5|to|10|km|abbr=on").as_deref(),
+            Some("5 to 10 km")
+        );
+        assert_eq!(
+            render("lang|fr|[[Paris|la ville]]").as_deref(),
+            Some("[[Paris|la ville]]")
+        );
+        assert_eq!(render("Template:Nowrap|a b").as_deref(), Some("a b"));
+        assert_eq!(render("citation needed|date=May 2020"), None);
+    }
+
+    #[test]
+    fn keeps_the_arguments_of_named_templates() {
+        let templates = Templates::keeping(&["Lead_text".to_owned()]);
+        assert_eq!(
+            templates.render("lead text|a [[b]]|c|style=x").as_deref(),
+            Some("a [[b]] c")
+        );
+        assert_eq!(templates.render("other|a"), None);
+    }
+
+    #[test]
+    fn splits_on_top_level_pipes() {
+        assert_eq!(
+            split_args("a|{{b|c}}|[[d|e]]|f=g"),
+            ["a", "{{b|c}}", "[[d|e]]", "f=g"]
+        );
+        assert!(is_named(" abbr = on"));
+        assert!(!is_named("1+1=2"));
+        assert!(!is_named("=x"));
+    }
+
+    #[test]
+    fn names_templates_and_parser_functions() {
+        assert_eq!(name("Template:Infobox_person|name=x"), "infobox person");
+        assert_eq!(name("#if: a | b"), "#if");
+        assert_eq!(name("DEFAULTSORT:Smith, John"), "defaultsort");
+    }
+
+    #[test]
+    fn finds_nested_invocations() {
+        let text = "a {{b|{{c}}}} {{{d}}} {{e";
+        assert_eq!(invocations(text).collect::<Vec<_>>(), ["b|{{c}}", "c"]);
+    }
+}
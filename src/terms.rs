@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use tracing::info;
+
+use crate::{
+    page,
+    plain::{self, PlainText, BOLD},
+    sentences,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to take terms from
+    dump: String,
+    /// write terms to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// add the sentence each term was found in as a third column
+    #[clap(long)]
+    with_sentence: bool,
+}
+
+/// Writes the terms set in bold in the first sentence of each article, one `title<TAB>term`
+/// line apiece. By convention that's the article's subject and any other names it goes by,
+/// so a page whose terms look nothing like its title may well have had its first sentence
+/// misplaced.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+
+    let (mut pages, mut terms) = (0, 0);
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(lead) = page.text().and_then(|text| Lead::find(&plain, text)) else {
+            continue;
+        };
+        if !lead.terms.is_empty() {
+            pages += 1;
+        }
+        for term in &lead.terms {
+            write!(out, "{}\t{term}", page.title)?;
+            if args.with_sentence {
+                write!(out, "\t{}", lead.sentence)?;
+            }
+            writeln!(out)?;
+            terms += 1;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {terms} terms from {pages} pages");
+    Ok(())
+}
+
+/// The first sentence of an article and the terms it sets in bold.
+#[derive(Debug)]
+pub struct Lead {
+    pub sentence: String,
+    pub terms: Vec<String>,
+}
+
+impl Lead {
+    /// Finds the first sentence of the first paragraph of an article's text.
+    pub fn find(plain: &PlainText, text: &str) -> Option<Self> {
+        let marked = plain.convert_marking_bold(text);
+        let paragraph = marked
+            .lines()
+            .find(|line| !line.is_empty() && plain::heading(line).is_none())?;
+
+        // Splitting has to be done without the marks, which would hide capitals.
+        let unmarked = paragraph.replace(BOLD, "");
+        let sentence = *sentences::split(&unmarked).first()?;
+        let len = sentence.chars().count();
+
+        let mut seen = 0;
+        let end = paragraph
+            .char_indices()
+            .find(|&(_, u)| {
+                if u != BOLD {
+                    seen += 1;
+                }
+                seen > len
+            })
+            .map_or(paragraph.len(), |(idx, _)| idx);
+
+        // Bold text is every other stretch between marks, starting with the second.
+        let terms = paragraph[..end]
+            .split(BOLD)
+            .skip(1)
+            .step_by(2)
+            .map(|term| term.trim().trim_matches(['"', ',']).trim())
+            .filter(|term| !term.is_empty())
+            .map(String::from)
+            .collect();
+
+        Some(Self {
+            sentence: sentence.to_owned(),
+            terms,
+        })
+    }
+}
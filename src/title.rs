@@ -0,0 +1,200 @@
+//! MediaWiki's rules for when two ways of writing a title name the same page: underscores
+//! and runs of spaces are one space, anything after a `#` is an anchor within the page, a
+//! namespace is recognized in any case and under its aliases, and the first letter of a name
+//! is capitalized on wikis that do so.
+
+use std::{collections::HashMap, fmt};
+
+/// The English Wikipedia's namespaces, besides the main one.
+static NAMESPACES: &[&str] = &[
+    "Media",
+    "Special",
+    "Talk",
+    "User",
+    "User talk",
+    "Wikipedia",
+    "Wikipedia talk",
+    "File",
+    "File talk",
+    "MediaWiki",
+    "MediaWiki talk",
+    "Template",
+    "Template talk",
+    "Help",
+    "Help talk",
+    "Category",
+    "Category talk",
+    "Portal",
+    "Portal talk",
+    "Draft",
+    "Draft talk",
+    "Module",
+    "Module talk",
+];
+
+/// Other names that lead to the namespaces above.
+static ALIASES: &[(&str, &str)] = &[
+    ("Image", "File"),
+    ("Image talk", "File talk"),
+    ("Project", "Wikipedia"),
+    ("Project talk", "Wikipedia talk"),
+    ("WP", "Wikipedia"),
+    ("WT", "Wikipedia talk"),
+];
+
+/// Applies the cheap parts of title normalization, without knowing the wiki's namespaces:
+/// underscores and runs of spaces become single spaces, anchors are dropped and the first
+/// letter is capitalized.
+pub fn normalize(title: &str) -> String {
+    let (title, _) = split_fragment(title);
+    capitalize(&collapse(title))
+}
+
+/// Splits a title from the anchor after its `#`, if it has one.
+pub fn split_fragment(title: &str) -> (&str, Option<&str>) {
+    match title.split_once('#') {
+        Some((title, fragment)) => (title, Some(fragment)),
+        None => (title, None),
+    }
+}
+
+/// Turns underscores into spaces and collapses runs of them, trimming the ends.
+fn collapse(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for word in text.split(|u: char| u == '_' || u.is_whitespace()) {
+        if word.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// How a wiki treats the case of the first letter of a title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Case {
+    /// `apple` and `Apple` are the same page, as on most wikis.
+    #[default]
+    FirstLetter,
+    /// `apple` and `Apple` are different pages, as on Wiktionary.
+    Sensitive,
+}
+
+/// A title taken apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Title {
+    /// The namespace's name as the wiki writes it, or `None` for the main namespace.
+    pub namespace: Option<String>,
+    /// The page's name within its namespace.
+    pub name: String,
+    /// The anchor after a `#`, as written.
+    pub fragment: Option<String>,
+}
+
+/// Written as the page's full title, without its anchor, which is what names the page.
+impl fmt::Display for Title {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{namespace}:")?;
+        }
+        f.write_str(&self.name)
+    }
+}
+
+/// A wiki's namespaces and case rules, for reading titles the way it does.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Every name a namespace goes by, folded, mapped to how the wiki writes it.
+    namespaces: HashMap<String, String>,
+    case: Case,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rules {
+    /// Rules for the English Wikipedia.
+    pub fn new() -> Self {
+        let mut namespaces: HashMap<String, String> = NAMESPACES
+            .iter()
+            .map(|&name| (fold(name), name.to_owned()))
+            .collect();
+        for &(alias, name) in ALIASES {
+            namespaces.insert(fold(alias), name.to_owned());
+        }
+        Self {
+            namespaces,
+            case: Case::default(),
+        }
+    }
+
+    /// Adds the wiki's own namespace names, as listed in a dump's site information, to the
+    /// English Wikipedia's.
+    pub fn with_namespaces<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        for name in names {
+            let name = collapse(name.as_ref());
+            if !name.is_empty() {
+                self.namespaces.insert(fold(&name), name);
+            }
+        }
+        self
+    }
+
+    pub fn with_case(self, case: Case) -> Self {
+        Self { case, ..self }
+    }
+
+    /// Reads a title. A leading colon, as in `[[:Category:Fruit]]`, is dropped, and a prefix
+    /// that isn't one of the wiki's namespaces is part of the name, as in `Star Wars: A New
+    /// Hope`.
+    pub fn parse(&self, text: &str) -> Title {
+        let (text, fragment) = split_fragment(text);
+        let text = collapse(text.trim_start().trim_start_matches(':'));
+
+        let (namespace, name) = match text.split_once(':') {
+            Some((prefix, name)) => match self.namespaces.get(&fold(prefix)) {
+                Some(namespace) => (Some(namespace.clone()), collapse(name)),
+                None => (None, text.clone()),
+            },
+            None => (None, text),
+        };
+        let name = match self.case {
+            Case::FirstLetter => capitalize(&name),
+            Case::Sensitive => name,
+        };
+        Title {
+            namespace,
+            name,
+            fragment: fragment.map(str::to_owned),
+        }
+    }
+
+    /// The full title of the page a link to `text` leads to.
+    pub fn normalize(&self, text: &str) -> String {
+        self.parse(text).to_string()
+    }
+
+    /// Whether two titles name the same page.
+    pub fn same(&self, a: &str, b: &str) -> bool {
+        self.normalize(a) == self.normalize(b)
+    }
+}
+
+/// Namespace names match in any case, with underscores for spaces.
+fn fold(name: &str) -> String {
+    collapse(name).to_lowercase()
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufRead,
+    mem,
+    path::Path,
+};
+
+use tempfile::TempDir;
+use tracing::info;
+
+use crate::{
+    graph::normalize,
+    memory, page,
+    sql::{self, Value},
+};
+
+/// The set of page titles present in a dump, along with where its redirects point.
+///
+/// Given a memory budget, titles are moved into an embedded database on disk whenever the
+/// ones held in memory outgrow it.
+#[derive(Default)]
+pub struct TitleSet {
+    titles: HashSet<String>,
+    redirects: HashMap<String, String>,
+    used: usize,
+    spilled: Option<Spilled>,
+}
+
+struct Spilled {
+    titles: sled::Tree,
+    redirects: sled::Tree,
+    // Held so the database is removed along with the set.
+    _dir: TempDir,
+}
+
+impl TitleSet {
+    /// Collects titles with a cheap line scan rather than a full parse of each page.
+    pub fn collect(
+        path: impl AsRef<Path>,
+        budget: Option<usize>,
+        temp_dir: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let (reader, _) = page::open_dump(path)?;
+        let mut set = TitleSet::default();
+        let mut current = String::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+
+            let title = line
+                .strip_prefix("<title>")
+                .and_then(|line| line.strip_suffix("</title>"));
+            if let Some(title) = title {
+                current = normalize(&unescape(title));
+                set.insert_title(current.clone());
+            }
+
+            let target = line
+                .strip_prefix("<redirect title=\"")
+                .and_then(|line| line.split('"').next());
+            if let Some(target) = target {
+                set.insert_redirect(current.clone(), normalize(&unescape(target)));
+            }
+
+            if budget.is_some_and(|budget| set.used > budget) {
+                set.spill(temp_dir)?;
+            }
+        }
+
+        Ok(set)
+    }
+
+    /// Collects the titles of main namespace pages from a `page.sql` dump, and where they
+    /// redirect to from a `redirect.sql` dump, which MediaWiki keeps up to date itself.
+    pub fn from_tables(
+        pages: impl AsRef<Path>,
+        redirects: Option<impl AsRef<Path>>,
+        budget: Option<usize>,
+        temp_dir: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        // Redirects are keyed by page id, so they have to be on hand before the pages are.
+        let mut targets = HashMap::new();
+        if let Some(path) = redirects {
+            for row in sql::read_rows(path)? {
+                // rd_from, rd_namespace, rd_title, rd_interwiki, ...
+                let row = row?;
+                let [Value::Int(from), Value::Int(0), Value::Str(title), rest @ ..] =
+                    row.as_slice()
+                else {
+                    continue;
+                };
+                // Redirects to other wikis have no page here to land on.
+                let interwiki = rest.first().and_then(Value::as_str);
+                if interwiki.is_none_or(str::is_empty) {
+                    targets.insert(*from, normalize(title));
+                }
+            }
+        }
+
+        let mut set = TitleSet::default();
+        for row in sql::read_rows(pages)? {
+            // page_id, page_namespace, page_title, ...
+            let row = row?;
+            let [Value::Int(id), Value::Int(0), Value::Str(title), ..] = row.as_slice() else {
+                continue;
+            };
+
+            let title = normalize(title);
+            if let Some(target) = targets.remove(id) {
+                set.insert_redirect(title.clone(), target);
+            }
+            set.insert_title(title);
+
+            if budget.is_some_and(|budget| set.used > budget) {
+                set.spill(temp_dir)?;
+            }
+        }
+
+        Ok(set)
+    }
+
+    fn insert_title(&mut self, title: String) {
+        self.used += memory::string_cost(&title);
+        self.titles.insert(title);
+    }
+
+    fn insert_redirect(&mut self, title: String, target: String) {
+        self.used += memory::string_cost(&title) + memory::string_cost(&target);
+        self.redirects.insert(title, target);
+    }
+
+    fn spill(&mut self, temp_dir: Option<&Path>) -> anyhow::Result<()> {
+        let spilled = match &mut self.spilled {
+            Some(spilled) => spilled,
+            None => {
+                let dir = match temp_dir {
+                    Some(parent) => TempDir::new_in(parent)?,
+                    None => TempDir::new()?,
+                };
+                info!("spilling titles to {}", dir.path().display());
+                let db = sled::open(dir.path())?;
+                self.spilled.insert(Spilled {
+                    titles: db.open_tree("titles")?,
+                    redirects: db.open_tree("redirects")?,
+                    _dir: dir,
+                })
+            }
+        };
+
+        let mut batch = sled::Batch::default();
+        for title in mem::take(&mut self.titles) {
+            batch.insert(title.as_str(), &[]);
+        }
+        spilled.titles.apply_batch(batch)?;
+
+        let mut batch = sled::Batch::default();
+        for (title, target) in mem::take(&mut self.redirects) {
+            batch.insert(title.as_str(), target.as_str());
+        }
+        spilled.redirects.apply_batch(batch)?;
+
+        self.used = 0;
+        Ok(())
+    }
+
+    /// Maps a link target to the canonical title of the page it lands on, following
+    /// redirects to redirects as far as [`MAX_HOPS`]. Returns `None` for red links, and for
+    /// redirects that loop or never land.
+    pub fn resolve(&self, title: &str) -> anyhow::Result<Option<String>> {
+        let chain = self.follow(&normalize(title))?;
+        let landed = chain.ending == Ending::Page;
+        Ok(landed.then(|| chain.titles.into_iter().last().unwrap()))
+    }
+
+    /// Follows redirects from a normalized title to wherever they end up.
+    pub fn follow(&self, title: &str) -> anyhow::Result<Chain> {
+        let mut titles = vec![title.to_owned()];
+        let ending = loop {
+            let current = titles.last().unwrap();
+            let Some(target) = self.redirect(current)? else {
+                break if self.contains(current)? {
+                    Ending::Page
+                } else {
+                    Ending::Missing
+                };
+            };
+            if titles.contains(&target) {
+                titles.push(target);
+                break Ending::Loop;
+            }
+            titles.push(target);
+            if titles.len() > MAX_HOPS + 1 {
+                break Ending::TooLong;
+            }
+        };
+        Ok(Chain { titles, ending })
+    }
+
+    /// The titles of every redirect in the set.
+    pub fn redirects(&self) -> impl Iterator<Item = anyhow::Result<String>> + '_ {
+        let spilled = self
+            .spilled
+            .iter()
+            .flat_map(|spilled| spilled.redirects.iter().keys())
+            .map(|key| Ok(String::from_utf8(key?.to_vec())?));
+        self.redirects.keys().cloned().map(Ok).chain(spilled)
+    }
+
+    fn redirect(&self, title: &str) -> anyhow::Result<Option<String>> {
+        if let Some(target) = self.redirects.get(title) {
+            return Ok(Some(target.clone()));
+        }
+        match &self.spilled {
+            Some(spilled) => match spilled.redirects.get(title)? {
+                Some(target) => Ok(Some(String::from_utf8(target.to_vec())?)),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn contains(&self, title: &str) -> anyhow::Result<bool> {
+        if self.titles.contains(title) {
+            return Ok(true);
+        }
+        match &self.spilled {
+            Some(spilled) => Ok(spilled.titles.contains_key(title)?),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Redirects are followed no further than this many hops from where they start.
+pub const MAX_HOPS: usize = 8;
+
+/// Where following redirects from a title led: every title along the way, starting with the
+/// one followed, and how it ended.
+#[derive(Debug)]
+pub struct Chain {
+    pub titles: Vec<String>,
+    pub ending: Ending,
+}
+
+impl Chain {
+    /// Redirects that take more than one hop to land, or never do, are worth fixing on the
+    /// wiki; MediaWiki itself only follows one.
+    pub fn is_double(&self) -> bool {
+        self.titles.len() > 2 || matches!(self.ending, Ending::Loop | Ending::TooLong)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ending {
+    /// At a page that exists.
+    Page,
+    /// At a page that doesn't.
+    Missing,
+    /// Back at a title already passed through.
+    Loop,
+    /// Past [`MAX_HOPS`] without landing.
+    TooLong,
+}
+
+impl Ending {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Ending::Page => "page",
+            Ending::Missing => "missing",
+            Ending::Loop => "loop",
+            Ending::TooLong => "too-long",
+        }
+    }
+}
+
+/// Decodes the handful of entities MediaWiki uses when escaping titles.
+pub fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.into();
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#039;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The URL of an article, given the prefix its wiki puts before titles, e.g.
+/// `https://en.wikipedia.org/wiki/`.
+///
+/// Titles are encoded the way MediaWiki encodes them in its own links: spaces become
+/// underscores, and besides letters and digits only `-_.~;:@$!*(),/` are left as they are.
+pub fn article_url(base: &str, title: &str) -> String {
+    let mut url = String::from(base);
+    for byte in normalize(title).bytes() {
+        match byte {
+            b' ' => url.push('_'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => url.push(byte as char),
+            b'-' | b'_' | b'.' | b'~' | b';' | b':' | b'@' | b'$' | b'!' | b'*' | b'(' | b')'
+            | b',' | b'/' => url.push(byte as char),
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Stderr, Write},
+    panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Once,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::{Block, Borders, Gauge, Paragraph},
+    Terminal,
+};
+
+use crate::progress::Progress;
+
+const REFRESH: Duration = Duration::from_millis(250);
+
+/// Log lines kept back while the dashboard is up, at most; older ones are dropped.
+const HELD_LINES: usize = 1000;
+
+/// Log lines written while the dashboard is up, which would otherwise land on its screen and
+/// be lost when it's left, along with how many were dropped for room. `None` while there's no
+/// dashboard.
+static HELD: Mutex<Option<(VecDeque<Vec<u8>>, usize)>> = Mutex::new(None);
+
+/// Where logs are written: stderr, or while the dashboard is up, a queue written out to stderr
+/// once it's gone.
+pub struct LogWriter;
+
+/// Makes a [`LogWriter`], for the tracing subscriber.
+pub fn log_writer() -> LogWriter {
+    LogWriter
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Each event is written in one go, so each write is a line of its own.
+        if let Some((lines, dropped)) = HELD.lock().unwrap().as_mut() {
+            if lines.len() == HELD_LINES {
+                lines.pop_front();
+                *dropped += 1;
+            }
+            lines.push_back(buf.to_vec());
+            return Ok(buf.len());
+        }
+        io::stderr().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Leaves the alternate screen and writes out the log lines held back while on it.
+fn leave() {
+    let _ = execute!(io::stderr(), LeaveAlternateScreen);
+    let Some((lines, dropped)) = HELD.lock().map_or(None, |mut held| held.take()) else {
+        return;
+    };
+    let mut stderr = io::stderr().lock();
+    if dropped > 0 {
+        let _ = writeln!(stderr, "({dropped} earlier log lines were dropped)");
+    }
+    for line in lines {
+        let _ = stderr.write_all(&line);
+    }
+}
+
+/// A live dashboard drawn on stderr while a dump is processed.
+///
+/// The dashboard takes over stderr's alternate screen. Output usually goes to a file, and
+/// an inline viewport would need to query the cursor position through stdout. Logs are held
+/// back until the screen is left, which happens when the dashboard is dropped, however the
+/// run ends, or on a panic.
+pub struct Dashboard {
+    done: Arc<AtomicBool>,
+    handle: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Dashboard {
+    pub fn spawn(progress: Arc<Progress>) -> io::Result<Self> {
+        // A panic's message is printed before anything is dropped, so the screen has to be
+        // left first for it to be seen.
+        static HOOK: Once = Once::new();
+        HOOK.call_once(|| {
+            let hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if HELD.try_lock().is_ok_and(|held| held.is_some()) {
+                    leave();
+                }
+                hook(info);
+            }));
+        });
+
+        execute!(io::stderr(), EnterAlternateScreen)?;
+        let mut terminal = match Terminal::new(CrosstermBackend::new(io::stderr())) {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                execute!(io::stderr(), LeaveAlternateScreen)?;
+                return Err(e);
+            }
+        };
+
+        *HELD.lock().unwrap() = Some((VecDeque::new(), 0));
+        let done = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let done = done.clone();
+            move || {
+                while !done.load(Ordering::Relaxed) {
+                    draw(&mut terminal, &progress)?;
+                    thread::sleep(REFRESH);
+                }
+                draw(&mut terminal, &progress)
+            }
+        });
+
+        Ok(Self {
+            done,
+            handle: Some(handle),
+        })
+    }
+
+    /// Draws the dashboard a last time and leaves it, with any error from drawing.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.done.store(true, Ordering::Relaxed);
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("dashboard thread panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = self.stop();
+        leave();
+    }
+}
+
+fn draw(terminal: &mut Terminal<CrosstermBackend<Stderr>>, progress: &Progress) -> io::Result<()> {
+    let elapsed = progress.elapsed_secs().max(f64::EPSILON);
+    let bytes = progress.bytes();
+    let total = progress.total();
+    let ratio = if total > 0 {
+        (bytes as f64 / total as f64).min(1.0)
+    } else {
+        0.0
+    };
+
+    let lines = vec![
+        Line::from(format!(
+            "pages: {}    pages/sec: {:.0}    errors: {}",
+            progress.pages(),
+            progress.pages() as f64 / elapsed,
+            progress.errors()
+        )),
+        Line::from(format!(
+            "read: {:.1} MiB    MiB/sec: {:.1}",
+            mib(bytes),
+            mib(bytes) / elapsed
+        )),
+        Line::from(format!(
+            "elapsed: {}    eta: {}",
+            clock(elapsed),
+            progress.eta_secs().map_or_else(|| "unknown".into(), clock)
+        )),
+        Line::from(format!("current: {}", progress.title())),
+    ];
+
+    terminal.draw(|frame| {
+        let [area] = Layout::vertical([Constraint::Length(8)]).areas(frame.area());
+        let block = Block::default().borders(Borders::ALL).title("wiki-crawler");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [gauge, stats] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        frame.render_widget(Gauge::default().ratio(ratio), gauge);
+        frame.render_widget(Paragraph::new(lines), stats);
+    })?;
+
+    Ok(())
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn clock(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
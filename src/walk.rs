@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{anyhow, bail};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::graph::Graph;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// link graph produced by the extract command
+    graph: String,
+    /// title every walk starts from and restarts at; random pages if omitted
+    #[clap(long)]
+    start: Option<String>,
+    /// number of walks
+    #[clap(short, long, default_value_t = 1000)]
+    walks: usize,
+    /// steps per walk
+    #[clap(short, long, default_value_t = 100)]
+    length: usize,
+    /// probability of restarting at each step
+    #[clap(short, long, default_value_t = 0.15)]
+    restart: f64,
+    /// random seed
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// number of most-visited titles to print
+    #[clap(short = 'n', long, default_value_t = 20)]
+    top: usize,
+    /// write every visited title's count to this file
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&args.restart) {
+        bail!("restart probability must be between 0 and 1");
+    }
+
+    let graph = Graph::load(&args.graph)?;
+    if graph.len() == 0 {
+        bail!("graph is empty");
+    }
+
+    let start = match &args.start {
+        Some(title) => Some(
+            graph
+                .id(title)
+                .ok_or_else(|| anyhow!("unknown title: {title}"))?,
+        ),
+        None => None,
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut visits = vec![0u64; graph.len()];
+    let origin = |rng: &mut StdRng| start.unwrap_or_else(|| rng.gen_range(0..graph.len() as u32));
+
+    for _ in 0..args.walks {
+        let mut current = origin(&mut rng);
+        visits[current as usize] += 1;
+
+        // Dead ends restart the walk just as a restart roll does.
+        for _ in 0..args.length {
+            current = match graph.links(current).choose(&mut rng) {
+                Some(&next) if !rng.gen_bool(args.restart) => next,
+                _ => origin(&mut rng),
+            };
+            visits[current as usize] += 1;
+        }
+    }
+
+    let mut visited: Vec<_> = visits
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+        .map(|(id, count)| (graph.title(id as u32), count))
+        .collect();
+    visited.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    for &(title, count) in visited.iter().take(args.top) {
+        println!("{count}\t{title}");
+    }
+
+    if let Some(path) = &args.output {
+        let mut out = File::create(path).map(BufWriter::new)?;
+        for (title, count) in visited {
+            writeln!(out, "{title}\t{count}")?;
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
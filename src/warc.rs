@@ -0,0 +1,309 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use sha1::{Digest, Sha1};
+use tracing::info;
+
+use crate::{
+    extract,
+    filter::{LinkExtractor, TextFilter},
+    page::{self, Page},
+    plain::{self, PlainText},
+    remote,
+    titles::article_url,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// dump to export
+    dump: String,
+    /// file to write the records to, compressed a record at a time if it ends in `.gz`;
+    /// stdout if not given
+    #[clap(short, long)]
+    output: Option<String>,
+    /// what each page's record holds
+    #[clap(long, arg_enum, default_value = "html")]
+    format: Format,
+    /// prefix for article URLs; defaults to the one in the dump's site information
+    #[clap(long)]
+    base_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+enum Format {
+    /// the page's cleaned text as a plain HTML document, with its links listed after it
+    Html,
+    /// the page's wikitext, as `action=raw` serves it
+    Wikitext,
+}
+
+/// Writes each page in a dump as a WARC response record, as though it had been fetched from
+/// the wiki, after a `warcinfo` record describing the export. Records are dated by the
+/// page's latest revision.
+///
+/// HTML is the crawler's own rendering of the cleaned text, headings and paragraphs, rather
+/// than what MediaWiki would serve, and redirects come as `301` responses pointing at their
+/// targets. Wikitext is served from the article's URL with `?action=raw` added, redirects
+/// included, as MediaWiki serves it.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let site = page::site_info(&args.dump)?;
+    let base = match &args.base_url {
+        Some(base) => base.clone(),
+        None => site.base.clone().ok_or_else(|| {
+            anyhow::anyhow!("the dump has no base URL in its site information; pass --base-url")
+        })?,
+    };
+    let (out, gzip): (Box<dyn Write>, bool) = match &args.output {
+        Some(path) => (
+            Box::new(File::create(path).map(BufWriter::new)?),
+            path.ends_with(".gz"),
+        ),
+        None => (Box::new(BufWriter::new(io::stdout().lock())), false),
+    };
+    let mut warc = Warc { out, gzip };
+
+    let date = now();
+    let warcinfo = record_id(&format!("{}\n{date}", args.dump));
+    let fields = format!(
+        "software: {}/{}\r\nformat: WARC File Format 1.1\r\n\
+         conformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n\
+         description: pages from the dump {}\r\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        args.dump
+    );
+    warc.record(
+        &[
+            ("WARC-Type", "warcinfo"),
+            ("WARC-Record-ID", &warcinfo),
+            ("WARC-Date", &date),
+            ("Content-Type", "application/warc-fields"),
+        ],
+        fields.as_bytes(),
+        None,
+    )?;
+
+    let tf = TextFilter::new();
+    let ex = LinkExtractor::new().skipping(&site.namespaces);
+    let plain = PlainText::new();
+    let mut written = 0;
+    for page in page::read_pages(&args.dump)? {
+        let Some(revision) = page.latest() else {
+            continue;
+        };
+        let (uri, status, headers, body) = match args.format {
+            Format::Wikitext => (
+                format!("{}?action=raw", article_url(&base, &page.title)),
+                "200 OK",
+                "Content-Type: text/x-wiki; charset=UTF-8\r\n".to_owned(),
+                revision.text().to_owned(),
+            ),
+            Format::Html => {
+                let uri = article_url(&base, &page.title);
+                match page.redirect() {
+                    Some(target) => (
+                        uri,
+                        "301 Moved Permanently",
+                        format!("Location: {}\r\n", article_url(&base, target)),
+                        String::new(),
+                    ),
+                    None => (
+                        uri,
+                        "200 OK",
+                        "Content-Type: text/html; charset=UTF-8\r\n".to_owned(),
+                        render(&tf, &ex, &plain, &base, site.lang.as_deref(), &page),
+                    ),
+                }
+            }
+        };
+
+        let mut block = format!(
+            "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        block.extend_from_slice(body.as_bytes());
+        let date = revision.timestamp.clone().unwrap_or_else(|| date.clone());
+        let id = record_id(&format!("{uri}\n{date}"));
+        warc.record(
+            &[
+                ("WARC-Type", "response"),
+                ("WARC-Record-ID", &id),
+                ("WARC-Warcinfo-ID", &warcinfo),
+                ("WARC-Date", &date),
+                ("WARC-Target-URI", &uri),
+                ("Content-Type", "application/http; msgtype=response"),
+            ],
+            &block,
+            Some(body.as_bytes()),
+        )?;
+        written += 1;
+    }
+    warc.out.flush()?;
+
+    info!("wrote {written} WARC records");
+    Ok(())
+}
+
+/// Where records go, each in a gzip member of its own when compressed, as WARC readers
+/// expect so that they can seek to any one record.
+struct Warc {
+    out: Box<dyn Write>,
+    gzip: bool,
+}
+
+impl Warc {
+    /// Writes a record with the named headers, its length and digests, and its block. The
+    /// payload is the part of the block after any HTTP headers.
+    fn record(
+        &mut self,
+        headers: &[(&str, &str)],
+        block: &[u8],
+        payload: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let mut record = b"WARC/1.1\r\n".to_vec();
+        for (name, value) in headers {
+            write!(record, "{name}: {value}\r\n")?;
+        }
+        write!(record, "WARC-Block-Digest: sha1:{}\r\n", digest(block))?;
+        if let Some(payload) = payload {
+            write!(record, "WARC-Payload-Digest: sha1:{}\r\n", digest(payload))?;
+        }
+        write!(record, "Content-Length: {}\r\n\r\n", block.len())?;
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+
+        if self.gzip {
+            let mut member = GzEncoder::new(&mut self.out, Compression::default());
+            member.write_all(&record)?;
+            member.finish()?;
+            Ok(())
+        } else {
+            self.out.write_all(&record)
+        }
+    }
+}
+
+/// A page's cleaned text as an HTML document, its sections under headings as deep as
+/// they're nested, and the links taken from it listed after.
+fn render(
+    tf: &TextFilter,
+    ex: &LinkExtractor,
+    plain: &PlainText,
+    base: &str,
+    lang: Option<&str>,
+    page: &Page,
+) -> String {
+    let title = escape(&page.title);
+    let mut html = String::from("<!DOCTYPE html>\n");
+    match lang {
+        Some(lang) => html.push_str(&format!("<html lang=\"{}\">\n", escape(lang))),
+        None => html.push_str("<html>\n"),
+    }
+    html.push_str(&format!(
+        "<head><meta charset=\"utf-8\"><title>{title}</title></head>\n<body>\n<h1>{title}</h1>\n"
+    ));
+
+    let text = page
+        .text()
+        .map(|text| plain.convert(text))
+        .unwrap_or_default();
+    let sections = plain::sections(&text);
+    let mut path: &[String] = &[];
+    for section in &sections {
+        let shared = path
+            .iter()
+            .zip(&section.path)
+            .take_while(|(before, heading)| before == heading)
+            .count();
+        for (depth, heading) in section.path.iter().enumerate().skip(shared) {
+            let level = (depth + 2).min(6);
+            html.push_str(&format!("<h{level}>{}</h{level}>\n", escape(heading)));
+        }
+        for paragraph in section.text.split("\n\n").map(str::trim) {
+            if !paragraph.is_empty() {
+                html.push_str(&format!("<p>{}</p>\n", escape(paragraph)));
+            }
+        }
+        path = &section.path;
+    }
+
+    let links = extract::page_links(tf, ex, page, true);
+    if !links.is_empty() {
+        html.push_str("<nav>\n<ul>\n");
+        for link in links {
+            let text = match link.display.is_empty() {
+                true => &link.target,
+                false => &link.display,
+            };
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                escape(&article_url(base, &link.target)),
+                escape(text)
+            ));
+        }
+        html.push_str("</ul>\n</nav>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A record id as a name-based UUID, so that exporting the same pages again gives the same
+/// ids.
+fn record_id(name: &str) -> String {
+    let mut bytes: [u8; 16] = Sha1::digest(name.as_bytes())[..16].try_into().unwrap();
+    bytes[6] = bytes[6] & 0x0f | 0x50;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// The SHA-1 of some bytes in base 32, as WARC digests are written.
+fn digest(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let hash = Sha1::digest(bytes);
+    let mut out = String::with_capacity(32);
+    // 160 bits make exactly 32 digits of 5 bits each.
+    for idx in 0..32 {
+        let bit = idx * 5;
+        let pair = u16::from(hash[bit / 8]) << 8 | u16::from(*hash.get(bit / 8 + 1).unwrap_or(&0));
+        let value = pair >> (11 - bit % 8) & 0x1f;
+        out.push(ALPHABET[value as usize] as char);
+    }
+    out
+}
+
+/// The time now as WARC dates are written, e.g. `2020-06-05T12:34:00Z`.
+fn now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    let stamp = remote::amz_date(secs);
+    format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &stamp[..4],
+        &stamp[4..6],
+        &stamp[6..8],
+        &stamp[9..11],
+        &stamp[11..13],
+        &stamp[13..15]
+    )
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, IsTerminal},
+};
+
+use serde::{
+    de::{DeserializeOwned, Error, IgnoredAny},
+    Deserialize, Deserializer,
+};
+use tracing::{debug, info};
+
+use crate::{
+    page,
+    progress::Bar,
+    sink::{Edge, Output},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Wikidata entity dump, as JSON, optionally compressed with bzip2 or gzip
+    dump: String,
+    /// what to emit for each entity
+    #[clap(long, arg_enum, default_value = "sitelinks")]
+    emit: Record,
+    /// wiki whose titles to emit for sitelinks
+    #[clap(long, default_value = "enwiki")]
+    site: String,
+    /// language whose labels to emit
+    #[clap(long, default_value = "en")]
+    language: String,
+    /// property whose values to emit for claims, e.g. P31; may be given more than once
+    #[clap(long)]
+    property: Vec<String>,
+    /// where to send records, as for extract
+    #[clap(short, long, default_value = "-")]
+    output: Output,
+    /// don't show a progress bar
+    #[clap(long)]
+    no_progress: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+enum Record {
+    /// the entity's article title on a wiki, e.g. Q42 -> Douglas Adams
+    Sitelinks,
+    /// the entity's label in a language
+    Labels,
+    /// the values of chosen properties, e.g. Q42 -> Q5 for P31
+    Claims,
+}
+
+/// Streams entities from a Wikidata dump, sending each one's id along with its sitelinks,
+/// labels or claims to a sink as edges, so that they can be joined with graphs extracted
+/// from Wikipedia dumps.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    if args.emit == Record::Claims && args.property.is_empty() {
+        anyhow::bail!("claims need at least one --property");
+    }
+
+    let (reader, progress) = page::open_dump(&args.dump)?;
+    let bar = (std::io::stderr().is_terminal() && !args.no_progress)
+        .then(|| Bar::spawn(progress.clone()));
+    let mut out = args.output.open(0)?;
+
+    let mut edges = 0;
+    for line in reader.lines() {
+        // The dump is one big array with an entity on each line in between the brackets.
+        let line = line?;
+        let line = line.trim_end().trim_end_matches(',');
+        if matches!(line, "[" | "]" | "") {
+            continue;
+        }
+
+        let entity: Entity = match serde_json::from_str(line) {
+            Ok(entity) => entity,
+            Err(e) => {
+                debug!("skipping entity that failed to parse: {e}");
+                progress.add_error();
+                continue;
+            }
+        };
+        progress.add_page(&entity.id);
+
+        match args.emit {
+            Record::Sitelinks => {
+                if let Some(sitelink) = entity.sitelinks.get(&args.site) {
+                    out.edge(Edge::new(&entity.id, &sitelink.title))?;
+                    edges += 1;
+                }
+            }
+            Record::Labels => {
+                if let Some(label) = entity.labels.get(&args.language) {
+                    out.edge(Edge::new(&entity.id, &label.value))?;
+                    edges += 1;
+                }
+            }
+            Record::Claims => {
+                for property in &args.property {
+                    let claims = entity.claims.get(property).into_iter().flatten();
+                    for value in claims.filter_map(Claim::value) {
+                        out.edge(Edge::new(&entity.id, value))?;
+                        edges += 1;
+                    }
+                }
+            }
+        }
+    }
+    out.finish()?;
+
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+
+    info!("wrote {edges} records from {} entities", progress.pages());
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Entity {
+    id: String,
+    #[serde(default, deserialize_with = "map")]
+    labels: HashMap<String, Label>,
+    #[serde(default, deserialize_with = "map")]
+    sitelinks: HashMap<String, Sitelink>,
+    #[serde(default, deserialize_with = "map")]
+    claims: HashMap<String, Vec<Claim>>,
+}
+
+#[derive(Deserialize)]
+struct Label {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Sitelink {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Claim {
+    mainsnak: Snak,
+}
+
+#[derive(Deserialize)]
+struct Snak {
+    #[serde(default)]
+    datavalue: Option<DataValue>,
+}
+
+#[derive(Deserialize)]
+struct DataValue {
+    value: serde_json::Value,
+}
+
+impl Claim {
+    /// The claim's value if it's an entity, given by its id, or a plain string. Values with
+    /// no value, unknown values and values of other types such as dates have none.
+    fn value(&self) -> Option<&str> {
+        let value = &self.mainsnak.datavalue.as_ref()?.value;
+        value.as_str().or_else(|| value.get("id")?.as_str())
+    }
+}
+
+/// Reads a JSON object as a map, taking the empty array the dumps write in place of an empty
+/// object as an empty map.
+fn map<'de, D, T>(deserializer: D) -> Result<HashMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MapOrList<T> {
+        Map(HashMap<String, T>),
+        List(Vec<IgnoredAny>),
+    }
+
+    match MapOrList::deserialize(deserializer)? {
+        MapOrList::Map(map) => Ok(map),
+        MapOrList::List(list) if list.is_empty() => Ok(HashMap::new()),
+        MapOrList::List(_) => Err(D::Error::custom("expected an object")),
+    }
+}
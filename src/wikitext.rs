@@ -0,0 +1,450 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::templates::Templates;
+
+/// Elements whose contents aren't prose, and go along with their tags: references and their
+/// lists, and markup for other renderers.
+static HIDDEN: &[&str] = &[
+    "ce",
+    "chem",
+    "gallery",
+    "graph",
+    "hiero",
+    "mapframe",
+    "maplink",
+    "math",
+    "ref",
+    "references",
+    "score",
+    "source",
+    "syntaxhighlight",
+    "templatedata",
+    "timeline",
+];
+
+/// Behavior switches, which change how a page is shown rather than adding to it.
+static SWITCHES: &[&str] = &[
+    "ARCHIVEDTALK",
+    "DISAMBIG",
+    "END",
+    "EXPECTUNUSEDCATEGORY",
+    "EXPECTUNUSEDTEMPLATE",
+    "EXPECTED_UNCONNECTED_PAGE",
+    "FORCETOC",
+    "HIDDENCAT",
+    "INDEX",
+    "NEWSECTIONLINK",
+    "NOCC",
+    "NOCONTENTCONVERT",
+    "NOEDITSECTION",
+    "NOGALLERY",
+    "NOGLOBAL",
+    "NOINDEX",
+    "NONEWSECTIONLINK",
+    "NOTALK",
+    "NOTC",
+    "NOTITLECONVERT",
+    "NOTOC",
+    "START",
+    "STATICREDIRECT",
+    "TOC",
+];
+
+/// A piece of wikitext, as read by [`Lexer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lexeme<'a> {
+    /// Anything the lexer has no rule for: prose, and markup such as formatting tags.
+    Text(&'a str),
+    TemplateOpen,
+    TemplateClose,
+    LinkOpen,
+    LinkClose,
+    /// A comment, a behavior switch such as `__NOTOC__`, or one of the [`HIDDEN`] elements.
+    Hidden,
+    /// What's inside `<nowiki>`, which is shown as written.
+    Literal(&'a str),
+}
+
+/// Splits wikitext into [`Lexeme`]s in one pass. Nothing is matched up here beyond an
+/// element's tags; that's left to whoever reads the lexemes.
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    /// A lexeme found while looking for the end of a run of text.
+    peeked: Option<(Lexeme<'a>, usize)>,
+    /// Elements found to have no closing tag past some offset, so that a page full of
+    /// unclosed tags isn't searched to the end again for each.
+    unclosed: HashMap<String, usize>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            peeked: None,
+            unclosed: HashMap::new(),
+        }
+    }
+
+    /// The lexeme starting at `at` and its length, unless what's there is plain text.
+    fn special(&mut self, at: usize) -> Option<(Lexeme<'a>, usize)> {
+        let rest = &self.text[at..];
+        let lexeme = match rest.as_bytes().get(..2)? {
+            b"{{" => Lexeme::TemplateOpen,
+            b"}}" => Lexeme::TemplateClose,
+            b"[[" => Lexeme::LinkOpen,
+            b"]]" => Lexeme::LinkClose,
+            [b'<', _] => return self.tag(at),
+            b"__" => return switch(rest).map(|len| (Lexeme::Hidden, len)),
+            _ => return None,
+        };
+        Some((lexeme, 2))
+    }
+
+    /// Reads a comment or one of the elements the lexer knows at `at`.
+    fn tag(&mut self, at: usize) -> Option<(Lexeme<'a>, usize)> {
+        let rest = &self.text[at..];
+        // An unclosed comment hides the rest of the page, as it does on the wiki.
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let len = comment.find("-->").map_or(rest.len(), |end| end + 7);
+            return Some((Lexeme::Hidden, len));
+        }
+
+        let name_len = rest[1..]
+            .bytes()
+            .take_while(u8::is_ascii_alphanumeric)
+            .count();
+        let name = rest[1..1 + name_len].to_ascii_lowercase();
+        let nowiki = name == "nowiki";
+        if !nowiki && !HIDDEN.contains(&name.as_str()) {
+            return None;
+        }
+        if !matches!(
+            rest.as_bytes().get(1 + name_len),
+            Some(b'>' | b'/' | b' ' | b'\t' | b'\n')
+        ) {
+            return None;
+        }
+
+        let open = tag_end(rest)?;
+        if rest[..open].ends_with("/>") {
+            return Some((Lexeme::Hidden, open));
+        }
+        // An element left open has only its opening tag dropped.
+        let Some((content, len)) = self.closing(at + open, &name) else {
+            return Some((Lexeme::Hidden, open));
+        };
+        let content = &rest[open..open + content];
+        let lexeme = if nowiki {
+            Lexeme::Literal(content)
+        } else {
+            Lexeme::Hidden
+        };
+        Some((lexeme, open + len))
+    }
+
+    /// Finds the closing tag for `name` at or after `from`, returning the length of what
+    /// comes before it and the length up to the end of the tag.
+    fn closing(&mut self, from: usize, name: &str) -> Option<(usize, usize)> {
+        if self.unclosed.get(name).is_some_and(|&after| after <= from) {
+            return None;
+        }
+
+        let rest = &self.text[from..];
+        let mut idx = 0;
+        while let Some(found) = rest[idx..].find("</") {
+            let start = idx + found;
+            let tag = &rest[start + 2..];
+            // `</references>` doesn't close a `<ref>`, but `</ref >` does.
+            let matches = tag
+                .get(..name.len())
+                .is_some_and(|tag| tag.eq_ignore_ascii_case(name));
+            let rest = &tag[if matches { name.len() } else { 0 }..];
+            let spaces = rest.len() - rest.trim_start().len();
+            if matches && rest[spaces..].starts_with('>') {
+                return Some((start, start + 2 + name.len() + spaces + 1));
+            }
+            idx = start + 2;
+        }
+
+        self.unclosed.insert(name.to_owned(), from);
+        None
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Lexeme<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((lexeme, len)) = self.peeked.take() {
+            self.pos += len;
+            return Some(lexeme);
+        }
+
+        let start = self.pos;
+        if start >= self.text.len() {
+            return None;
+        }
+        if let Some((lexeme, len)) = self.special(start) {
+            self.pos += len;
+            return Some(lexeme);
+        }
+
+        let bytes = self.text.as_bytes();
+        let mut end = start + 1;
+        while end < bytes.len() {
+            if matches!(bytes[end], b'{' | b'}' | b'[' | b']' | b'<' | b'_') {
+                if let Some(special) = self.special(end) {
+                    self.peeked = Some(special);
+                    break;
+                }
+            }
+            end += 1;
+        }
+        self.pos = end;
+        Some(Lexeme::Text(&self.text[start..end]))
+    }
+}
+
+/// The length of the behavior switch `text` starts with, if it does.
+fn switch(text: &str) -> Option<usize> {
+    let name = &text[2..];
+    let len = name.find("__")?;
+    let name = &name[..len];
+    SWITCHES
+        .iter()
+        .any(|switch| switch.eq_ignore_ascii_case(name))
+        .then_some(len + 4)
+}
+
+/// The length of the opening tag `tag` starts with, through its `>`. Attribute values are
+/// quoted or not, and a quoted one can hold a `>` of its own, as in `<ref name="a>b">`.
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, byte) in tag.bytes().enumerate() {
+        match (quote, byte) {
+            // Only a quote opening a value counts, so an apostrophe in an unquoted one
+            // doesn't swallow the rest of the tag.
+            (None, b'"' | b'\'') if tag[..idx].trim_end().ends_with('=') => quote = Some(byte),
+            (Some(open), _) if byte == open => quote = None,
+            (None, b'>') => return Some(idx + 1),
+            // A quote left open doesn't run past the end of its line.
+            (Some(_), b'\n') => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Takes what isn't prose out of wikitext in a single pass: comments, references and the like
+/// go, and so do `{{...}}` templates, nested ones included, other than those that stand for
+/// part of a sentence or are to be kept, which are replaced by their text. Links are left as
+/// they are, templates and references inside them aside.
+///
+/// With `asides`, parenthesized asides go too, which is where lead sentences keep
+/// pronunciations, etymologies and birth dates, along with the links in them. Parentheses
+/// inside templates and links don't count, so a `)` in an IPA template doesn't end the aside
+/// early and `[[Mercury (planet)]]` is left whole. An aside still open at the end of its line
+/// is left as it is.
+///
+/// An unclosed template's `{{` is left as it is, the way MediaWiki shows it, and what follows
+/// is cleaned as usual rather than swallowed or kept raw. Returns `false` along with the text
+/// if the deadline passed before the end.
+pub fn clean(text: &str, templates: &Templates, asides: bool, deadline: Instant) -> (String, bool) {
+    let mut out = String::with_capacity(text.len());
+    let finished = clean_into(&mut out, text, templates, asides, deadline);
+    (out, finished)
+}
+
+/// Cleans text as for [`clean`] into `out`, replacing what was there, so that one buffer can
+/// serve page after page. Returns whether it got to the end before the deadline.
+pub fn clean_into(
+    out: &mut String,
+    text: &str,
+    templates: &Templates,
+    asides: bool,
+    deadline: Instant,
+) -> bool {
+    // Checking the clock every so many bytes keeps the check off the hot path.
+    const CHECK_EVERY: usize = 64 << 10;
+
+    let mut lexer = Lexer::new(text);
+    out.clear();
+    out.reserve(text.len());
+    let mut next_check = CHECK_EVERY;
+    // Where each open template starts, outermost first.
+    let mut opens: Vec<usize> = Vec::new();
+    // Where templates found never to be closed start, the nearest last.
+    let mut unclosed: Vec<usize> = Vec::new();
+    // Links don't span lines, so one left open only lasts until the end of its line.
+    let mut links = 0usize;
+    // Where the outermost open aside starts in `out`, and how deep asides are nested there.
+    let (mut aside, mut parens) = (0, 0usize);
+
+    let mut start = 0;
+    loop {
+        let Some(lexeme) = lexer.next() else {
+            // Templates still open at the end are the ones that never close, which are shown
+            // as written, while the text after the first of them is read again as usual.
+            // Nothing was written since it opened.
+            let Some(&outer) = opens.first() else {
+                break;
+            };
+            unclosed = opens.drain(..).rev().collect();
+            lexer.pos = outer;
+            start = outer;
+            continue;
+        };
+        if start >= next_check {
+            next_check += CHECK_EVERY;
+            if Instant::now() > deadline {
+                return false;
+            }
+        }
+        let end = lexer.pos;
+
+        match lexeme {
+            Lexeme::TemplateOpen if unclosed.last() == Some(&start) => {
+                unclosed.pop();
+                out.push_str("{{");
+            }
+            Lexeme::TemplateOpen => opens.push(start),
+            Lexeme::TemplateClose if !opens.is_empty() => {
+                let open = opens.pop().unwrap_or_default();
+                if opens.is_empty() {
+                    if let Some(rendered) = templates.render(&text[open + 2..start]) {
+                        // What's kept can still hold templates of its own.
+                        let (rendered, finished) = clean(&rendered, templates, false, deadline);
+                        out.push_str(&rendered);
+                        if !finished {
+                            return false;
+                        }
+                    }
+                }
+            }
+            _ if !opens.is_empty() => {}
+            Lexeme::Hidden => {}
+            Lexeme::Literal(literal) => escape(out, literal),
+            Lexeme::LinkOpen => {
+                links += 1;
+                out.push_str("[[");
+            }
+            Lexeme::LinkClose => {
+                links = links.saturating_sub(1);
+                out.push_str("]]");
+            }
+            Lexeme::TemplateClose => out.push_str("}}"),
+            Lexeme::Text(text) if !asides => out.push_str(text),
+            Lexeme::Text(text) => {
+                let mut kept = 0;
+                for (idx, byte) in text.bytes().enumerate() {
+                    match byte {
+                        b'\n' => {
+                            links = 0;
+                            parens = 0;
+                        }
+                        b'(' if links == 0 => {
+                            if parens == 0 {
+                                out.push_str(&text[kept..idx]);
+                                kept = idx;
+                                aside = out.len();
+                            }
+                            parens += 1;
+                        }
+                        b')' if links == 0 && parens > 0 => {
+                            parens -= 1;
+                            if parens == 0 {
+                                out.truncate(aside);
+                                kept = idx + 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                out.push_str(&text[kept..]);
+            }
+        }
+        start = end;
+    }
+    true
+}
+
+/// Writes text shown as written with what would otherwise be read as markup escaped.
+fn escape(out: &mut String, literal: &str) {
+    for c in literal.chars() {
+        match c {
+            '[' => out.push_str("&#91;"),
+            ']' => out.push_str("&#93;"),
+            '{' => out.push_str("&#123;"),
+            '}' => out.push_str("&#125;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn cleaned(text: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let (text, finished) = clean(text, &Templates::default(), false, deadline);
+        assert!(finished);
+        text
+    }
+
+    #[test]
+    fn drops_templates_references_and_comments() {
+        assert_eq!(
+            cleaned("A {{infobox|x={{y}}}}[[B]]<ref>[[C]]</ref><!-- [[D]] --> e."),
+            "A [[B]] e."
+        );
+    }
+
+    #[test]
+    fn unclosed_template_is_shown_and_the_rest_cleaned() {
+        assert_eq!(
+            cleaned("{{infobox\n[[Philosophy]]<ref>[[Leak]]</ref> text"),
+            "{{infobox\n[[Philosophy]] text"
+        );
+    }
+
+    #[test]
+    fn only_unclosed_templates_are_shown() {
+        assert_eq!(cleaned("{{a {{b}} [[C]]<!-- [[D]] -->"), "{{a  [[C]]");
+        assert_eq!(cleaned("{{a {{b [[C]]"), "{{a {{b [[C]]");
+        assert_eq!(cleaned("x}} {{y}}"), "x}} ");
+    }
+
+    #[test]
+    fn unclosed_elements_lose_only_their_tags() {
+        assert_eq!(cleaned("a <ref name=x>b [[C]]"), "a b [[C]]");
+        assert_eq!(cleaned("a <!-- b [[C]]"), "a ");
+    }
+
+    #[test]
+    fn nowiki_is_escaped() {
+        assert_eq!(
+            cleaned("<nowiki>[[A]]</nowiki> [[B]]"),
+            "&#91;&#91;A&#93;&#93; [[B]]"
+        );
+    }
+
+    #[test]
+    fn switches_are_hidden() {
+        assert_eq!(cleaned("__NOTOC__a __b__ c"), "a __b__ c");
+    }
+
+    #[test]
+    fn asides_go_with_their_links() {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let text = "[[Mercury (planet)]] (from [[Latin]]) is small";
+        let (text, _) = clean(text, &Templates::default(), true, deadline);
+        assert_eq!(text, "[[Mercury (planet)]]  is small");
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use regex::Regex;
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+    page,
+    plain::{self, PlainText},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Wiktionary dump to read entries from
+    dump: String,
+    /// keep only entries in this language, e.g. English; may be given more than once
+    #[clap(short, long)]
+    language: Vec<String>,
+    /// write records to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+/// Parts of speech as Wiktionary's headings name them. Anything else under a language, such
+/// as Etymology or Pronunciation, isn't an entry of its own.
+static PARTS_OF_SPEECH: &[&str] = &[
+    "Abbreviation",
+    "Acronym",
+    "Adjective",
+    "Adverb",
+    "Affix",
+    "Article",
+    "Circumfix",
+    "Classifier",
+    "Conjunction",
+    "Contraction",
+    "Determiner",
+    "Idiom",
+    "Infix",
+    "Initialism",
+    "Interfix",
+    "Interjection",
+    "Letter",
+    "Noun",
+    "Number",
+    "Numeral",
+    "Participle",
+    "Particle",
+    "Phrase",
+    "Postposition",
+    "Prefix",
+    "Preposition",
+    "Prepositional phrase",
+    "Pronoun",
+    "Proper noun",
+    "Proverb",
+    "Punctuation mark",
+    "Suffix",
+    "Symbol",
+    "Verb",
+];
+
+/// Writes a JSON record for every entry in a Wiktionary dump: a word in one language as one
+/// part of speech, with its definitions as plain text and any translations listed for it.
+///
+/// Pages are laid out the way the English Wiktionary lays them out, with a level 2 heading
+/// per language, parts of speech beneath, and definitions as `#` lines.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map(BufWriter::new)?),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let plain = PlainText::new();
+    let translation = Regex::new(r#"\{\{(?:t|t\+|tt|tt\+|t-simple)\|([^|}]+)\|([^|}]+)"#).unwrap();
+
+    let mut count = 0;
+    for page in page::read_pages(&args.dump)? {
+        if page.ns.unwrap_or(0) != 0 {
+            continue;
+        }
+        let Some(text) = page.text() else {
+            continue;
+        };
+
+        for entry in entries(text) {
+            if !args.language.is_empty() && !args.language.iter().any(|l| l == entry.language) {
+                continue;
+            }
+
+            let definitions: Vec<String> = entry
+                .definitions
+                .iter()
+                .map(|line| plain.convert(line))
+                .filter(|line| !line.is_empty())
+                .collect();
+            let translations: Vec<_> = entry
+                .translations
+                .iter()
+                .flat_map(|line| translation.captures_iter(line))
+                .map(|cx| json!({ "language": cx[1].trim(), "term": cx[2].trim() }))
+                .collect();
+            if definitions.is_empty() && translations.is_empty() {
+                continue;
+            }
+
+            let record = json!({
+                "word": page.title,
+                "language": entry.language,
+                "pos": entry.pos,
+                "definitions": definitions,
+                "translations": translations,
+            });
+            writeln!(out, "{record}")?;
+            count += 1;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote {count} entries");
+    Ok(())
+}
+
+/// The lines of a page that belong to one part of speech in one language.
+struct Entry<'a> {
+    language: &'a str,
+    pos: &'a str,
+    /// Top-level definition lines, leaving out examples, quotations and subsenses.
+    definitions: Vec<&'a str>,
+    /// Lines of the entry's translations section.
+    translations: Vec<&'a str>,
+}
+
+fn entries(text: &str) -> Vec<Entry<'_>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut language = None;
+    // The level of the part of speech heading the current entry is under, if any.
+    let mut pos_level = None;
+    let mut in_translations = false;
+
+    for line in text.lines() {
+        if let Some((level, name)) = plain::heading(line) {
+            in_translations = false;
+            if level == 2 {
+                language = Some(name);
+                pos_level = None;
+            } else if let (Some(language), true) = (language, PARTS_OF_SPEECH.contains(&name)) {
+                pos_level = Some(level);
+                entries.push(Entry {
+                    language,
+                    pos: name,
+                    definitions: Vec::new(),
+                    translations: Vec::new(),
+                });
+            } else if pos_level.is_some_and(|pos_level| level > pos_level) {
+                in_translations = name == "Translations";
+            } else {
+                pos_level = None;
+            }
+            continue;
+        }
+
+        let (Some(_), Some(entry)) = (pos_level, entries.last_mut()) else {
+            continue;
+        };
+        if in_translations {
+            entry.translations.push(line);
+        } else if let Some(definition) = line.strip_prefix('#') {
+            if !definition.starts_with(['#', ':', '*']) {
+                entry.definitions.push(definition.trim());
+            }
+        }
+    }
+    entries
+}